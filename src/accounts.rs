@@ -0,0 +1,467 @@
+//! Account fixtures in the `solana account --output json` format
+//!
+//! A fixture is a JSON file such as
+//!
+//! ```json
+//! {
+//!   "pubkey": "...",
+//!   "account": {
+//!     "lamports": 1461600,
+//!     "data": ["AQAAAA==", "base64"],
+//!     "owner": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+//!     "executable": false,
+//!     "rentEpoch": 0
+//!   }
+//! }
+//! ```
+//!
+//! The account fields may also sit next to `pubkey` at the top level. Init
+//! writes the decoded data to a `.bin` file next to the fixture and adds the
+//! account to the generated test with `include_bytes!`. Fetch writes the
+//! same format.
+
+use serde::Deserialize;
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, ErrorKind};
+use crate::utility;
+
+/// Loader of upgradeable programs
+pub const BPF_LOADER_UPGRADEABLE: &str = "BPFLoaderUpgradeab1e11111111111111111111111";
+
+/// Loaders keeping the program ELF in the program account itself
+pub const BPF_LOADERS: &[&str] = &[
+    "BPFLoader1111111111111111111111111111111111",
+    "BPFLoader2111111111111111111111111111111111",
+];
+
+/// `UpgradeableLoaderState` tags, serialized as little endian `u32`
+const UPGRADEABLE_PROGRAM_TAG: u32 = 2;
+const UPGRADEABLE_PROGRAM_DATA_TAG: u32 = 3;
+
+/// Size of the `ProgramData` header - tag, slot and optional authority
+const PROGRAM_DATA_METADATA_LEN: usize = 4 + 8 + 1 + 32;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawAccount {
+    lamports: u64,
+    data: RawData,
+    owner: String,
+    executable: bool,
+    #[serde(default)]
+    rent_epoch: u64,
+}
+
+/// Account data, `["<data>", "<encoding>"]` or a plain base64 string
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum RawData {
+    Encoded(String, String),
+    Base64(String),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawFixture {
+    Nested {
+        pubkey: String,
+        account: RawAccount,
+    },
+    Flat {
+        pubkey: String,
+        #[serde(flatten)]
+        account: RawAccount,
+    },
+}
+
+/// Decoded account fixture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountFixture {
+    /// JSON file the fixture was read from
+    pub path: PathBuf,
+    pub pubkey: String,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: String,
+    pub executable: bool,
+    pub rent_epoch: u64,
+}
+
+impl AccountFixture {
+    /// Parse a fixture file.
+    pub fn load(path: &Path) -> Result<AccountFixture, Error> {
+        let fail = |message: String| -> Error {
+            ErrorKind::Fixture
+                .context(format!("{}: {}", path.display(), message))
+                .into()
+        };
+        let contents = fs::read_to_string(path).map_err(|e| fail(e.to_string()))?;
+        let (pubkey, account) = match serde_json::from_str(&contents) {
+            Ok(RawFixture::Nested { pubkey, account }) | Ok(RawFixture::Flat { pubkey, account }) => {
+                (pubkey, account)
+            }
+            Err(_) => {
+                return Err(fail(String::from(
+                    "expected pubkey, lamports, data, owner and executable as written by `solana account --output json`",
+                )))
+            }
+        };
+        for (name, key) in [("pubkey", &pubkey), ("owner", &account.owner)] {
+            if !utility::is_pubkey(key) {
+                return Err(fail(format!("{} {} is not a base58 public key", name, key)));
+            }
+        }
+        let data = match &account.data {
+            RawData::Encoded(data, encoding) if encoding == "base64" => base64::decode(data).ok(),
+            RawData::Encoded(data, encoding) if encoding == "base58" => {
+                bs58::decode(data).into_vec().ok()
+            }
+            RawData::Encoded(_, encoding) => {
+                return Err(fail(format!(
+                    "unsupported data encoding {}, dump the account with `--output json`",
+                    encoding
+                )))
+            }
+            RawData::Base64(data) => base64::decode(data).ok(),
+        }
+        .ok_or_else(|| fail(String::from("account data cannot be decoded")))?;
+
+        Ok(AccountFixture {
+            path: path.to_path_buf(),
+            pubkey,
+            lamports: account.lamports,
+            data,
+            owner: account.owner,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+        })
+    }
+
+    /// Write the fixture in the `solana account --output json` format.
+    pub fn save(&self) -> Result<(), Error> {
+        let fixture = json!({
+            "pubkey": self.pubkey,
+            "account": {
+                "lamports": self.lamports,
+                "data": [base64::encode(&self.data), "base64"],
+                "owner": self.owner,
+                "executable": self.executable,
+                "rentEpoch": self.rent_epoch,
+                "space": self.data.len(),
+            },
+        });
+        let contents =
+            serde_json::to_string_pretty(&fixture).map_err(|e| ErrorKind::Fixture.context(e))?;
+        fs::write(&self.path, contents + "\n").map_err(|e| {
+            ErrorKind::WriteFailed
+                .context(format!("{}: {}", self.path.display(), e))
+                .into()
+        })
+    }
+
+    /// `ProgramData` address of an upgradeable program account.
+    pub fn program_data_address(&self) -> Option<String> {
+        if self.owner != BPF_LOADER_UPGRADEABLE
+            || self.data.len() < 36
+            || upgradeable_tag(&self.data) != Some(UPGRADEABLE_PROGRAM_TAG)
+        {
+            return None;
+        }
+        Some(bs58::encode(&self.data[4..36]).into_string())
+    }
+
+    /// Program ELF held by a `ProgramData` account or a program account of
+    /// the older loaders.
+    pub fn program_elf(&self) -> Option<&[u8]> {
+        if self.owner == BPF_LOADER_UPGRADEABLE {
+            if self.data.len() > PROGRAM_DATA_METADATA_LEN
+                && upgradeable_tag(&self.data) == Some(UPGRADEABLE_PROGRAM_DATA_TAG)
+            {
+                return Some(&self.data[PROGRAM_DATA_METADATA_LEN..]);
+            }
+            return None;
+        }
+        if self.executable && BPF_LOADERS.contains(&self.owner.as_str()) {
+            return Some(&self.data);
+        }
+        None
+    }
+
+    /// File holding the decoded data, next to the fixture.
+    pub fn data_path(&self) -> PathBuf {
+        self.path.with_extension("bin")
+    }
+
+    /// `program.add_account(...)` call adding the account to a
+    /// `ProgramTest` named `program` in a test file in `test_dir`.
+    fn render(&self, test_dir: &Path) -> String {
+        let data = if self.data.is_empty() {
+            String::from("Vec::new()")
+        } else {
+            let include = utility::relative_path(
+                &utility::absolute_path(test_dir),
+                &utility::absolute_path(&self.data_path()),
+            );
+            format!(
+                "include_bytes!(\"{}\").to_vec()",
+                include.to_string_lossy().replace('\\', "/")
+            )
+        };
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        format!(
+            r#"    // Account fixture {name}
+    program.add_account(
+        Pubkey::from_str("{pubkey}").unwrap(),
+        Account {{
+            lamports: {lamports},
+            data: {data},
+            owner: Pubkey::from_str("{owner}").unwrap(),
+            executable: {executable},
+            rent_epoch: {rent_epoch},
+        }},
+    );
+
+"#,
+            name = name,
+            pubkey = self.pubkey,
+            lamports = self.lamports,
+            data = data,
+            owner = self.owner,
+            executable = self.executable,
+            rent_epoch = self.rent_epoch,
+        )
+    }
+}
+
+/// Load the `*.json` fixtures of a directory, sorted by file name.
+pub fn load_dir(dir: &Path) -> Result<Vec<AccountFixture>, Error> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| ErrorKind::Fixture.context(format!("{}: {}", dir.display(), e)))?;
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| AccountFixture::load(path))
+        .collect()
+}
+
+/// Account set up for the `{{accounts}}` template variable.
+pub fn render(fixtures: &[AccountFixture], test_file_path: &Path) -> String {
+    let test_dir = test_file_path.parent().unwrap_or_else(|| Path::new(""));
+    fixtures
+        .iter()
+        .map(|fixture| fixture.render(test_dir))
+        .collect()
+}
+
+fn upgradeable_tag(data: &[u8]) -> Option<u32> {
+    let tag = data.get(..4)?;
+    Some(u32::from_le_bytes([tag[0], tag[1], tag[2], tag[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBKEY: &str = "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T";
+    const OWNER: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+    fn write(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn fixture(path: PathBuf, owner: &str, data: Vec<u8>) -> AccountFixture {
+        AccountFixture {
+            path,
+            pubkey: String::from(PUBKEY),
+            lamports: 1,
+            data,
+            owner: String::from(owner),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn loads_nested_fixture() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(
+            dir.path(),
+            "mint.json",
+            &format!(
+                r#"{{"pubkey": "{}", "account": {{"lamports": 1461600, "data": ["AQAAAA==", "base64"],
+                "owner": "{}", "executable": false, "rentEpoch": 361, "space": 4}}}}"#,
+                PUBKEY, OWNER
+            ),
+        );
+        let fixture = AccountFixture::load(&path).unwrap();
+        assert_eq!(fixture.pubkey, PUBKEY);
+        assert_eq!(fixture.lamports, 1461600);
+        assert_eq!(fixture.data, [1, 0, 0, 0]);
+        assert_eq!(fixture.owner, OWNER);
+        assert!(!fixture.executable);
+        assert_eq!(fixture.rent_epoch, 361);
+        assert_eq!(fixture.data_path(), dir.path().join("mint.bin"));
+    }
+
+    #[test]
+    fn loads_flat_fixture_with_plain_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(
+            dir.path(),
+            "flat.json",
+            &format!(
+                r#"{{"pubkey": "{}", "lamports": 5, "data": "AgI=", "owner": "{}", "executable": true}}"#,
+                PUBKEY, OWNER
+            ),
+        );
+        let fixture = AccountFixture::load(&path).unwrap();
+        assert_eq!(fixture.lamports, 5);
+        assert_eq!(fixture.data, [2, 2]);
+        assert!(fixture.executable);
+        assert_eq!(fixture.rent_epoch, 0);
+    }
+
+    #[test]
+    fn decodes_base58_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(
+            dir.path(),
+            "base58.json",
+            &format!(
+                r#"{{"pubkey": "{}", "account": {{"lamports": 1, "data": ["{}", "base58"],
+                "owner": "{}", "executable": false}}}}"#,
+                PUBKEY,
+                bs58::encode([7, 8, 9]).into_string(),
+                OWNER
+            ),
+        );
+        assert_eq!(AccountFixture::load(&path).unwrap().data, [7, 8, 9]);
+    }
+
+    #[test]
+    fn rejects_invalid_fixtures() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = |name: &str, contents: String| {
+            let path = write(dir.path(), name, &contents);
+            let error = AccountFixture::load(&path).unwrap_err();
+            assert_eq!(error.exit_code(), ErrorKind::Fixture.exit_code());
+            error.to_string()
+        };
+
+        let message = error(
+            "zstd.json",
+            format!(
+                r#"{{"pubkey": "{}", "account": {{"lamports": 1, "data": ["KLUv/Q==", "base64+zstd"],
+                "owner": "{}", "executable": false}}}}"#,
+                PUBKEY, OWNER
+            ),
+        );
+        assert!(message.contains("unsupported data encoding base64+zstd"));
+
+        let message = error(
+            "owner.json",
+            format!(
+                r#"{{"pubkey": "{}", "lamports": 1, "data": "", "owner": "not-a-key", "executable": false}}"#,
+                PUBKEY
+            ),
+        );
+        assert!(message.contains("owner not-a-key is not a base58 public key"));
+
+        let message = error(
+            "data.json",
+            format!(
+                r#"{{"pubkey": "{}", "lamports": 1, "data": "!!", "owner": "{}", "executable": false}}"#,
+                PUBKEY, OWNER
+            ),
+        );
+        assert!(message.contains("account data cannot be decoded"));
+
+        let message = error("missing.json", format!(r#"{{"pubkey": "{}"}}"#, PUBKEY));
+        assert!(message.contains("solana account --output json"));
+    }
+
+    #[test]
+    fn save_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut saved = fixture(dir.path().join("saved.json"), OWNER, vec![0, 1, 2, 255]);
+        saved.executable = true;
+        saved.rent_epoch = 18446744073709551615;
+        saved.save().unwrap();
+        assert_eq!(AccountFixture::load(&saved.path).unwrap(), saved);
+    }
+
+    #[test]
+    fn program_accounts() {
+        let program_data = [9u8; 32];
+        let mut program = vec![2, 0, 0, 0];
+        program.extend(program_data);
+        let program = fixture(PathBuf::from("p.json"), BPF_LOADER_UPGRADEABLE, program);
+        assert_eq!(
+            program.program_data_address(),
+            Some(bs58::encode(program_data).into_string())
+        );
+        assert_eq!(program.program_elf(), None);
+
+        let mut data = vec![3, 0, 0, 0];
+        data.extend([0; PROGRAM_DATA_METADATA_LEN - 4]);
+        data.extend(b"\x7fELF");
+        let program_data = fixture(PathBuf::from("d.json"), BPF_LOADER_UPGRADEABLE, data);
+        assert_eq!(program_data.program_data_address(), None);
+        assert_eq!(program_data.program_elf(), Some(&b"\x7fELF"[..]));
+
+        let mut legacy = fixture(PathBuf::from("l.json"), BPF_LOADERS[1], b"\x7fELF".to_vec());
+        assert_eq!(legacy.program_elf(), None);
+        legacy.executable = true;
+        assert_eq!(legacy.program_elf(), Some(&b"\x7fELF"[..]));
+
+        let token = fixture(PathBuf::from("t.json"), OWNER, vec![2; 36]);
+        assert_eq!(token.program_data_address(), None);
+    }
+
+    #[test]
+    fn load_dir_reads_json_files_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.json", "a.json"] {
+            fixture(dir.path().join(name), OWNER, Vec::new())
+                .save()
+                .unwrap();
+        }
+        write(dir.path(), "a.bin", "");
+        let names: Vec<PathBuf> = load_dir(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|fixture| fixture.path.strip_prefix(dir.path()).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(names, [PathBuf::from("a.json"), PathBuf::from("b.json")]);
+        assert!(load_dir(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn render_includes_data_relative_to_the_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixtures = [
+            fixture(dir.path().join("fixtures/mint.json"), OWNER, vec![1]),
+            fixture(dir.path().join("fixtures/empty.json"), OWNER, Vec::new()),
+        ];
+        let rendered = render(&fixtures, &dir.path().join("tests/test.rs"));
+        assert!(rendered.contains("    // Account fixture mint.json\n"));
+        assert!(rendered.contains("data: include_bytes!(\"../fixtures/mint.bin\").to_vec(),"));
+        assert!(rendered.contains("data: Vec::new(),"));
+        assert!(rendered.contains(&format!("Pubkey::from_str(\"{}\").unwrap(),", PUBKEY)));
+        assert!(rendered.contains(&format!("owner: Pubkey::from_str(\"{}\").unwrap(),", OWNER)));
+    }
+}
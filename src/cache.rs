@@ -0,0 +1,315 @@
+//! Local cache of vendored framework archives
+//!
+//! Archives are unpacked to `<cache dir>/solana-test/frameworks/<name>-<hash>`
+//! where `<hash>` is the start of the archive's SHA-256, so init can use the
+//! framework as a `path` dependency without network access.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+use crate::error::{Error, ErrorKind};
+
+/// Number of hash characters used in entry names
+const ENTRY_HASH_LEN: usize = 12;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Directory holding the vendored frameworks.
+pub fn cache_dir() -> Result<PathBuf, Error> {
+    let cache_dir = dirs::cache_dir().ok_or_else(|| {
+        ErrorKind::Cache.context("cannot determine the cache directory for this platform")
+    })?;
+    Ok(cache_dir.join("solana-test").join("frameworks"))
+}
+
+/// Download an archive into memory.
+pub async fn download(url: &str) -> Result<Vec<u8>, Error> {
+    let response = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| ErrorKind::Download.context(e))?;
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| ErrorKind::Download.context(e))?;
+    Ok(bytes.to_vec())
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Verify and unpack a zip archive into the cache, returning the entry.
+/// An entry unpacked earlier from the same archive is reused.
+pub fn vendor(
+    archive: &[u8],
+    name: &str,
+    expected_sha256: Option<&str>,
+) -> Result<CacheEntry, Error> {
+    vendor_in(&cache_dir()?, archive, name, expected_sha256)
+}
+
+fn vendor_in(
+    cache_dir: &Path,
+    archive: &[u8],
+    name: &str,
+    expected_sha256: Option<&str>,
+) -> Result<CacheEntry, Error> {
+    let sha256 = sha256_hex(archive);
+    if let Some(expected) = expected_sha256 {
+        if !expected.eq_ignore_ascii_case(&sha256) {
+            return Err(ErrorKind::Checksum
+                .context(format!("expected {}, got {}", expected, sha256))
+                .into());
+        }
+    }
+
+    let entry_name = format!("{}-{}", name, &sha256[..ENTRY_HASH_LEN]);
+    let entry_path = cache_dir.join(&entry_name);
+    if !entry_path.exists() {
+        fs::create_dir_all(cache_dir)?;
+        // Unpack next to the final location so a failed extraction never
+        // leaves a partial entry behind
+        let staging = tempfile::tempdir_in(cache_dir)?;
+        unpack(archive, staging.path())?;
+        fs::rename(staging.into_path(), &entry_path)?;
+    }
+    entry(&entry_path)
+}
+
+/// Extract a zip archive, dropping the single top-level directory that
+/// GitHub archives wrap their contents in.
+fn unpack(archive: &[u8], destination: &Path) -> Result<(), Error> {
+    let mut zip =
+        ZipArchive::new(Cursor::new(archive)).map_err(|e| ErrorKind::Archive.context(e))?;
+
+    let names: Vec<PathBuf> = (0..zip.len())
+        .filter_map(|index| zip.by_index(index).ok()?.enclosed_name().map(PathBuf::from))
+        .collect();
+    let prefix = names
+        .first()
+        .and_then(|name| name.components().next())
+        .map(|first| PathBuf::from(first.as_os_str()))
+        .filter(|first| {
+            names.iter().all(|name| name.starts_with(first))
+                && names.iter().any(|name| name != first)
+        });
+
+    for index in 0..zip.len() {
+        let mut file = zip
+            .by_index(index)
+            .map_err(|e| ErrorKind::Archive.context(e))?;
+        let name = match file.enclosed_name() {
+            Some(name) => name.to_path_buf(),
+            None => continue,
+        };
+        let relative = match &prefix {
+            Some(prefix) => name.strip_prefix(prefix).unwrap_or(&name).to_path_buf(),
+            None => name,
+        };
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+        let path = destination.join(relative);
+        if file.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut file, &mut fs::File::create(&path)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// All vendored frameworks.
+pub fn list() -> Result<Vec<CacheEntry>, Error> {
+    list_in(&cache_dir()?)
+}
+
+fn list_in(cache_dir: &Path) -> Result<Vec<CacheEntry>, Error> {
+    if !cache_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    for dir_entry in fs::read_dir(cache_dir)? {
+        let path = dir_entry?.path();
+        if path.is_dir() {
+            entries.push(entry(&path)?);
+        }
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// Find an entry by its full name or by the name it was vendored under, e.g.
+/// the framework name.
+pub fn find(name: &str) -> Result<CacheEntry, Error> {
+    find_in(&cache_dir()?, name)
+}
+
+fn find_in(cache_dir: &Path, name: &str) -> Result<CacheEntry, Error> {
+    let entries = list_in(cache_dir)?;
+    let mut matching = entries
+        .iter()
+        .filter(|entry| entry.name == name || vendored_name(&entry.name) == Some(name));
+    match (matching.next(), matching.next()) {
+        (Some(entry), None) => Ok(entry.clone()),
+        (Some(_), Some(_)) => Err(ErrorKind::Cache
+            .context(format!("{} matches several cache entries", name))
+            .into()),
+        (None, _) => Err(ErrorKind::Cache
+            .context(format!(
+                "{} is not vendored, run `solana-test vendor` first",
+                name
+            ))
+            .into()),
+    }
+}
+
+/// Remove one entry, or all of them when `name` is `None`. Returns the
+/// removed entries.
+pub fn clean(name: Option<&str>) -> Result<Vec<CacheEntry>, Error> {
+    clean_in(&cache_dir()?, name)
+}
+
+fn clean_in(cache_dir: &Path, name: Option<&str>) -> Result<Vec<CacheEntry>, Error> {
+    let entries = match name {
+        Some(name) => vec![find_in(cache_dir, name)?],
+        None => list_in(cache_dir)?,
+    };
+    for entry in &entries {
+        fs::remove_dir_all(&entry.path)?;
+    }
+    Ok(entries)
+}
+
+/// Name an entry was vendored under, i.e. without the `-<hash>` suffix.
+fn vendored_name(entry_name: &str) -> Option<&str> {
+    let (name, hash) = entry_name.rsplit_once('-')?;
+    let is_hash = hash.len() == ENTRY_HASH_LEN && hash.chars().all(|c| c.is_ascii_hexdigit());
+    is_hash.then_some(name)
+}
+
+fn entry(path: &Path) -> Result<CacheEntry, Error> {
+    Ok(CacheEntry {
+        name: path.file_name().unwrap().to_string_lossy().into_owned(),
+        path: path.to_path_buf(),
+        size: dir_size(path)?,
+    })
+}
+
+fn dir_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for dir_entry in fs::read_dir(path)? {
+        let dir_entry = dir_entry?;
+        let metadata = dir_entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&dir_entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    /// Zip archive laid out like a GitHub branch archive
+    fn archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.add_directory("framework-main/", FileOptions::default())
+            .unwrap();
+        for (name, contents) in files {
+            zip.start_file(format!("framework-main/{}", name), FileOptions::default())
+                .unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn vendors_archive_without_top_level_directory() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let archive = archive(&[("Cargo.toml", "[package]\n"), ("src/lib.rs", "")]);
+        let entry = vendor_in(cache_dir.path(), &archive, "framework", None).unwrap();
+        assert_eq!(
+            entry.name,
+            format!("framework-{}", &sha256_hex(&archive)[..ENTRY_HASH_LEN])
+        );
+        assert_eq!(
+            fs::read_to_string(entry.path.join("Cargo.toml")).unwrap(),
+            "[package]\n"
+        );
+        assert!(entry.path.join("src").join("lib.rs").exists());
+        assert_eq!(entry.size, 10);
+    }
+
+    #[test]
+    fn rejects_checksum_mismatch() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let archive = archive(&[("Cargo.toml", "")]);
+        let e = vendor_in(cache_dir.path(), &archive, "framework", Some("00")).unwrap_err();
+        assert_eq!(*e.kind(), ErrorKind::Checksum);
+        assert!(list_in(cache_dir.path()).unwrap().is_empty());
+
+        let sha256 = sha256_hex(&archive).to_uppercase();
+        assert!(vendor_in(cache_dir.path(), &archive, "framework", Some(&sha256)).is_ok());
+    }
+
+    #[test]
+    fn finds_entry_by_exact_name() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let foo_1 = vendor_in(cache_dir.path(), &archive(&[("a", "1")]), "foo-1", None).unwrap();
+        let foo_10 = vendor_in(cache_dir.path(), &archive(&[("a", "10")]), "foo-10", None).unwrap();
+
+        assert_eq!(find_in(cache_dir.path(), "foo-1").unwrap().path, foo_1.path);
+        assert_eq!(
+            find_in(cache_dir.path(), "foo-10").unwrap().path,
+            foo_10.path
+        );
+        assert_eq!(
+            find_in(cache_dir.path(), &foo_10.name).unwrap().path,
+            foo_10.path
+        );
+        let e = find_in(cache_dir.path(), "foo").unwrap_err();
+        assert_eq!(*e.kind(), ErrorKind::Cache);
+    }
+
+    #[test]
+    fn reports_ambiguous_name() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        vendor_in(cache_dir.path(), &archive(&[("a", "1")]), "foo", None).unwrap();
+        vendor_in(cache_dir.path(), &archive(&[("a", "2")]), "foo", None).unwrap();
+        assert!(find_in(cache_dir.path(), "foo").is_err());
+
+        let removed = clean_in(cache_dir.path(), None).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(list_in(cache_dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn strips_hash_from_entry_name() {
+        assert_eq!(vendored_name("foo-10-0123456789ab"), Some("foo-10"));
+        assert_eq!(vendored_name("foo-10"), None);
+        assert_eq!(vendored_name("foo-0123456789xy"), None);
+    }
+}
@@ -2,31 +2,103 @@
 //!
 //! This is where you specify the subcommands of your application.
 //!
-//! The default application comes with two subcommands:
-//!
-//! - `start`: launches the application
+//! - `init`: add the framework to a project and generate its tests
+//! - `deinit`: revert the changes made by init
+//! - `migrate`: rename the test-bpf feature to test-sbf
+//! - `fetch`: download accounts and programs into account fixtures
+//! - `run`: build and run the generated tests
+//! - `report`: convert saved test output into JUnit XML or JSON reports
+//! - `doctor`: check the local Solana toolchain
+//! - `vendor`, `cache`: download and manage framework archives
+//! - `templates`: browse test templates
+//! - `config`: inspect the configuration
 //! - `--version`: print application version
 //!
 //! See the `impl Configurable` below for how to specify the path to the
 //! application's configuration file.
 
+mod cache;
+mod config;
+mod deinit;
+mod doctor;
+mod fetch;
 mod init;
+mod migrate;
+mod report;
+mod run;
+mod templates;
+mod vendor;
 
-use self::init::InitCmd;
-use crate::config::SolanaTestConfig;
+use self::{
+    cache::CacheCmd, config::ConfigCmd, deinit::DeinitCmd, doctor::DoctorCmd, fetch::FetchCmd,
+    init::InitCmd, migrate::MigrateCmd, report::ReportCmd, run::RunCmd, templates::TemplatesCmd,
+    vendor::VendorCmd,
+};
+use crate::config::{layers, SolanaTestConfig};
+use crate::error::Error;
+use crate::report::{set_output_format, OutputFormat, Report};
 use abscissa_core::{config::Override, Command, Configurable, FrameworkError, Runnable};
 use clap::Parser;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// SolanaTest Configuration Filename
 pub const CONFIG_FILE: &str = "solana-test.toml";
 
 /// SolanaTest Subcommands
 /// Subcommands need to be listed in an enum.
+// Parsed once per run, so the size of `InitCmd` does not matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Command, Debug, Parser, Runnable)]
 pub enum SolanaTestCmd {
-    /// The `start` subcommand
+    /// Add the framework to a project and generate its tests
     Init(InitCmd),
+
+    /// Revert the changes made by init
+    Deinit(DeinitCmd),
+
+    /// Rename the test-bpf feature to test-sbf
+    Migrate(MigrateCmd),
+
+    /// Download accounts and programs into account fixtures
+    Fetch(FetchCmd),
+
+    /// Build and run the generated tests
+    Run(RunCmd),
+
+    /// Convert saved test output into JUnit XML or JSON reports
+    Report(ReportCmd),
+
+    /// Check the local Solana toolchain
+    Doctor(DoctorCmd),
+
+    /// Download a framework archive into the local cache
+    Vendor(VendorCmd),
+
+    /// Manage vendored frameworks
+    #[clap(subcommand)]
+    Cache(CacheCmd),
+
+    /// Browse test templates
+    #[clap(subcommand)]
+    Templates(TemplatesCmd),
+
+    /// Inspect the configuration
+    #[clap(subcommand)]
+    Config(ConfigCmd),
+}
+
+impl SolanaTestCmd {
+    /// Project path given on the command line.
+    fn path(&self) -> Option<&Path> {
+        match self {
+            SolanaTestCmd::Init(cmd) => cmd.path.as_deref(),
+            SolanaTestCmd::Deinit(cmd) => cmd.path.as_deref(),
+            SolanaTestCmd::Migrate(cmd) => cmd.path.as_deref(),
+            SolanaTestCmd::Run(cmd) => cmd.path.as_deref(),
+            SolanaTestCmd::Doctor(cmd) => cmd.path.as_deref(),
+            _ => None,
+        }
+    }
 }
 
 /// Entry point for the application. It needs to be a struct to allow using subcommands!
@@ -43,10 +115,15 @@ pub struct EntryPoint {
     /// Use the specified config file
     #[clap(short, long)]
     pub config: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value_t, global = true)]
+    pub output: OutputFormat,
 }
 
 impl Runnable for EntryPoint {
     fn run(&self) {
+        set_output_format(self.output);
         self.cmd.run()
     }
 }
@@ -55,34 +132,49 @@ impl Runnable for EntryPoint {
 impl Configurable<SolanaTestConfig> for EntryPoint {
     /// Location of the configuration file
     fn config_path(&self) -> Option<PathBuf> {
-        // Check if the config file exists, and if it does not, ignore it.
-        // If you'd like for a missing configuration file to be a hard error
-        // instead, always return `Some(CONFIG_FILE)` here.
-        let filename = self
-            .config
-            .as_ref()
-            .map(PathBuf::from)
-            .unwrap_or_else(|| CONFIG_FILE.into());
-
-        if filename.exists() {
-            Some(filename)
-        } else {
-            None
-        }
+        // The config files are layered in `process_config` instead of
+        // letting abscissa load a single one
+        None
     }
 
     /// Apply changes to the config after it's been loaded, e.g. overriding
     /// values in a config file using command-line options.
-    ///
-    /// This can be safely deleted if you don't want to override config
-    /// settings from command-line options.
-    fn process_config(&self, config: SolanaTestConfig) -> Result<SolanaTestConfig, FrameworkError> {
+    fn process_config(&self, _: SolanaTestConfig) -> Result<SolanaTestConfig, FrameworkError> {
+        // Set before loading so config errors are reported in this format
+        set_output_format(self.output);
+        let mut config = match self.load_config() {
+            Ok(config) => config,
+            // Let validate report the problems with line numbers
+            Err(_) if matches!(self.cmd, SolanaTestCmd::Config(ConfigCmd::Validate(_))) => {
+                SolanaTestConfig::default()
+            }
+            Err(e) => Report::new("config").fail(&e),
+        };
+        config.project_config = self.config.as_ref().map(PathBuf::from);
         match &self.cmd {
-            SolanaTestCmd::Init(cmd) => cmd.override_config(config),
-            //
-            // If you don't need special overrides for some
-            // subcommands, you can just use a catch all
-            // _ => Ok(config),
+            SolanaTestCmd::Init(cmd) => {
+                let before = config.clone();
+                let mut config = cmd.override_config(config)?;
+                layers::mark_cli_overrides(&mut config, &before);
+                Ok(config)
+            }
+            _ => Ok(config),
         }
     }
 }
+
+impl EntryPoint {
+    /// Merge the global config, the metadata in the project's `Cargo.toml`,
+    /// the project config given with `--config` or found in the current
+    /// directory, and the environment.
+    fn load_config(&self) -> Result<SolanaTestConfig, Error> {
+        let project_config = layers::project_config_path(self.config.as_deref().map(Path::new))?;
+        // The project path decides which manifest is read
+        let config = layers::load(project_config.as_deref(), None)?;
+        let project_path = self.cmd.path().unwrap_or(&config.init.path);
+        layers::load(
+            project_config.as_deref(),
+            Some(&project_path.join("Cargo.toml")),
+        )
+    }
+}
@@ -0,0 +1,75 @@
+//! `cache` subcommand - manage vendored frameworks
+
+use crate::prelude::*;
+
+use crate::cache;
+use crate::report::{self, Report};
+use abscissa_core::{Command, Runnable};
+use clap::Parser;
+
+/// `cache` subcommand
+#[derive(Command, Debug, Parser, Runnable)]
+pub enum CacheCmd {
+    /// List vendored frameworks
+    List(CacheListCmd),
+
+    /// Remove vendored frameworks
+    Clean(CacheCleanCmd),
+}
+
+/// `cache list` subcommand
+#[derive(Command, Debug, Parser)]
+pub struct CacheListCmd {}
+
+impl Runnable for CacheListCmd {
+    fn run(&self) {
+        let mut report = Report::new("cache list");
+        match cache::list() {
+            Ok(entries) => {
+                if entries.is_empty() {
+                    status_info!("Cache", "No vendored frameworks");
+                }
+                if !report::is_json() {
+                    for entry in &entries {
+                        println!(
+                            "{}\t{} KiB\t{}",
+                            entry.name,
+                            entry.size / 1024,
+                            entry.path.display()
+                        );
+                    }
+                }
+                report.insert("entries", entries);
+                report.finish();
+            }
+            Err(e) => report.fail(&e),
+        }
+    }
+}
+
+/// `cache clean` subcommand
+#[derive(Command, Debug, Parser)]
+pub struct CacheCleanCmd {
+    /// Entry to remove
+    #[clap(help = "Cache entry to remove. Removes all entries when omitted.")]
+    name: Option<String>,
+}
+
+impl Runnable for CacheCleanCmd {
+    fn run(&self) {
+        let mut report = Report::new("cache clean");
+        match cache::clean(self.name.as_deref()) {
+            Ok(entries) => {
+                for entry in &entries {
+                    status_ok!("Removed", "{}", entry.name);
+                }
+                if entries.is_empty() {
+                    status_info!("Cache", "Nothing to remove");
+                }
+                report.insert("removed", entries);
+                report.finish();
+            }
+            Err(e) => report.fail(&e),
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! `config` subcommand - inspect, write and validate the configuration
+
+use crate::prelude::*;
+
+use crate::commands::CONFIG_FILE;
+use crate::config::layers::{self, Origin};
+use crate::config::schema::{self, Issue};
+use crate::error::{Error, ErrorKind};
+use crate::report::{self, Report};
+use abscissa_core::{Command, Runnable};
+use clap::Parser;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `config` subcommand
+#[derive(Command, Debug, Parser, Runnable)]
+pub enum ConfigCmd {
+    /// Print the effective configuration
+    Show(ConfigShowCmd),
+
+    /// Write a commented solana-test.toml with the effective configuration
+    Init(ConfigInitCmd),
+
+    /// Check config files for unknown keys, wrong types and missing paths
+    Validate(ConfigValidateCmd),
+}
+
+/// `config show` subcommand
+#[derive(Command, Debug, Parser)]
+pub struct ConfigShowCmd {
+    /// Print where each value came from
+    #[clap(
+        long = "origin",
+        help = "Print the file, environment variable or default each value came from."
+    )]
+    origin: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Setting {
+    key: String,
+    value: serde_json::Value,
+    origin: String,
+}
+
+impl Runnable for ConfigShowCmd {
+    fn run(&self) {
+        let config = APP.config();
+        let mut report = Report::new("config show");
+        let settings: Vec<Setting> = layers::effective_values(&config)
+            .into_iter()
+            .map(|(key, value)| Setting {
+                origin: config
+                    .origins
+                    .get(&key)
+                    .unwrap_or(&Origin::Default)
+                    .to_string(),
+                key,
+                value,
+            })
+            .collect();
+        if !report::is_json() {
+            for setting in &settings {
+                if self.origin {
+                    println!("{} = {}  # {}", setting.key, setting.value, setting.origin);
+                } else {
+                    println!("{} = {}", setting.key, setting.value);
+                }
+            }
+        }
+        report.insert("settings", &settings);
+        report.finish();
+    }
+}
+
+/// `config init` subcommand
+#[derive(Command, Debug, Parser)]
+pub struct ConfigInitCmd {
+    /// File to write
+    #[clap(help = "File to write. Defaults to solana-test.toml in the current directory.")]
+    file: Option<PathBuf>,
+
+    /// Write the global config
+    #[clap(
+        long = "global",
+        conflicts_with = "file",
+        help = "Write the global config in the platform config directory."
+    )]
+    global: bool,
+
+    /// Overwrite an existing file
+    #[clap(long = "force", help = "Overwrite an existing config file.")]
+    force: bool,
+}
+
+impl Runnable for ConfigInitCmd {
+    fn run(&self) {
+        let mut report = Report::new("config init");
+        match self.write() {
+            Ok(path) => {
+                status_ok!("Written", "{}", path.display());
+                report.insert("path", path);
+                report.finish();
+            }
+            Err(e) => report.fail(&e),
+        }
+    }
+}
+
+impl ConfigInitCmd {
+    fn write(&self) -> Result<PathBuf, Error> {
+        let path = match (&self.file, self.global) {
+            (Some(file), _) => file.clone(),
+            (None, true) => layers::global_config_path().ok_or_else(|| {
+                ErrorKind::Config.context("cannot determine the platform config directory")
+            })?,
+            (None, false) => PathBuf::from(CONFIG_FILE),
+        };
+        if path.exists() && !self.force {
+            return Err(ErrorKind::Config
+                .context(format!(
+                    "{} already exists, use --force to overwrite it",
+                    path.display()
+                ))
+                .into());
+        }
+        let contents = schema::render(&APP.config());
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, contents)
+            .map_err(|e| ErrorKind::WriteFailed.context(format!("{}: {}", path.display(), e)))?;
+        Ok(path)
+    }
+}
+
+/// `config validate` subcommand
+#[derive(Command, Debug, Parser)]
+pub struct ConfigValidateCmd {
+    /// Files to check
+    #[clap(
+        help = "Config files to check. Defaults to the global config and the project config, given with --config or solana-test.toml in the current directory."
+    )]
+    files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct ValidatedFile {
+    path: PathBuf,
+    issues: Vec<Issue>,
+}
+
+impl Runnable for ConfigValidateCmd {
+    fn run(&self) {
+        let mut report = Report::new("config validate");
+        let files: Vec<PathBuf> = if self.files.is_empty() {
+            default_files(APP.config().project_config.as_deref())
+        } else {
+            self.files.clone()
+        };
+        if files.is_empty() {
+            status_info!("Skipped", "no config file found");
+        }
+
+        let mut validated = Vec::new();
+        for path in files {
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(e) => {
+                    report.insert("files", &validated);
+                    report.fail(
+                        &ErrorKind::Config
+                            .context(format!("{}: {}", path.display(), e))
+                            .into(),
+                    )
+                }
+            };
+            let dir = path.parent().unwrap_or_else(|| Path::new(""));
+            let issues = schema::validate(&contents, dir);
+            if issues.is_empty() {
+                status_ok!("Valid", "{}", path.display());
+            } else if !report::is_json() {
+                for issue in &issues {
+                    let line = issue
+                        .line
+                        .map(|line| format!(":{}", line))
+                        .unwrap_or_default();
+                    let key = if issue.key.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" {}:", issue.key)
+                    };
+                    println!("{}{}:{} {}", path.display(), line, key, issue.message);
+                }
+            }
+            validated.push(ValidatedFile { path, issues });
+        }
+
+        let count: usize = validated.iter().map(|file| file.issues.len()).sum();
+        report.insert("files", &validated);
+        if count > 0 {
+            report.fail(
+                &ErrorKind::Config
+                    .context(format!("{} problem(s) found", count))
+                    .into(),
+            );
+        }
+        report.finish();
+    }
+}
+
+/// The existing global config and the project config. A missing `--config`
+/// file is kept so that it is reported rather than skipped.
+fn default_files(project_config: Option<&Path>) -> Vec<PathBuf> {
+    let project_config = match project_config {
+        Some(path) => Some(path.to_path_buf()),
+        None => Some(PathBuf::from(CONFIG_FILE)).filter(|path| path.exists()),
+    };
+    layers::global_config_path()
+        .filter(|path| path.exists())
+        .into_iter()
+        .chain(project_config)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_files_include_the_given_config() {
+        let missing = Path::new("/nonexistent/solana-test.toml");
+        let files = default_files(Some(missing));
+        assert_eq!(files.last().map(PathBuf::as_path), Some(missing));
+        assert!(!files[..files.len() - 1].iter().any(|file| file == missing));
+    }
+}
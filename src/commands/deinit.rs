@@ -0,0 +1,230 @@
+//! `deinit` subcommand - revert the changes made by init
+
+use crate::prelude::*;
+
+use crate::error::{Error, ErrorKind};
+use crate::project_toml::read_manifest;
+use crate::record::InitRecord;
+use crate::report::Report;
+use crate::workspace;
+use abscissa_core::{Command, Runnable};
+use clap::Parser;
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `deinit` subcommand
+///
+/// Removes the manifest keys, test files and directories recorded by init and
+/// restores the manifest keys init replaced, leaving test files edited since
+/// alone.
+#[derive(Command, Debug, Parser)]
+pub struct DeinitCmd {
+    /// Path to tested project
+    #[clap(
+        long = "path",
+        help = "Path to tested project. Defaults to the configured path."
+    )]
+    pub(crate) path: Option<PathBuf>,
+
+    /// Workspace member to revert
+    #[clap(
+        long = "package",
+        help = "Workspace member to revert. Defaults to all initialized members."
+    )]
+    package: Option<String>,
+
+    /// Delete edited test files
+    #[clap(
+        long = "force",
+        help = "Also delete generated test files that were edited since init."
+    )]
+    force: bool,
+}
+
+impl Runnable for DeinitCmd {
+    fn run(&self) {
+        let config = APP.config();
+        let path = self.path.as_ref().unwrap_or(&config.init.path);
+
+        let mut report = Report::new("deinit");
+
+        let projects = match self.projects(path) {
+            Ok(projects) => projects,
+            Err(e) => report.fail(&e),
+        };
+        let mut programs = Vec::new();
+        for (name, project_path) in &projects {
+            match self.deinit_program(project_path) {
+                Ok(changes) => {
+                    if let Some(name) = name {
+                        status_ok!("Reverted", "{}: {}", name, changes.join(", "));
+                    }
+                    programs.push(json!({
+                        "name": name,
+                        "path": project_path,
+                        "changes": changes,
+                    }));
+                }
+                Err(e) => {
+                    report.insert("programs", &programs);
+                    report.fail(&e)
+                }
+            }
+        }
+        status_ok!("Completed", "Changes made by init were reverted");
+        report.insert("programs", programs);
+        report.finish();
+    }
+}
+
+impl DeinitCmd {
+    /// Projects with an init record - the project itself, or the selected
+    /// workspace members.
+    fn projects(&self, path: &Path) -> Result<Vec<(Option<String>, PathBuf)>, Error> {
+        let members = match workspace::load_workspace(path)? {
+            Some(members) => members,
+            None => {
+                if InitRecord::load(path)?.is_none() {
+                    return Err(ErrorKind::InitRecord
+                        .context(format!("init has not been run in {}", path.display()))
+                        .into());
+                }
+                return Ok(vec![(None, path.to_path_buf())]);
+            }
+        };
+        let projects: Vec<(Option<String>, PathBuf)> = members
+            .into_iter()
+            .filter(|member| {
+                self.package
+                    .as_ref()
+                    .is_none_or(|package| *package == member.name)
+            })
+            .filter(|member| InitRecord::path(&member.path).exists())
+            .map(|member| (Some(member.name), member.path))
+            .collect();
+        if projects.is_empty() {
+            return Err(ErrorKind::InitRecord
+                .context("no initialized workspace member found")
+                .into());
+        }
+        Ok(projects)
+    }
+
+    /// Revert a single program. Returns a description of the changes made.
+    fn deinit_program(&self, project_path: &Path) -> Result<Vec<String>, Error> {
+        let record = match InitRecord::load(project_path)? {
+            Some(record) => record,
+            None => return Ok(Vec::new()),
+        };
+        let mut changes = Vec::new();
+
+        let manifest_path = project_path.join("Cargo.toml");
+        let mut document = read_manifest(&manifest_path)?;
+        let (removed, restored) = record.revert_manifest(&mut document);
+        if !removed.is_empty() || !restored.is_empty() {
+            fs::write(&manifest_path, document.to_string()).map_err(|e| {
+                ErrorKind::WriteFailed.context(format!("{}: {}", manifest_path.display(), e))
+            })?;
+        }
+        if !removed.is_empty() {
+            changes.push(format!("removed {}", removed.join(", ")));
+        }
+        if !restored.is_empty() {
+            changes.push(format!("restored {}", restored.join(", ")));
+        }
+
+        let mut kept = Vec::new();
+        for file in &record.files {
+            if !file.path.exists() {
+                continue;
+            }
+            if self.force || file.is_unmodified()? {
+                fs::remove_file(&file.path)?;
+                changes.push(format!("deleted {}", file.path.display()));
+            } else {
+                status_warn!(
+                    "{} was edited since init, keeping it - use --force to delete it",
+                    file.path.display()
+                );
+                kept.push(file.clone());
+            }
+        }
+
+        for dir in record.remove_created_dirs()? {
+            changes.push(format!("removed {}", dir.display()));
+        }
+
+        if kept.is_empty() {
+            InitRecord::remove(project_path)?;
+            status_ok!("Removed", "init record of {}", project_path.display());
+        } else {
+            // The manifest is reverted already; keep the files left behind
+            // and their directories so that `deinit --force` can remove them.
+            let remaining = InitRecord {
+                files: kept,
+                created_dirs: record
+                    .created_dirs
+                    .into_iter()
+                    .filter(|dir| dir.exists())
+                    .collect(),
+                ..InitRecord::default()
+            };
+            remaining.save(project_path)?;
+            status_ok!(
+                "Kept",
+                "init record of {} for the files left behind",
+                project_path.display()
+            );
+        }
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::init_terminal;
+
+    const MANIFEST: &str = "[package]\nname = \"vault\"\n";
+
+    fn deinit(force: bool) -> DeinitCmd {
+        DeinitCmd {
+            path: None,
+            package: None,
+            force,
+        }
+    }
+
+    #[test]
+    fn keeps_the_record_of_edited_files() {
+        init_terminal();
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::write(root.join("Cargo.toml"), MANIFEST).unwrap();
+        let tests_dir = root.join("tests");
+        let edited = tests_dir.join("edited.rs");
+        let untouched = tests_dir.join("untouched.rs");
+
+        let mut record = InitRecord::default();
+        record.create_dir_all(&tests_dir).unwrap();
+        for path in [&edited, &untouched] {
+            fs::write(path, "// generated").unwrap();
+            record.add_file(path, "// generated");
+        }
+        record.save(&root).unwrap();
+        fs::write(&edited, "// edited").unwrap();
+
+        deinit(false).deinit_program(&root).unwrap();
+        assert!(edited.exists());
+        assert!(!untouched.exists());
+        let remaining = InitRecord::load(&root).unwrap().unwrap();
+        assert_eq!(remaining.files.len(), 1);
+        assert_eq!(remaining.files[0].path, edited);
+        assert_eq!(remaining.created_dirs, vec![tests_dir.clone()]);
+
+        deinit(true).deinit_program(&root).unwrap();
+        assert!(!tests_dir.exists());
+        assert!(InitRecord::load(&root).unwrap().is_none());
+    }
+}
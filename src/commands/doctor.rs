@@ -0,0 +1,285 @@
+//! `doctor` subcommand - validate the local Solana toolchain
+
+use crate::prelude::*;
+
+use crate::compat::{self, SolanaVersion};
+use crate::error::ErrorKind;
+use crate::report::{self, Report};
+use crate::{project_toml, toolchain, workspace};
+use abscissa_core::{Command, Runnable};
+use clap::Parser;
+use semver::Version;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// `doctor` subcommand
+///
+/// Checks the Rust toolchain, the Solana CLI, the BPF/SBF build tools and
+/// platform-tools against the project's Solana version, and prints fixes for
+/// what is missing.
+#[derive(Command, Debug, Parser)]
+pub struct DoctorCmd {
+    /// Path to tested project
+    #[clap(
+        long = "path",
+        help = "Path to tested project. Defaults to the configured path."
+    )]
+    pub(crate) path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CheckStatus {
+    Ok,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Serialize)]
+struct Check {
+    name: &'static str,
+    status: CheckStatus,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, message: String) -> Check {
+        Check {
+            name,
+            status: CheckStatus::Ok,
+            message,
+            fix: None,
+        }
+    }
+
+    fn warning(name: &'static str, message: String, fix: String) -> Check {
+        Check {
+            name,
+            status: CheckStatus::Warning,
+            message,
+            fix: Some(fix),
+        }
+    }
+
+    fn error(name: &'static str, message: String, fix: String) -> Check {
+        Check {
+            name,
+            status: CheckStatus::Error,
+            message,
+            fix: Some(fix),
+        }
+    }
+}
+
+impl Runnable for DoctorCmd {
+    fn run(&self) {
+        let config = APP.config();
+        let path = self.path.as_ref().unwrap_or(&config.init.path);
+        let mut report = Report::new("doctor");
+
+        let project_version = project_solana_version(path);
+        match &project_version {
+            Some(version) => {
+                status_ok!(
+                    "Found",
+                    "project Solana {} ({})",
+                    version.version,
+                    version.source
+                );
+                report.insert("project_solana_version", version.version.to_string());
+            }
+            None => status_warn!(
+                "Solana version of {} not found, skipping version checks",
+                path.display()
+            ),
+        }
+
+        let checks = vec![
+            check_rust(),
+            check_solana_cli(project_version.as_ref()),
+            check_build_tools(project_version.as_ref()),
+            check_platform_tools(),
+        ];
+        for check in &checks {
+            match check.status {
+                CheckStatus::Ok => status_ok!("Ok", "{}: {}", check.name, check.message),
+                CheckStatus::Warning => status_warn!("{}: {}", check.name, check.message),
+                CheckStatus::Error if !report::is_json() => {
+                    status_err!("{}: {}", check.name, check.message)
+                }
+                CheckStatus::Error => {}
+            }
+            if let Some(fix) = &check.fix {
+                status_info!("Fix", "{}", fix);
+            }
+        }
+
+        let failed = checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Error)
+            .count();
+        report.insert("checks", &checks);
+        if failed > 0 {
+            report.fail(
+                &ErrorKind::Toolchain
+                    .context(format!("{} check(s) failed", failed))
+                    .into(),
+            );
+        }
+        status_ok!("Completed", "Toolchain is ready");
+        report.finish();
+    }
+}
+
+/// Solana version of the project, or of the first program of a workspace.
+fn project_solana_version(path: &Path) -> Option<SolanaVersion> {
+    let project_path = match workspace::load_workspace(path).ok()? {
+        Some(members) => members.into_iter().find(|m| m.kind.is_program())?.path,
+        None => path.to_path_buf(),
+    };
+    let project_toml = project_toml::ProjectToml::new(project_path.clone(), &None).ok()?;
+    compat::detect_solana_version(&project_path, &project_toml.document)
+}
+
+/// Command installing the Solana tools of the given version.
+fn install_command(version: &Version) -> String {
+    let release_host = if (version.major, version.minor) >= (1, 18) {
+        "release.anza.xyz"
+    } else {
+        "release.solana.com"
+    };
+    format!(
+        "sh -c \"$(curl -sSfL https://{}/v{}/install)\"",
+        release_host, version
+    )
+}
+
+fn check_rust() -> Check {
+    let rustc = toolchain::find_executable("rustc");
+    let cargo = toolchain::find_executable("cargo");
+    match (rustc, cargo) {
+        (Some(rustc), Some(_)) => match toolchain::executable_version(&rustc) {
+            Some(version) => Check::ok("rust", format!("rustc {}", version)),
+            None => Check::warning(
+                "rust",
+                format!("cannot run {}", rustc.display()),
+                String::from("Reinstall the toolchain with `rustup toolchain install stable`"),
+            ),
+        },
+        _ => Check::error(
+            "rust",
+            String::from("rustc or cargo not found in PATH"),
+            String::from("Install Rust from https://rustup.rs"),
+        ),
+    }
+}
+
+fn check_solana_cli(project_version: Option<&SolanaVersion>) -> Check {
+    let solana = match toolchain::find_executable("solana") {
+        Some(solana) => solana,
+        None => {
+            let version = project_version
+                .map(|project| project.version.clone())
+                .unwrap_or_else(|| Version::new(1, 18, 0));
+            return Check::error(
+                "solana",
+                String::from("solana CLI not found in PATH"),
+                format!(
+                    "Install the Solana tools with `{}` and add them to PATH",
+                    install_command(&version)
+                ),
+            );
+        }
+    };
+    let version = match toolchain::executable_version(&solana) {
+        Some(version) => version,
+        None => {
+            return Check::warning(
+                "solana",
+                format!("cannot read the version of {}", solana.display()),
+                String::from("Check that `solana --version` runs"),
+            )
+        }
+    };
+    match project_version {
+        Some(project)
+            if (project.version.major, project.version.minor) != (version.major, version.minor) =>
+        {
+            Check::warning(
+                "solana",
+                format!(
+                    "solana CLI {} does not match the project's Solana {}",
+                    version, project.version
+                ),
+                format!(
+                    "Install the matching tools with `{}`",
+                    install_command(&project.version)
+                ),
+            )
+        }
+        _ => Check::ok("solana", format!("solana CLI {}", version)),
+    }
+}
+
+fn check_build_tools(project_version: Option<&SolanaVersion>) -> Check {
+    let sbf = toolchain::find_executable("cargo-build-sbf");
+    let bpf = toolchain::find_executable("cargo-build-bpf");
+    let needs_sbf = project_version.is_some_and(SolanaVersion::uses_sbf);
+    match (sbf, bpf) {
+        (Some(sbf), _) => Check::ok("build tools", format!("{}", sbf.display())),
+        (None, Some(bpf)) if !needs_sbf => Check::ok("build tools", format!("{}", bpf.display())),
+        (None, Some(_)) => Check::error(
+            "build tools",
+            String::from("only cargo-build-bpf found, the project needs cargo-build-sbf"),
+            String::from(
+                "Install the Solana tools matching the project, they ship cargo-build-sbf",
+            ),
+        ),
+        (None, None) => Check::error(
+            "build tools",
+            String::from("neither cargo-build-sbf nor cargo-build-bpf found in PATH"),
+            String::from("Install the Solana tools, they ship cargo-build-sbf and cargo-build-bpf"),
+        ),
+    }
+}
+
+fn check_platform_tools() -> Check {
+    let tools = toolchain::platform_tools();
+    if tools.is_empty() {
+        let cache_dir = toolchain::solana_cache_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_else(|| String::from("~/.cache/solana"));
+        return Check::warning(
+            "platform-tools",
+            format!("no platform-tools installed in {}", cache_dir),
+            String::from("Run `cargo build-sbf` once while online to download platform-tools"),
+        );
+    }
+    Check::ok(
+        "platform-tools",
+        tools
+            .iter()
+            .map(|tool| tool.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn install_command_uses_release_host_of_version() {
+        assert_eq!(
+            install_command(&Version::new(1, 17, 3)),
+            "sh -c \"$(curl -sSfL https://release.solana.com/v1.17.3/install)\""
+        );
+        assert_eq!(
+            install_command(&Version::new(1, 18, 0)),
+            "sh -c \"$(curl -sSfL https://release.anza.xyz/v1.18.0/install)\""
+        );
+    }
+}
@@ -0,0 +1,448 @@
+//! `fetch` subcommand - download accounts into account fixtures
+
+use crate::prelude::*;
+
+use crate::accounts::AccountFixture;
+use crate::error::{Error, ErrorKind};
+use crate::report::Report;
+use crate::rpc::{self, RpcClient};
+use crate::utility;
+use abscissa_core::{Command, Runnable};
+use clap::Parser;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Directory written to when no accounts directory is configured
+const DEFAULT_DIR: &str = "tests/fixtures/accounts";
+
+/// `fetch` subcommand
+///
+/// Reads accounts from an RPC endpoint and writes them as fixtures in the
+/// `solana account --output json` format, following upgradeable programs to
+/// their `ProgramData` account.
+#[derive(Command, Debug, Parser)]
+pub struct FetchCmd {
+    /// Accounts to fetch
+    #[clap(
+        required = true,
+        value_parser,
+        help = "Accounts to fetch, as <ADDRESS> or <NAME>=<ADDRESS>. Fixtures are named after the address by default."
+    )]
+    accounts: Vec<FetchTarget>,
+
+    /// RPC endpoint
+    #[clap(
+        long = "url",
+        short = 'u',
+        help = "RPC URL or moniker: mainnet-beta, devnet, testnet or localhost. Defaults to the configured URL, or mainnet-beta."
+    )]
+    url: Option<String>,
+
+    /// Fixture directory
+    #[clap(
+        long = "dir",
+        help = "Directory to write the fixtures to. Defaults to the configured accounts directory, or tests/fixtures/accounts."
+    )]
+    dir: Option<PathBuf>,
+}
+
+/// Account requested as `<NAME>=<ADDRESS>` or `<ADDRESS>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchTarget {
+    name: String,
+    pubkey: String,
+}
+
+impl FromStr for FetchTarget {
+    type Err = String;
+
+    fn from_str(target: &str) -> Result<Self, Self::Err> {
+        let (name, pubkey) = target.split_once('=').unwrap_or((target, target));
+        if !utility::is_pubkey(pubkey) {
+            return Err(format!("{} is not a base58 public key", pubkey));
+        }
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(format!("{} cannot be used as a file name", name));
+        }
+        Ok(FetchTarget {
+            name: name.to_string(),
+            pubkey: pubkey.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct FetchedAccount {
+    pubkey: String,
+    owner: String,
+    lamports: u64,
+    size: usize,
+    executable: bool,
+    path: PathBuf,
+    /// Program ELF extracted from the account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    program_file: Option<PathBuf>,
+}
+
+impl Runnable for FetchCmd {
+    fn run(&self) {
+        let config = APP.config();
+        let mut report = Report::new("fetch");
+
+        let url = rpc::resolve_url(
+            self.url
+                .as_deref()
+                .or(config.accounts.url.as_deref())
+                .unwrap_or(rpc::DEFAULT_URL),
+        );
+        let dir = self
+            .dir
+            .clone()
+            .or_else(|| config.accounts.dir.clone())
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_DIR));
+        report.insert("url", &url);
+        report.insert("dir", &dir);
+
+        status_info!(
+            "Fetching",
+            "{} account(s) from {}",
+            self.accounts.len(),
+            url
+        );
+        let client = RpcClient::new(&url);
+        let fixtures = match abscissa_tokio::run(&APP, self.fetch(&client, &dir)) {
+            Ok(Ok(fixtures)) => fixtures,
+            Ok(Err(e)) => report.fail(&e),
+            Err(e) => report.fail(&ErrorKind::Rpc.context(e).into()),
+        };
+
+        let fetched = match save(&fixtures, &dir) {
+            Ok(fetched) => fetched,
+            Err(e) => report.fail(&e),
+        };
+        report.insert("accounts", &fetched);
+
+        if config.accounts.dir.as_deref() != Some(dir.as_path()) {
+            status_info!(
+                "Usage",
+                "solana-test init --accounts {} to add them to the generated test",
+                dir.display()
+            );
+        }
+        status_ok!(
+            "Completed",
+            "{} fixture(s) written to {}",
+            fetched.len(),
+            dir.display()
+        );
+        report.finish();
+    }
+}
+
+impl FetchCmd {
+    /// Fetch the requested accounts and the `ProgramData` of upgradeable
+    /// programs among them.
+    async fn fetch(&self, client: &RpcClient, dir: &Path) -> Result<Vec<AccountFixture>, Error> {
+        let pubkeys: Vec<String> = self
+            .accounts
+            .iter()
+            .map(|target| target.pubkey.clone())
+            .collect();
+        let accounts = client.get_multiple_accounts(&pubkeys).await?;
+
+        let missing: Vec<&str> = self
+            .accounts
+            .iter()
+            .zip(&accounts)
+            .filter(|(_, account)| account.is_none())
+            .map(|(target, _)| target.pubkey.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return Err(ErrorKind::Rpc
+                .context(format!(
+                    "account(s) not found on {}: {}",
+                    client.url(),
+                    missing.join(", ")
+                ))
+                .into());
+        }
+
+        let mut fixtures = Vec::new();
+        for (target, account) in self.accounts.iter().zip(accounts.into_iter().flatten()) {
+            let fixture = AccountFixture {
+                path: dir.join(format!("{}.json", target.name)),
+                pubkey: target.pubkey.clone(),
+                lamports: account.lamports,
+                data: account.data,
+                owner: account.owner,
+                executable: account.executable,
+                rent_epoch: account.rent_epoch,
+            };
+            let program_data_address = fixture.program_data_address();
+            fixtures.push(fixture);
+
+            // The program account only points to the account holding the ELF
+            if let Some(address) = program_data_address {
+                let account = client.get_account_info(&address).await?.ok_or_else(|| {
+                    ErrorKind::Rpc.context(format!(
+                        "ProgramData {} of {} not found on {}",
+                        address,
+                        target.pubkey,
+                        client.url()
+                    ))
+                })?;
+                fixtures.push(AccountFixture {
+                    path: dir.join(format!("{}_program_data.json", target.name)),
+                    pubkey: address,
+                    lamports: account.lamports,
+                    data: account.data,
+                    owner: account.owner,
+                    executable: account.executable,
+                    rent_epoch: account.rent_epoch,
+                });
+            }
+        }
+        Ok(fixtures)
+    }
+}
+
+/// Write the fixtures, and the ELF of programs as `<name>.so`.
+fn save(fixtures: &[AccountFixture], dir: &Path) -> Result<Vec<FetchedAccount>, Error> {
+    fs::create_dir_all(dir)
+        .map_err(|e| ErrorKind::WriteFailed.context(format!("{}: {}", dir.display(), e)))?;
+    let mut fetched = Vec::new();
+    for fixture in fixtures {
+        fixture.save()?;
+        status_ok!(
+            "Fetched",
+            "{} ({} bytes, owner {})",
+            fixture.path.display(),
+            fixture.data.len(),
+            fixture.owner
+        );
+
+        let program_file = match fixture.program_elf() {
+            Some(elf) => {
+                let stem = fixture
+                    .path
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                let name = stem.strip_suffix("_program_data").unwrap_or(&stem);
+                let path = dir.join(format!("{}.so", name));
+                fs::write(&path, elf).map_err(|e| {
+                    ErrorKind::WriteFailed.context(format!("{}: {}", path.display(), e))
+                })?;
+                status_ok!("Written", "program {}", path.display());
+                Some(path)
+            }
+            None => None,
+        };
+        fetched.push(FetchedAccount {
+            pubkey: fixture.pubkey.clone(),
+            owner: fixture.owner.clone(),
+            lamports: fixture.lamports,
+            size: fixture.data.len(),
+            executable: fixture.executable,
+            path: fixture.path.clone(),
+            program_file,
+        });
+    }
+    Ok(fetched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::BPF_LOADER_UPGRADEABLE;
+    use serde_json::{json, Value};
+    use std::collections::BTreeMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    const MINT: &str = "So11111111111111111111111111111111111111112";
+    const PROGRAM: &str = "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS";
+    const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+    /// JSON RPC stub answering `getMultipleAccounts` and `getAccountInfo`
+    /// from `accounts`. Returns its URL and the methods called.
+    fn serve(accounts: BTreeMap<String, Value>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = calls.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                respond(stream, &accounts, &recorded);
+            }
+        });
+        (url, calls)
+    }
+
+    fn respond(
+        mut stream: TcpStream,
+        accounts: &BTreeMap<String, Value>,
+        calls: &Mutex<Vec<String>>,
+    ) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+
+        let request: Value = serde_json::from_slice(&body).unwrap();
+        let method = request["method"].as_str().unwrap().to_string();
+        let params = &request["params"][0];
+        let account = |pubkey: &Value| {
+            accounts
+                .get(pubkey.as_str().unwrap())
+                .cloned()
+                .unwrap_or(Value::Null)
+        };
+        let value = match method.as_str() {
+            "getMultipleAccounts" => {
+                Value::Array(params.as_array().unwrap().iter().map(account).collect())
+            }
+            _ => account(params),
+        };
+        calls.lock().unwrap().push(method);
+
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": { "context": { "slot": 1 }, "value": value },
+        })
+        .to_string();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.len(),
+            response
+        )
+        .unwrap();
+    }
+
+    fn account(data: &[u8], owner: &str, executable: bool) -> Value {
+        json!({
+            "lamports": 1000,
+            "data": [base64::encode(data), "base64"],
+            "owner": owner,
+            "executable": executable,
+            "rentEpoch": 0,
+            "space": data.len(),
+        })
+    }
+
+    fn fetch(url: &str, accounts: &[&str], dir: &Path) -> Result<Vec<AccountFixture>, Error> {
+        let cmd = FetchCmd::try_parse_from(["fetch", "--url", url].iter().chain(accounts)).unwrap();
+        let client = RpcClient::new(&rpc::resolve_url(cmd.url.as_deref().unwrap()));
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(cmd.fetch(&client, dir))
+    }
+
+    #[test]
+    fn fetches_accounts_and_program_data() {
+        crate::testing::init_terminal();
+        let program_data = [7u8; 32];
+        let program_data_address = bs58::encode(program_data).into_string();
+        let mut program = 2u32.to_le_bytes().to_vec();
+        program.extend(program_data);
+        let mut elf = 3u32.to_le_bytes().to_vec();
+        elf.extend([0; 8 + 1 + 32]);
+        elf.extend(b"\x7fELF");
+
+        let accounts = BTreeMap::from([
+            (
+                String::from(MINT),
+                account(&[1, 0, 0, 0], TOKEN_PROGRAM, false),
+            ),
+            (
+                String::from(PROGRAM),
+                account(&program, BPF_LOADER_UPGRADEABLE, true),
+            ),
+            (
+                program_data_address.clone(),
+                account(&elf, BPF_LOADER_UPGRADEABLE, false),
+            ),
+        ]);
+        let (url, calls) = serve(accounts);
+        let dir = tempfile::tempdir().unwrap();
+
+        let fixtures = fetch(&url, &[MINT, &format!("vault={}", PROGRAM)], dir.path()).unwrap();
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["getMultipleAccounts", "getAccountInfo"]
+        );
+        let files: Vec<(&str, PathBuf)> = fixtures
+            .iter()
+            .map(|fixture| (fixture.pubkey.as_str(), fixture.path.clone()))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                (MINT, dir.path().join(format!("{}.json", MINT))),
+                (PROGRAM, dir.path().join("vault.json")),
+                (
+                    program_data_address.as_str(),
+                    dir.path().join("vault_program_data.json")
+                ),
+            ]
+        );
+
+        let fetched = save(&fixtures, dir.path()).unwrap();
+        let program_files: Vec<Option<PathBuf>> = fetched
+            .iter()
+            .map(|account| account.program_file.clone())
+            .collect();
+        assert_eq!(
+            program_files,
+            [None, None, Some(dir.path().join("vault.so"))]
+        );
+        assert_eq!(fs::read(dir.path().join("vault.so")).unwrap(), b"\x7fELF");
+        for fixture in &fixtures {
+            assert_eq!(&AccountFixture::load(&fixture.path).unwrap(), fixture);
+        }
+    }
+
+    #[test]
+    fn missing_accounts_are_reported() {
+        let (url, _) = serve(BTreeMap::new());
+        let dir = tempfile::tempdir().unwrap();
+        let error = fetch(&url, &[MINT], dir.path()).unwrap_err();
+        assert_eq!(error.exit_code(), ErrorKind::Rpc.exit_code());
+        assert!(error
+            .to_string()
+            .contains(&format!("account(s) not found on {}: {}", url, MINT)));
+    }
+
+    #[test]
+    fn target_parsing() {
+        assert_eq!(
+            "mint=So11111111111111111111111111111111111111112".parse::<FetchTarget>(),
+            Ok(FetchTarget {
+                name: String::from("mint"),
+                pubkey: String::from(MINT)
+            })
+        );
+        assert_eq!(MINT.parse::<FetchTarget>().unwrap().name, MINT);
+        assert!("mint=not-a-key".parse::<FetchTarget>().is_err());
+        assert!(format!("../mint={}", MINT).parse::<FetchTarget>().is_err());
+        assert!(format!("={}", MINT).parse::<FetchTarget>().is_err());
+    }
+}
@@ -1,80 +1,805 @@
-//! `start` subcommand - example of how to write a subcommand
+//! `init` subcommand - add the framework to a project and generate its test
 
 /// App-local prelude includes `app_reader()`/`app_writer()`/`app_config()`
 /// accessors along with logging macros. Customize as you see fit.
-use crate::{prelude::*, project_toml, utility};
+use crate::{
+    accounts, cache, compat, idl, instruction, keys, prelude::*, program, project_toml, template,
+    utility, workspace,
+};
 
-use crate::config::SolanaTestConfig;
+use crate::accounts::AccountFixture;
+use crate::config::{InitSection, KeypairsSection, SolanaTestConfig};
+use crate::error::{Error, ErrorKind};
+use crate::record::InitRecord;
+use crate::report::{self, ChangeStatus, FileReport, KeyReport, Report};
 use abscissa_core::{config, Command, FrameworkError, Runnable};
 use clap::Parser;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fs;
-use std::{path::PathBuf, process::exit};
+use std::path::{Path, PathBuf};
 
-/// `start` subcommand
+/// `init` subcommand
 ///
-/// The `Parser` proc macro generates an option parser based on the struct
-/// definition, and is defined in the `clap` crate. See their documentation
-/// for a more comprehensive example:
-///
-/// <https://docs.rs/clap/>
+/// Adds the test feature and the framework dev-dependency to the project
+/// manifest, then writes a test file rendered from the detected program.
+/// Everything written is recorded so `deinit` can revert it.
 #[derive(Command, Debug, Parser)]
 pub struct InitCmd {
     /// Path to tested project
     #[clap(long = "path", help = "Path to tested project.")]
-    path: Option<PathBuf>,
+    pub(crate) path: Option<PathBuf>,
 
     /// Framework version
-    #[clap(long = "framework_url", help = "Url to framework repository.")]
+    #[clap(
+        long = "framework-url",
+        alias = "framework_url",
+        help = "Url to framework repository."
+    )]
     framework_repo_url: Option<String>,
 
+    /// Framework dependency name
+    #[clap(long = "framework-name", help = "Name of the framework dependency.")]
+    framework_name: Option<String>,
+
+    /// Framework git branch
+    #[clap(
+        long = "framework-branch",
+        conflicts_with_all = &["framework-tag", "framework-rev"],
+        help = "Framework git branch. Defaults to the branch matching the project's Solana version."
+    )]
+    framework_branch: Option<String>,
+
+    /// Framework git tag
+    #[clap(
+        long = "framework-tag",
+        conflicts_with = "framework-rev",
+        help = "Framework git tag."
+    )]
+    framework_tag: Option<String>,
+
+    /// Framework git revision
+    #[clap(long = "framework-rev", help = "Framework git revision.")]
+    framework_rev: Option<String>,
+
+    /// Local framework checkout
+    #[clap(
+        long = "framework-path",
+        conflicts_with_all = &["framework-version", "framework-branch", "framework-tag", "framework-rev", "framework-repo-url"],
+        help = "Path to a local framework checkout."
+    )]
+    framework_path: Option<PathBuf>,
+
+    /// Vendored framework
+    #[clap(
+        long = "framework-cache",
+        conflicts_with_all = &["framework-path", "framework-version", "framework-branch", "framework-tag", "framework-rev", "framework-repo-url"],
+        help = "Use a framework vendored with `solana-test vendor` as a path dependency."
+    )]
+    framework_cache: Option<String>,
+
+    /// Framework crates.io version
+    #[clap(
+        long = "framework-version",
+        conflicts_with_all = &["framework-branch", "framework-tag", "framework-rev", "framework-repo-url"],
+        help = "Framework version published on crates.io."
+    )]
+    framework_version: Option<String>,
+
+    /// Test template
+    #[clap(
+        long = "template",
+        help = "Name of the test template. User templates are read from the template directory."
+    )]
+    template: Option<String>,
+
+    /// Template variables
+    #[clap(
+        long = "var",
+        multiple_occurrences = true,
+        help = "Template variable in key=value format. Can be repeated."
+    )]
+    vars: Vec<String>,
+
+    /// Feature gating the tests
+    #[clap(
+        long = "test-feature",
+        help = "Feature gating the generated tests. Defaults to test-sbf for Solana 1.16 and later, test-bpf before."
+    )]
+    test_feature: Option<String>,
+
+    /// Account fixtures
+    #[clap(
+        long = "accounts",
+        help = "Directory with account dumps from `solana account --output json` to add to the generated test."
+    )]
+    accounts: Option<PathBuf>,
+
     /// Path to test file
-    #[clap(long = "test_file_path", help = "Path where to create test file.")]
+    #[clap(
+        long = "test-file-path",
+        alias = "test_file_path",
+        help = "Path where to create test file."
+    )]
     test_file_path: Option<PathBuf>,
 
-    #[clap(long = "is_anchor", help = "Is anchor project.")]
+    #[clap(long = "is-anchor", alias = "is_anchor", help = "Is anchor project.")]
     is_anchor: Option<bool>,
+
+    /// Workspace member to initialize
+    #[clap(
+        long = "package",
+        help = "Workspace member to initialize. Defaults to all program members."
+    )]
+    package: Option<String>,
+
+    /// Only print planned changes
+    #[clap(
+        long = "dry-run",
+        help = "Print the Cargo.toml diff and planned test files without writing anything."
+    )]
+    dry_run: bool,
+
+    /// Overwrite existing test files
+    #[clap(long = "force", help = "Overwrite an existing test file.")]
+    force: bool,
+
+    /// Write the test file under a new name when it exists
+    #[clap(
+        long = "suffix",
+        min_values = 0,
+        max_values = 1,
+        multiple_values = false,
+        help = "Add a suffix to the test file name when it exists. Without a value the first free number is used."
+    )]
+    suffix: Option<Option<String>>,
 }
 
 impl Runnable for InitCmd {
     /// Start the application.
     fn run(&self) {
+        let mut report = Report::new("init");
+        report.insert("dry_run", self.dry_run);
+
         let config = APP.config();
-        let project_toml =
-            project_toml::ProjectToml::new(config.init.path.clone(), &config.init.is_anchor)
-                .expect("Cannot parse project toml");
-
-        let modify_project_toml = &project_toml.clone().modify_project_toml(
-            &config.init.framework_repo_url,
-            &config.init.framework_branch,
-            &config.init.framework_name,
-        );
+        let init = match self.resolve_config(&config.init) {
+            Ok(init) => init,
+            Err(e) => report.fail(&e),
+        };
+
+        let fixtures = match &config.accounts.dir {
+            Some(dir) => match accounts::load_dir(dir) {
+                Ok(fixtures) => {
+                    status_ok!(
+                        "Found",
+                        "{} account fixture(s) in {}",
+                        fixtures.len(),
+                        dir.display()
+                    );
+                    fixtures
+                }
+                Err(e) => report.fail(&e),
+            },
+            None => Vec::new(),
+        };
+
+        let targets = match self.targets(&init) {
+            Ok(targets) => targets,
+            Err(e) => report.fail(&e),
+        };
+
+        let mut programs = Vec::new();
+        for target in &targets {
+            match self.init_program(&init, &config.keypairs, &fixtures, target) {
+                Ok(program) => {
+                    if let Some(name) = &target.name {
+                        let status = if self.dry_run {
+                            "Planned"
+                        } else {
+                            "Initialized"
+                        };
+                        status_ok!(status, "{}: {}", name, program.changes.join(", "));
+                    }
+                    programs.push(program);
+                }
+                Err(e) => {
+                    report.insert("programs", &programs);
+                    report.fail(&e)
+                }
+            }
+        }
+        report.insert("programs", &programs);
+        if self.dry_run {
+            status_ok!("Completed", "Dry run, nothing was written");
+        } else {
+            status_ok!(
+                "Completed",
+                "Setup completed! You can run your tests using solana-test run"
+            );
+        }
+        report.finish();
+    }
+}
+
+/// Outcome of initializing a single program
+#[derive(Debug, Serialize)]
+struct ProgramReport {
+    /// Workspace member name, `None` for standalone packages
+    name: Option<String>,
+    path: PathBuf,
+    /// `anchor` or `native`
+    project_type: &'static str,
+    manifest: PathBuf,
+    solana_version: Option<String>,
+    /// Why the framework branch is not the one built against the Solana
+    /// version, when init fell back to another branch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    framework_branch_fallback: Option<String>,
+    /// Feature gating the generated tests
+    test_feature: String,
+    /// Public keys of the keypairs loaded by the generated test
+    keypairs: BTreeMap<String, String>,
+    dependencies: Vec<KeyReport>,
+    files: Vec<FileReport>,
+    /// Human readable summary of the changes
+    changes: Vec<String>,
+    /// Planned manifest diff in dry-run mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diff: Option<String>,
+}
+
+/// A single program to initialize
+struct Target {
+    /// Workspace member name, `None` for standalone packages
+    name: Option<String>,
+    path: PathBuf,
+    test_file_path: PathBuf,
+    is_anchor: Option<bool>,
+}
+
+impl InitCmd {
+    /// Validate the configuration and resolve the settings that need the
+    /// file system.
+    fn resolve_config(&self, config: &InitSection) -> Result<InitSection, Error> {
+        let mut config = config.clone();
+
+        if !config.path.exists() {
+            return Err(ErrorKind::MissingCargoFile
+                .context(format!(
+                    "Incorrect path to tested project: {}",
+                    config.path.display()
+                ))
+                .into());
+        }
+
+        if let Some(framework_cache) = &self.framework_cache {
+            config.framework_path = Some(cache::find(framework_cache)?.path);
+            config.framework_version = None;
+        }
+
+        for var in &self.vars {
+            let (key, value) = template::parse_var(var)?;
+            config.vars.insert(key, value);
+        }
+
+        let test_file_path = &config.test_file_path;
+        if test_file_path.to_str().is_none() {
+            return Err(ErrorKind::NonUtf8Path
+                .context(test_file_path.to_string_lossy().into_owned())
+                .into());
+        }
+        if test_file_path.extension().is_none_or(|ext| ext != "rs") || test_file_path.is_dir() {
+            return Err(ErrorKind::InvalidTestPath
+                .context(test_file_path.display().to_string())
+                .into());
+        }
+
+        Ok(config)
+    }
 
-        match modify_project_toml {
-            Ok(_) => {}
-            Err(e) => {
-                status_err!("{}", e);
-                exit(2);
+    /// Programs to initialize - the project itself, or the selected members
+    /// when the project is a workspace.
+    fn targets(&self, config: &InitSection) -> Result<Vec<Target>, Error> {
+        let members = match workspace::load_workspace(&config.path)? {
+            Some(members) => self.select_members(members)?,
+            None => {
+                return Ok(vec![Target {
+                    name: None,
+                    path: config.path.clone(),
+                    test_file_path: config.test_file_path.clone(),
+                    is_anchor: config.is_anchor,
+                }])
             }
         };
+        Ok(members
+            .into_iter()
+            .map(|member| Target {
+                test_file_path: member_test_file_path(
+                    &config.path,
+                    &member.path,
+                    &config.test_file_path,
+                ),
+                is_anchor: config
+                    .is_anchor
+                    .or(Some(member.kind == workspace::MemberKind::Anchor)),
+                name: Some(member.name),
+                path: member.path,
+            })
+            .collect())
+    }
+
+    /// Pick the members selected with `--package`, or all program members.
+    fn select_members(
+        &self,
+        members: Vec<workspace::Member>,
+    ) -> Result<Vec<workspace::Member>, Error> {
+        for member in &members {
+            status_info!("Member", "{} ({})", member.name, member.kind);
+        }
+        let selected: Vec<workspace::Member> = match &self.package {
+            Some(package) => members
+                .into_iter()
+                .filter(|member| &member.name == package)
+                .collect(),
+            None => members
+                .into_iter()
+                .filter(|member| member.kind.is_program())
+                .collect(),
+        };
+        match (&self.package, selected.is_empty()) {
+            (Some(package), true) => {
+                return Err(ErrorKind::MissingWorkspaceProgram
+                    .context(format!("no member named {}", package))
+                    .into())
+            }
+            (None, true) => return Err(ErrorKind::MissingWorkspaceProgram.into()),
+            _ => {}
+        }
+        Ok(selected)
+    }
+
+    /// Add the framework to a single program and generate its test file.
+    fn init_program(
+        &self,
+        config: &InitSection,
+        keypairs: &KeypairsSection,
+        fixtures: &[AccountFixture],
+        target: &Target,
+    ) -> Result<ProgramReport, Error> {
+        let project_path = &target.path;
+        let test_file_path = &target.test_file_path;
+        let project_toml = project_toml::ProjectToml::new(project_path.clone(), &target.is_anchor)?;
+        let mut program = ProgramReport {
+            name: target.name.clone(),
+            path: project_path.clone(),
+            project_type: if project_toml.is_anchor {
+                "anchor"
+            } else {
+                "native"
+            },
+            manifest: project_toml.path.clone(),
+            solana_version: None,
+            framework_branch_fallback: None,
+            test_feature: String::new(),
+            keypairs: BTreeMap::new(),
+            dependencies: Vec::new(),
+            files: Vec::new(),
+            changes: Vec::new(),
+            diff: None,
+        };
+
+        let solana_version = compat::detect_solana_version(project_path, &project_toml.document);
+        let program_test_version = match &solana_version {
+            Some(solana_version) => {
+                program.solana_version = Some(solana_version.version.to_string());
+                status_ok!(
+                    "Found",
+                    "Solana {} ({})",
+                    solana_version.version,
+                    solana_version.source
+                );
+                solana_version.program_test_requirement()
+            }
+            None => {
+                let ((major, minor), _) = compat::newest_framework_branch();
+                status_warn!(
+                    "Solana version not found, defaulting to {}.{}",
+                    major,
+                    minor
+                );
+                format!("~{}.{}", major, minor)
+            }
+        };
+        let (framework_branch, fallback) = compat::pick_framework_branch(solana_version.as_ref());
+        if config.uses_default_branch() {
+            if let (Some(reason), Some(_)) = (&fallback, &solana_version) {
+                status_warn!("{}", reason);
+            }
+            program.framework_branch_fallback = fallback;
+        }
+        let framework_source = config.framework_source(framework_branch);
+        program.test_feature = test_feature(config, &project_toml, solana_version.as_ref());
+
+        let mut record = InitRecord::load(project_path)?.unwrap_or_default();
+        let mut modified = project_toml.clone();
+        program.changes = modified.apply_changes(
+            &program.test_feature,
+            &framework_source,
+            &config.framework_name,
+            &program_test_version,
+        )?;
+        program.dependencies = [
+            ("features", program.test_feature.as_str()),
+            ("dev-dependencies", config.framework_name.as_str()),
+            ("dev-dependencies", "solana-program-test"),
+        ]
+        .iter()
+        .filter_map(|(table, key)| {
+            KeyReport::compare(&project_toml.document, &modified.document, table, key)
+        })
+        .collect();
 
         // Create tests boilerplate
-        if project_toml.get_is_anchor() {
-            fs::write(
-                config.init.test_file_path.to_str().unwrap(),
-                utility::ANCHOR_TEMPLATE,
-            )
-            .expect("Could not write to file!");
+        let keys_dir = keypairs
+            .dir
+            .clone()
+            .unwrap_or_else(|| project_path.join("tests").join("fixtures").join("keys"));
+        let relative_keys_dir = utility::relative_path(
+            &utility::absolute_path(project_path),
+            &utility::absolute_path(&keys_dir),
+        );
+        let mut vars = BTreeMap::new();
+        vars.insert(String::from("test_feature"), program.test_feature.clone());
+        vars.insert(
+            String::from("accounts"),
+            accounts::render(fixtures, test_file_path),
+        );
+        vars.insert(
+            String::from("keys_dir"),
+            relative_keys_dir.to_string_lossy().replace('\\', "/"),
+        );
+        let contents = self.render_test_file(config, project_path, &project_toml, vars)?;
+        // Fail on an existing test file before anything is written
+        let resolved_path = self.resolve_test_file_path(test_file_path, &contents)?;
+        if self.dry_run {
+            let diff = modified.diff()?;
+            if !report::is_json() {
+                print!("{}", diff);
+            }
+            program.diff = Some(diff);
+        } else if !program.changes.is_empty() {
+            // Leave the manifest untouched when init is re-run
+            record.add_manifest_changes(&project_toml.document, &modified.document);
+            record.save(project_path)?;
+            modified.save_toml()?;
+        }
+        self.write_fixture_data(fixtures, &mut program, &mut record)?;
+        self.write_keypairs(
+            &keys::keypair_names(&contents),
+            &keys_dir,
+            keypairs.seed.as_deref(),
+            &mut program,
+            &mut record,
+        )?;
+        let wrote_files = program
+            .files
+            .iter()
+            .any(|file| file.status == ChangeStatus::Written);
+        if wrote_files {
+            record.save(project_path)?;
+        }
+        let test_file_path = match resolved_path {
+            Some(test_file_path) => test_file_path,
+            None => {
+                program
+                    .changes
+                    .push(format!("{} is up to date", test_file_path.display()));
+                program.files.push(FileReport {
+                    path: test_file_path.clone(),
+                    status: ChangeStatus::Skipped,
+                });
+                return Ok(program);
+            }
+        };
+        if self.dry_run {
+            let action = if test_file_path.exists() {
+                "Overwrite"
+            } else {
+                "Create"
+            };
+            status_info!(action, "{}", test_file_path.display());
+            program.files.push(FileReport {
+                path: test_file_path,
+                status: ChangeStatus::Planned,
+            });
+            return Ok(program);
+        }
+        if let Some(parent) = test_file_path.parent() {
+            record.create_dir_all(parent)?;
+        }
+        fs::write(&test_file_path, &contents).map_err(|e| {
+            ErrorKind::WriteFailed.context(format!("{}: {}", test_file_path.display(), e))
+        })?;
+        record.add_file(&test_file_path, &contents);
+        record.save(project_path)?;
+        program
+            .changes
+            .push(format!("wrote {}", test_file_path.display()));
+        program.files.push(FileReport {
+            path: test_file_path,
+            status: ChangeStatus::Written,
+        });
+
+        Ok(program)
+    }
+
+    /// Fill the selected template with the values detected in the project
+    /// and the `vars` init prepared, such as the account fixtures.
+    fn render_test_file(
+        &self,
+        config: &InitSection,
+        project_path: &Path,
+        project_toml: &project_toml::ProjectToml,
+        mut vars: BTreeMap<String, String>,
+    ) -> Result<String, Error> {
+        let crate_name = project_toml.crate_name().unwrap_or_else(|| {
+            utility::absolute_path(project_path)
+                .file_name()
+                .map(|name| utility::to_snake_case(&name.to_string_lossy()))
+                .unwrap_or_else(|| String::from("program"))
+        });
+
+        let mut instruction_tests = None;
+        if project_toml.is_anchor {
+            match idl::find_idl(project_path, &crate_name) {
+                Some(idl_path) => match idl::Idl::load(&idl_path) {
+                    Ok(idl) => {
+                        status_ok!("Found", "Anchor IDL {}", idl_path.display());
+                        instruction_tests = Some(idl::render_tests(&idl, &crate_name));
+                    }
+                    Err(e) => {
+                        status_warn!("{}: {}, using default template", idl_path.display(), e)
+                    }
+                },
+                None => {
+                    status_warn!("Anchor IDL not found, run `anchor build` to generate typed tests")
+                }
+            }
+        } else {
+            match program::find_instruction_enum(project_path, &crate_name) {
+                Some(instruction_enum) => {
+                    status_ok!("Found", "instruction enum {}", instruction_enum.path);
+                    let borsh = if compat::borsh_has_to_vec(project_path, &project_toml.document) {
+                        instruction::BorshSerialize::ToVec
+                    } else {
+                        instruction::BorshSerialize::TryToVec
+                    };
+                    instruction_tests = Some(instruction::render_tests(&instruction_enum, borsh));
+                }
+                None => status_warn!(
+                    "Borsh instruction enum not found, the test uses an INSTRUCTION placeholder"
+                ),
+            }
+        }
+
+        let template = match &config.template {
+            Some(name) => {
+                let template_dirs = template::template_dirs(config.template_dir.as_deref());
+                let template = template::load(name, &template_dirs)?;
+                if let Some(path) = &template.path {
+                    status_ok!("Found", "template {}", path.display());
+                }
+                template.contents
+            }
+            None if instruction_tests.is_some() && project_toml.is_anchor => {
+                String::from(utility::ANCHOR_IDL_TEMPLATE)
+            }
+            None if instruction_tests.is_some() => {
+                String::from(utility::SOLANA_INSTRUCTIONS_TEMPLATE)
+            }
+            None if project_toml.is_anchor => String::from(utility::ANCHOR_TEMPLATE),
+            None => String::from(utility::SOLANA_TEMPLATE),
+        };
+
+        let (program_id, program_id_source) = program::find_program_id(project_path, &crate_name);
+        if program_id_source == program::ProgramIdSource::Generated {
+            status_warn!(
+                "Program ID not found, using generated ID {} - update it if the program checks its own ID",
+                program_id
+            );
         } else {
-            fs::write(
-                config.init.test_file_path.to_str().unwrap(),
-                utility::SOLANA_TEMPLATE,
-            )
-            .expect("Could not write to file!");
-        }
-        status_ok!(
-            "Completed",
-            "Setup completed! You can run your tests using cargo test-bpf"
+            status_ok!("Found", "program ID {} ({})", program_id, program_id_source);
+        }
+
+        let processor = match program::find_entrypoint(
+            project_path,
+            &crate_name,
+            project_toml.is_anchor,
+        ) {
+            Some(entrypoint) => {
+                status_ok!("Found", "program entrypoint {}", entrypoint);
+                format!("processor!({})", entrypoint)
+            }
+            None => {
+                status_warn!(
+                    "Program entrypoint not found, the test will load {}.so built by cargo build-bpf",
+                    crate_name
+                );
+                String::from("None")
+            }
+        };
+
+        vars.insert(String::from("processor"), processor);
+        vars.insert(String::from("program_id"), program_id);
+        vars.insert(String::from("program_name"), crate_name.clone());
+        vars.insert(String::from("crate_ident"), crate_name);
+        vars.insert(
+            String::from("instruction_tests"),
+            instruction_tests.unwrap_or_default(),
         );
+        // Variables from the config and command line win over detected values
+        vars.extend(config.vars.clone());
+        let keypair_helper = template::render(utility::KEYPAIR_HELPER, &vars)?;
+        vars.entry(String::from("keypair_helper"))
+            .or_insert(keypair_helper);
+
+        template::render(&template, &vars)
+    }
+
+    /// Write the decoded account data the generated test includes.
+    fn write_fixture_data(
+        &self,
+        fixtures: &[AccountFixture],
+        program: &mut ProgramReport,
+        record: &mut InitRecord,
+    ) -> Result<(), Error> {
+        for fixture in fixtures.iter().filter(|fixture| !fixture.data.is_empty()) {
+            let path = fixture.data_path();
+            let status = if fs::read(&path).is_ok_and(|data| data == fixture.data) {
+                ChangeStatus::Skipped
+            } else if self.dry_run {
+                status_info!("Create", "{}", path.display());
+                ChangeStatus::Planned
+            } else {
+                fs::write(&path, &fixture.data).map_err(|e| {
+                    ErrorKind::WriteFailed.context(format!("{}: {}", path.display(), e))
+                })?;
+                record.add_file(&path, &fixture.data);
+                program.changes.push(format!("wrote {}", path.display()));
+                ChangeStatus::Written
+            };
+            program.files.push(FileReport { path, status });
+        }
+        Ok(())
+    }
+
+    /// Write the keypairs the generated test loads. Existing keypair files
+    /// are kept, so keys placed by the user win over generated ones.
+    fn write_keypairs(
+        &self,
+        names: &[String],
+        keys_dir: &Path,
+        seed: Option<&str>,
+        program: &mut ProgramReport,
+        record: &mut InitRecord,
+    ) -> Result<(), Error> {
+        for name in names {
+            let path = keys_dir.join(format!("{}.json", name));
+            let derived = seed.map(|seed| keys::derive(seed, name));
+            let (keypair, status) = match keys::read(&path) {
+                Some(existing) => {
+                    if derived.is_some_and(|derived| derived != existing) {
+                        status_warn!(
+                            "{} was not derived from the configured seed, delete it to derive it again",
+                            path.display()
+                        );
+                    }
+                    (existing, ChangeStatus::Skipped)
+                }
+                None if path.exists() => {
+                    return Err(ErrorKind::Fixture
+                        .context(format!(
+                            "{}: not a keypair in the solana-keygen format",
+                            path.display()
+                        ))
+                        .into())
+                }
+                None => {
+                    let keypair = match derived {
+                        Some(keypair) => keypair,
+                        None => keys::generate()?,
+                    };
+                    if self.dry_run {
+                        status_info!("Create", "{}", path.display());
+                        (keypair, ChangeStatus::Planned)
+                    } else {
+                        record.create_dir_all(keys_dir)?;
+                        let contents = keys::to_json(&keypair);
+                        fs::write(&path, &contents).map_err(|e| {
+                            ErrorKind::WriteFailed.context(format!("{}: {}", path.display(), e))
+                        })?;
+                        record.add_file(&path, &contents);
+                        program.changes.push(format!("wrote {}", path.display()));
+                        (keypair, ChangeStatus::Written)
+                    }
+                }
+            };
+            let pubkey = keys::pubkey(&keypair);
+            status_info!("Keypair", "{} {}", name, pubkey);
+            program.keypairs.insert(name.clone(), pubkey);
+            program.files.push(FileReport { path, status });
+        }
+        Ok(())
+    }
+
+    /// Decide where the test file goes without clobbering existing work.
+    ///
+    /// Returns `None` when the file already holds the generated contents.
+    fn resolve_test_file_path(
+        &self,
+        test_file_path: &Path,
+        contents: &str,
+    ) -> Result<Option<PathBuf>, Error> {
+        if !test_file_path.exists() || self.force {
+            return Ok(Some(test_file_path.to_path_buf()));
+        }
+        if fs::read_to_string(test_file_path)? == contents {
+            return Ok(None);
+        }
+
+        let suffixed = |suffix: &str| {
+            let stem = test_file_path.file_stem().unwrap().to_string_lossy();
+            test_file_path.with_file_name(format!("{}_{}.rs", stem, suffix))
+        };
+        match &self.suffix {
+            Some(Some(suffix)) if !suffixed(suffix).exists() => Ok(Some(suffixed(suffix))),
+            Some(None) => Ok((2..)
+                .map(|number| suffixed(&number.to_string()))
+                .find(|path| !path.exists())),
+            _ => Err(ErrorKind::TestFileExists
+                .context(test_file_path.display().to_string())
+                .into()),
+        }
+    }
+}
+
+/// Feature gating the generated tests - the configured one, the one the
+/// project already declares, or the one matching its Solana version.
+fn test_feature(
+    config: &InitSection,
+    project_toml: &project_toml::ProjectToml,
+    solana_version: Option<&compat::SolanaVersion>,
+) -> String {
+    if let Some(test_feature) = &config.test_feature {
+        return test_feature.clone();
+    }
+    let preferred = solana_version.map_or(compat::TEST_BPF_FEATURE, |v| v.test_feature());
+    match project_toml.existing_test_feature() {
+        Some(existing) => {
+            if existing == compat::TEST_BPF_FEATURE && preferred == compat::TEST_SBF_FEATURE {
+                status_warn!(
+                    "{} uses the {} feature, run `solana-test migrate` to switch to {}",
+                    project_toml.path.display(),
+                    existing,
+                    preferred
+                );
+            }
+            existing.to_string()
+        }
+        None => preferred.to_string(),
+    }
+}
+
+/// Test file location for a workspace member - the configured path relative
+/// to the workspace root is applied to the member directory.
+fn member_test_file_path(root: &Path, member_path: &Path, test_file_path: &Path) -> PathBuf {
+    match test_file_path.strip_prefix(root) {
+        Ok(relative) => member_path.join(relative),
+        Err(_) => member_path.join("tests").join(
+            test_file_path
+                .file_name()
+                .unwrap_or_else(|| "genereted_test.rs".as_ref()),
+        ),
     }
 }
 
@@ -87,54 +812,176 @@ impl config::Override<SolanaTestConfig> for InitCmd {
         mut config: SolanaTestConfig,
     ) -> Result<SolanaTestConfig, FrameworkError> {
         if self.path.is_some() {
-            if self.path.clone().unwrap().exists() {
-                config.init.path = self.path.clone().unwrap();
-            } else {
-                status_err!(
-                    "Incorrect path to tested project: {}",
-                    self.path.clone().unwrap().to_str().unwrap()
-                );
-                exit(1);
-            }
+            config.init.path = self.path.clone().unwrap();
         }
 
         if self.framework_repo_url.is_some() {
             config.init.framework_repo_url = self.framework_repo_url.clone().unwrap();
         }
 
+        if self.framework_name.is_some() {
+            config.init.framework_name = self.framework_name.clone().unwrap();
+        }
+
+        // Explicit flags replace any source set in the config file
+        if self.framework_branch.is_some()
+            || self.framework_tag.is_some()
+            || self.framework_rev.is_some()
+        {
+            config.init.framework_branch = self.framework_branch.clone();
+            config.init.framework_tag = self.framework_tag.clone();
+            config.init.framework_rev = self.framework_rev.clone();
+            config.init.framework_path = None;
+            config.init.framework_version = None;
+        }
+
+        if let Some(framework_path) = &self.framework_path {
+            config.init.framework_path =
+                Some(fs::canonicalize(framework_path).unwrap_or_else(|_| framework_path.clone()));
+            config.init.framework_version = None;
+        }
+
+        if self.framework_version.is_some() {
+            config.init.framework_version = self.framework_version.clone();
+            config.init.framework_path = None;
+        }
+
         if self.is_anchor.is_some() {
             config.init.is_anchor = self.is_anchor;
         }
 
-        if self.test_file_path.is_some() {
-            assert!(
-                "rs" == self
-                    .test_file_path
-                    .clone()
-                    .unwrap()
-                    .extension()
-                    .expect("Missing test file extension"),
-                "Incorrect test file extension"
-            );
-            config.init.test_file_path = self.test_file_path.clone().unwrap();
+        if self.template.is_some() {
+            config.init.template = self.template.clone();
+        }
 
-            config.init.test_file_path = self.test_file_path.clone().unwrap();
+        if self.test_feature.is_some() {
+            config.init.test_feature = self.test_feature.clone();
         }
 
-        if config.init.test_file_path.clone().parent().is_some() {
-            if !config
-                .init
-                .test_file_path
-                .clone()
-                .parent()
-                .clone()
-                .unwrap()
-                .exists()
-            {
-                fs::create_dir_all(config.init.test_file_path.clone().parent().unwrap()).unwrap();
-            }
+        if self.accounts.is_some() {
+            config.accounts.dir = self.accounts.clone();
+        }
+
+        if self.test_file_path.is_some() {
+            config.init.test_file_path = self.test_file_path.clone().unwrap();
         }
 
         Ok(config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_cmd(args: &[&str]) -> InitCmd {
+        InitCmd::parse_from(["init"].iter().chain(args))
+    }
+
+    fn existing_test_file(contents: &str) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("genereted_test.rs");
+        fs::write(&path, contents).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn refuses_to_overwrite_edited_test_file() {
+        let (_dir, path) = existing_test_file("// edited");
+        let e = init_cmd(&[])
+            .resolve_test_file_path(&path, "// generated")
+            .unwrap_err();
+        assert_eq!(*e.kind(), ErrorKind::TestFileExists);
+    }
+
+    #[test]
+    fn skips_up_to_date_test_file() {
+        let (_dir, path) = existing_test_file("// generated");
+        let resolved = init_cmd(&[])
+            .resolve_test_file_path(&path, "// generated")
+            .unwrap();
+        assert_eq!(resolved, None);
+    }
+
+    #[test]
+    fn force_overwrites_test_file() {
+        let (_dir, path) = existing_test_file("// edited");
+        let resolved = init_cmd(&["--force"])
+            .resolve_test_file_path(&path, "// generated")
+            .unwrap();
+        assert_eq!(resolved, Some(path));
+    }
+
+    #[test]
+    fn suffix_picks_free_name() {
+        let (dir, path) = existing_test_file("// edited");
+        fs::write(dir.path().join("genereted_test_2.rs"), "// edited").unwrap();
+        let resolved = init_cmd(&["--suffix"])
+            .resolve_test_file_path(&path, "// generated")
+            .unwrap();
+        assert_eq!(resolved, Some(dir.path().join("genereted_test_3.rs")));
+
+        let resolved = init_cmd(&["--suffix", "vault"])
+            .resolve_test_file_path(&path, "// generated")
+            .unwrap();
+        assert_eq!(resolved, Some(dir.path().join("genereted_test_vault.rs")));
+
+        let e = init_cmd(&["--suffix", "2"])
+            .resolve_test_file_path(&path, "// generated")
+            .unwrap_err();
+        assert_eq!(*e.kind(), ErrorKind::TestFileExists);
+    }
+
+    #[test]
+    fn accepts_snake_case_flag_aliases() {
+        for args in [
+            [
+                "--framework-url",
+                "u",
+                "--test-file-path",
+                "t.rs",
+                "--is-anchor",
+                "true",
+            ],
+            [
+                "--framework_url",
+                "u",
+                "--test_file_path",
+                "t.rs",
+                "--is_anchor",
+                "true",
+            ],
+        ] {
+            let cmd = init_cmd(&args);
+            assert_eq!(cmd.framework_repo_url.as_deref(), Some("u"));
+            assert_eq!(cmd.test_file_path, Some(PathBuf::from("t.rs")));
+            assert_eq!(cmd.is_anchor, Some(true));
+        }
+    }
+
+    #[test]
+    fn suffix_takes_at_most_one_value() {
+        assert!(InitCmd::try_parse_from(["init", "--suffix", "a", "b"]).is_err());
+    }
+
+    #[test]
+    fn member_test_file_follows_workspace_layout() {
+        let root = Path::new("/ws");
+        assert_eq!(
+            member_test_file_path(
+                root,
+                Path::new("/ws/programs/vault"),
+                Path::new("/ws/tests/genereted_test.rs")
+            ),
+            Path::new("/ws/programs/vault/tests/genereted_test.rs")
+        );
+        assert_eq!(
+            member_test_file_path(
+                root,
+                Path::new("/ws/programs/vault"),
+                Path::new("/elsewhere/poc.rs")
+            ),
+            Path::new("/ws/programs/vault/tests/poc.rs")
+        );
+    }
+}
@@ -0,0 +1,360 @@
+//! `migrate` subcommand - switch the tests to another feature
+
+use crate::prelude::*;
+
+use crate::compat::{TEST_BPF_FEATURE, TEST_SBF_FEATURE};
+use crate::error::{Error, ErrorKind};
+use crate::project_toml::ProjectToml;
+use crate::record::InitRecord;
+use crate::report::{ChangeStatus, FileReport, Report};
+use crate::{program, workspace};
+use abscissa_core::{Command, Runnable};
+use clap::Parser;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `migrate` subcommand
+///
+/// Renames the `test-bpf` feature to `test-sbf` in `Cargo.toml` and rewrites
+/// the `cfg(feature = "test-bpf")` attributes of the test files, for projects
+/// moving to Solana 1.16 or later.
+#[derive(Command, Debug, Parser)]
+pub struct MigrateCmd {
+    /// Path to tested project
+    #[clap(
+        long = "path",
+        help = "Path to tested project. Defaults to the configured path."
+    )]
+    pub(crate) path: Option<PathBuf>,
+
+    /// Workspace member to migrate
+    #[clap(
+        long = "package",
+        help = "Workspace member to migrate. Defaults to all members."
+    )]
+    package: Option<String>,
+
+    /// Feature to rename
+    #[clap(long = "from", default_value = TEST_BPF_FEATURE, help = "Feature to rename.")]
+    from: String,
+
+    /// New feature name
+    #[clap(long = "to", default_value = TEST_SBF_FEATURE, help = "New feature name.")]
+    to: String,
+
+    /// Only print planned changes
+    #[clap(
+        long = "dry-run",
+        help = "Print the planned changes without writing anything."
+    )]
+    dry_run: bool,
+}
+
+/// Outcome of migrating a single program
+#[derive(Debug, Serialize)]
+struct MigratedProgram {
+    /// Workspace member name, `None` for standalone packages
+    name: Option<String>,
+    path: PathBuf,
+    manifest: ChangeStatus,
+    files: Vec<FileReport>,
+}
+
+impl Runnable for MigrateCmd {
+    fn run(&self) {
+        let config = APP.config();
+        let path = self.path.as_ref().unwrap_or(&config.init.path);
+        let mut report = Report::new("migrate");
+        report.insert("from", &self.from);
+        report.insert("to", &self.to);
+        report.insert("dry_run", self.dry_run);
+
+        let programs = match self.programs(path) {
+            Ok(programs) => programs,
+            Err(e) => report.fail(&e),
+        };
+
+        let mut migrated = Vec::new();
+        for (name, program_path) in programs {
+            match self.migrate_program(name, program_path) {
+                Ok(program) => migrated.push(program),
+                Err(e) => {
+                    report.insert("programs", &migrated);
+                    report.fail(&e)
+                }
+            }
+        }
+        report.insert("programs", &migrated);
+
+        let changed = migrated
+            .iter()
+            .any(|program| program.manifest != ChangeStatus::Skipped || !program.files.is_empty());
+        if !changed {
+            status_ok!("Completed", "Nothing uses the {} feature", self.from);
+        } else if self.dry_run {
+            status_ok!("Completed", "Dry run, nothing was written");
+        } else {
+            status_ok!("Completed", "Tests are gated on the {} feature", self.to);
+        }
+        report.finish();
+    }
+}
+
+impl MigrateCmd {
+    /// The project itself, or the selected workspace members.
+    fn programs(&self, path: &Path) -> Result<Vec<(Option<String>, PathBuf)>, Error> {
+        let members = match workspace::load_workspace(path)? {
+            Some(members) => members,
+            None => return Ok(vec![(None, path.to_path_buf())]),
+        };
+        let programs: Vec<(Option<String>, PathBuf)> = members
+            .into_iter()
+            .filter(|member| self.package.as_ref().is_none_or(|p| *p == member.name))
+            .map(|member| (Some(member.name), member.path))
+            .collect();
+        match &self.package {
+            Some(package) if programs.is_empty() => Err(ErrorKind::MissingWorkspaceProgram
+                .context(format!("no member named {}", package))
+                .into()),
+            _ => Ok(programs),
+        }
+    }
+
+    /// Rename the feature in the manifest and rewrite the test files.
+    fn migrate_program(
+        &self,
+        name: Option<String>,
+        program_path: PathBuf,
+    ) -> Result<MigratedProgram, Error> {
+        let mut project_toml = ProjectToml::new(program_path.clone(), &None)?;
+        let mut record = InitRecord::load(&program_path)?;
+        let mut program = MigratedProgram {
+            name,
+            path: program_path,
+            manifest: ChangeStatus::Skipped,
+            files: Vec::new(),
+        };
+
+        let to_existed = project_toml
+            .document
+            .get("features")
+            .and_then(|features| features.get(&self.to))
+            .is_some();
+        if project_toml.rename_feature(&self.from, &self.to) {
+            let manifest = project_toml.path.clone();
+            if self.dry_run {
+                program.manifest = ChangeStatus::Planned;
+                status_info!(
+                    "Rename",
+                    "{} to {} in {}",
+                    self.from,
+                    self.to,
+                    manifest.display()
+                );
+            } else {
+                program.manifest = ChangeStatus::Updated;
+                project_toml.save_toml()?;
+                status_ok!(
+                    "Renamed",
+                    "{} to {} in {}",
+                    self.from,
+                    self.to,
+                    manifest.display()
+                );
+            }
+            if let Some(record) = &mut record {
+                record.rename_key("features", &self.from, &self.to, to_existed);
+            }
+        }
+
+        // Test files, including generated ones written outside `tests`, but
+        // not the account data written next to fixtures
+        let mut files = program::rust_files(&program.path.join("tests"));
+        for file in record.iter().flat_map(|record| &record.files) {
+            let is_rust = file.path.extension().is_some_and(|ext| ext == "rs");
+            if is_rust
+                && file.path.exists()
+                && !files.iter().any(|path| same_file(path, &file.path))
+            {
+                files.push(file.path.clone());
+            }
+        }
+        for file in files {
+            let contents = fs::read_to_string(&file)?;
+            let migrated = rewrite_cfg(&contents, &self.from, &self.to);
+            if migrated == contents {
+                continue;
+            }
+            if self.dry_run {
+                status_info!("Rewrite", "{}", file.display());
+                program.files.push(FileReport {
+                    path: file,
+                    status: ChangeStatus::Planned,
+                });
+                continue;
+            }
+            fs::write(&file, &migrated).map_err(|e| {
+                ErrorKind::WriteFailed.context(format!("{}: {}", file.display(), e))
+            })?;
+            if let Some(record) = &mut record {
+                record.update_file(&file, &contents, &migrated);
+            }
+            status_ok!("Rewrote", "{}", file.display());
+            program.files.push(FileReport {
+                path: file,
+                status: ChangeStatus::Written,
+            });
+        }
+
+        if let Some(record) = &record {
+            if !self.dry_run {
+                record.save(&program.path)?;
+            }
+        }
+        Ok(program)
+    }
+}
+
+/// Replace `feature = "<from>"` in `cfg` and `cfg_attr` attributes, keeping
+/// their spacing. Other mentions of the feature are left alone.
+fn rewrite_cfg(contents: &str, from: &str, to: &str) -> String {
+    let mut rewritten = String::with_capacity(contents.len());
+    let mut rest = contents;
+    while let Some(start) = rest.find('#') {
+        let (before, attribute) = rest.split_at(start);
+        rewritten.push_str(before);
+        match cfg_attribute_len(attribute) {
+            Some(len) => {
+                rewritten.push_str(&rewrite_features(&attribute[..len], from, to));
+                rest = &attribute[len..];
+            }
+            None => {
+                rewritten.push('#');
+                rest = &attribute[1..];
+            }
+        }
+    }
+    rewritten.push_str(rest);
+    rewritten
+}
+
+/// Length of the `#[cfg(...)]`, `#![cfg(...)]` or `#[cfg_attr(...)]`
+/// attribute `text` starts with.
+fn cfg_attribute_len(text: &str) -> Option<usize> {
+    let body = text.strip_prefix('#')?;
+    let body = body.strip_prefix('!').unwrap_or(body);
+    let body = body.trim_start().strip_prefix('[')?.trim_start();
+    let name_len = body.find(|c: char| !is_ident_char(c)).unwrap_or(body.len());
+    if !matches!(&body[..name_len], "cfg" | "cfg_attr") {
+        return None;
+    }
+
+    let offset = text.len() - body.len();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in body.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' => depth += 1,
+            ']' if depth == 0 => return Some(offset + index + 1),
+            ')' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Replace the `"<from>"` value of the `feature` keys in an attribute.
+fn rewrite_features(attribute: &str, from: &str, to: &str) -> String {
+    let literal = format!("\"{}\"", from);
+    let mut rewritten = String::with_capacity(attribute.len());
+    let mut rest = attribute;
+    while let Some(start) = rest.find("feature") {
+        rewritten.push_str(&rest[..start]);
+        let key = &rest[start..];
+        let after_key = &key["feature".len()..];
+        let is_key = !rewritten.ends_with(is_ident_char);
+        let value = after_key
+            .trim_start()
+            .strip_prefix('=')
+            .map(str::trim_start)
+            .filter(|value| is_key && value.starts_with(&literal));
+        match value {
+            Some(value) => {
+                rewritten.push_str(&key[..key.len() - value.len()]);
+                rewritten.push_str(&format!("\"{}\"", to));
+                rest = &value[literal.len()..];
+            }
+            None => {
+                rewritten.push_str("feature");
+                rest = after_key;
+            }
+        }
+    }
+    rewritten.push_str(rest);
+    rewritten
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrite_cfg_keeps_spacing() {
+        let contents = "#![cfg(feature = \"test-bpf\")]\n\
+                        #[cfg(feature=\"test-bpf\")]\n\
+                        # [ cfg(all(feature  =  \"test-bpf\", not(windows))) ]\n\
+                        #[cfg_attr(feature = \"test-bpf\", ignore)]\n";
+        assert_eq!(
+            rewrite_cfg(contents, "test-bpf", "test-sbf"),
+            "#![cfg(feature = \"test-sbf\")]\n\
+             #[cfg(feature=\"test-sbf\")]\n\
+             # [ cfg(all(feature  =  \"test-sbf\", not(windows))) ]\n\
+             #[cfg_attr(feature = \"test-sbf\", ignore)]\n"
+        );
+    }
+
+    #[test]
+    fn rewrite_cfg_only_touches_cfg_attributes() {
+        let contents = "#[doc = \"see feature = \\\"test-bpf\\\"\"]\n\
+                        // cfg(feature = \"test-bpf\")\n\
+                        let s = \"feature = \\\"test-bpf\\\"\";\n\
+                        #[cfg(my_feature = \"test-bpf\")]\n\
+                        #[cfg(feature = \"test-bpf-extra\")]\n\
+                        #[cfg(not(feature = \"other\"))]\n";
+        assert_eq!(rewrite_cfg(contents, "test-bpf", "test-sbf"), contents);
+    }
+
+    #[test]
+    fn cfg_attribute_len_skips_strings_and_nesting() {
+        let attribute = "#[cfg_attr(feature = \"x\", doc = \"a ] b\")]";
+        assert_eq!(
+            cfg_attribute_len(&format!("{}\nfn f() {{}}", attribute)),
+            Some(attribute.len())
+        );
+        assert_eq!(cfg_attribute_len("#[test]"), None);
+        assert_eq!(cfg_attribute_len("#[cfg(unterminated"), None);
+    }
+}
@@ -0,0 +1,119 @@
+//! `report` subcommand - convert saved test output into test reports
+
+use crate::prelude::*;
+
+use crate::error::{Error, ErrorKind};
+use crate::libtest::{ReportTarget, TestRun, TestStatus};
+use crate::report::{self, Report};
+use abscissa_core::{Command, Runnable};
+use clap::Parser;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+/// `report` subcommand
+///
+/// Parses the libtest output of a test run, e.g. saved with
+/// `cargo test-sbf | tee out.txt`, and writes JUnit XML or JSON reports.
+#[derive(Command, Debug, Parser)]
+pub struct ReportCmd {
+    /// Saved test output
+    #[clap(help = "File with the saved test output, or - for stdin.")]
+    input: PathBuf,
+
+    /// Suite name
+    #[clap(
+        long = "name",
+        help = "Name of the test suite. Defaults to the input file name."
+    )]
+    name: Option<String>,
+
+    /// Test reports to write
+    #[clap(
+        long = "report",
+        value_parser,
+        help = "Write a test report, as junit:<path> or json:<path>. Can be repeated."
+    )]
+    reports: Vec<ReportTarget>,
+}
+
+impl Runnable for ReportCmd {
+    fn run(&self) {
+        let mut report = Report::new("report");
+        let output = match self.read_input() {
+            Ok(output) => output,
+            Err(e) => report.fail(&e),
+        };
+        let run = TestRun::parse(&output);
+        if run.tests.is_empty() {
+            status_warn!("no test results found in {}", self.input.display());
+        }
+
+        let name = self.name.clone().unwrap_or_else(|| {
+            self.input
+                .file_stem()
+                .filter(|_| !self.is_stdin())
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| String::from("tests"))
+        });
+        if !report::is_json() && self.reports.is_empty() {
+            for test in &run.tests {
+                let status = match test.status {
+                    TestStatus::Passed => "passed",
+                    TestStatus::Failed => "FAILED",
+                    TestStatus::Ignored => "ignored",
+                };
+                println!("{}\t{}", status, test.name);
+                if let Some(message) = &test.panic_message {
+                    println!("\t{}", message.replace('\n', "\n\t"));
+                }
+            }
+        }
+
+        let suites = vec![(name, run)];
+        for target in &self.reports {
+            if let Err(e) = target.write(&suites) {
+                report.fail(&e);
+            }
+            status_ok!("Written", "test report {}", target.path.display());
+        }
+        let (name, run) = &suites[0];
+        status_ok!(
+            "Parsed",
+            "{}: {} passed, {} failed, {} ignored",
+            name,
+            run.summary.passed,
+            run.summary.failed,
+            run.summary.ignored
+        );
+        report.insert("name", name);
+        report.insert("run", run);
+        report.insert(
+            "reports",
+            self.reports
+                .iter()
+                .map(|target| &target.path)
+                .collect::<Vec<_>>(),
+        );
+        report.finish();
+    }
+}
+
+impl ReportCmd {
+    fn is_stdin(&self) -> bool {
+        self.input.as_os_str() == "-"
+    }
+
+    fn read_input(&self) -> Result<String, Error> {
+        if self.is_stdin() {
+            let mut output = String::new();
+            io::stdin().read_to_string(&mut output)?;
+            return Ok(output);
+        }
+        fs::read_to_string(&self.input).map_err(|e| {
+            ErrorKind::Io
+                .context(format!("{}: {}", self.input.display(), e))
+                .into()
+        })
+    }
+}
@@ -0,0 +1,459 @@
+//! `run` subcommand - build and execute the generated tests
+
+use crate::prelude::*;
+
+use crate::compat::{TEST_BPF_FEATURE, TEST_SBF_FEATURE};
+use crate::error::{Error, ErrorKind};
+use crate::libtest::{ReportTarget, TestRun, TestSummary};
+use crate::project_toml::read_manifest;
+use crate::report::{self, Report};
+use crate::{toolchain, workspace};
+use abscissa_core::{Command, Runnable};
+use clap::Parser;
+use serde::Serialize;
+use std::env;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::sync::mpsc;
+use std::thread;
+
+/// `run` subcommand
+///
+/// Runs `cargo test-sbf`, or `cargo test-bpf` on older toolchains, in every
+/// initialized program and prints a combined summary.
+#[derive(Command, Debug, Parser)]
+pub struct RunCmd {
+    /// Path to tested project
+    #[clap(
+        long = "path",
+        help = "Path to tested project. Defaults to the configured path."
+    )]
+    pub(crate) path: Option<PathBuf>,
+
+    /// Workspace member to test
+    #[clap(
+        long = "package",
+        help = "Workspace member to test. Defaults to all initialized members."
+    )]
+    package: Option<String>,
+
+    /// Test name filter
+    #[clap(help = "Only run tests whose name contains this string.")]
+    filter: Option<String>,
+
+    /// Test reports to write
+    #[clap(
+        long = "report",
+        value_parser,
+        help = "Write a test report, as junit:<path> or json:<path>. Can be repeated."
+    )]
+    reports: Vec<ReportTarget>,
+
+    /// Arguments for the test binaries
+    #[clap(last = true, help = "Arguments passed to the test binaries.")]
+    args: Vec<String>,
+}
+
+/// Cargo subcommand building and testing programs
+#[derive(Debug, Clone, Copy)]
+enum TestTool {
+    Sbf,
+    Bpf,
+}
+
+impl TestTool {
+    /// Prefer `test-sbf`, as `test-bpf` is deprecated since Solana 1.16.
+    fn detect() -> Option<TestTool> {
+        if toolchain::find_executable("cargo-test-sbf").is_some() {
+            Some(TestTool::Sbf)
+        } else if toolchain::find_executable("cargo-test-bpf").is_some() {
+            Some(TestTool::Bpf)
+        } else {
+            None
+        }
+    }
+
+    fn subcommand(self) -> &'static str {
+        match self {
+            TestTool::Sbf => "test-sbf",
+            TestTool::Bpf => "test-bpf",
+        }
+    }
+
+    fn out_dir_var(self) -> &'static str {
+        match self {
+            TestTool::Sbf => "SBF_OUT_DIR",
+            TestTool::Bpf => "BPF_OUT_DIR",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ProgramRun {
+    name: Option<String>,
+    path: PathBuf,
+    success: bool,
+    /// The tool failed before any test ran, e.g. on a compile error
+    build_failed: bool,
+    #[serde(flatten)]
+    summary: TestSummary,
+}
+
+impl Runnable for RunCmd {
+    fn run(&self) {
+        let config = APP.config();
+        let path = self.path.as_ref().unwrap_or(&config.init.path);
+        let mut report = Report::new("run");
+
+        let tool = match TestTool::detect() {
+            Some(tool) => tool,
+            None => report.fail(
+                &ErrorKind::Toolchain
+                    .context(
+                        "neither cargo test-sbf nor cargo test-bpf found, run `solana-test doctor`",
+                    )
+                    .into(),
+            ),
+        };
+        report.insert("tool", tool.subcommand());
+
+        let programs = match self.programs(path, &config.init.framework_name) {
+            Ok(programs) => programs,
+            Err(e) => report.fail(&e),
+        };
+
+        let mut runs = Vec::new();
+        let mut suites = Vec::new();
+        let mut total = TestSummary::default();
+        for (name, program_path) in &programs {
+            let out_dir = deploy_dir(path, program_path);
+            status_info!(
+                "Running",
+                "cargo {} in {}",
+                tool.subcommand(),
+                program_path.display()
+            );
+            let (success, output) = match self.run_tests(
+                tool,
+                program_path,
+                &out_dir,
+                config.init.test_feature.as_deref(),
+            ) {
+                Ok(result) => result,
+                Err(e) => {
+                    report.insert("programs", &runs);
+                    report.fail(&e)
+                }
+            };
+            let test_run = TestRun::parse(&output);
+            let summary = test_run.summary;
+            total.add(summary);
+            runs.push(ProgramRun {
+                name: name.clone(),
+                path: program_path.clone(),
+                success,
+                build_failed: build_failed(success, &output),
+                summary,
+            });
+            // Name single projects after their directory, `--path .` included
+            let suite_name = name.clone().unwrap_or_else(|| {
+                program_path
+                    .canonicalize()
+                    .ok()
+                    .and_then(|path| path.file_name().map(|n| n.to_string_lossy().into_owned()))
+                    .unwrap_or_else(|| program_path.display().to_string())
+            });
+            suites.push((suite_name, test_run));
+        }
+
+        for target in &self.reports {
+            if let Err(e) = target.write(&suites) {
+                report.fail(&e);
+            }
+            status_ok!("Written", "test report {}", target.path.display());
+        }
+        report.insert(
+            "reports",
+            self.reports
+                .iter()
+                .map(|target| &target.path)
+                .collect::<Vec<_>>(),
+        );
+
+        for run in &runs {
+            let name = run
+                .name
+                .clone()
+                .unwrap_or_else(|| run.path.display().to_string());
+            let counts = format!(
+                "{} passed, {} failed, {} ignored",
+                run.summary.passed, run.summary.failed, run.summary.ignored
+            );
+            if run.success {
+                status_ok!("Passed", "{}: {}", name, counts);
+            } else if run.build_failed {
+                status_warn!("{} failed to build, no tests ran", name);
+            } else {
+                status_warn!("{} failed: {}", name, counts);
+            }
+        }
+        report.insert("programs", &runs);
+        report.insert("total", total);
+
+        let build_failures: Vec<String> = runs
+            .iter()
+            .filter(|run| run.build_failed)
+            .map(|run| {
+                run.name
+                    .clone()
+                    .unwrap_or_else(|| run.path.display().to_string())
+            })
+            .collect();
+        if !build_failures.is_empty() {
+            report.fail(
+                &ErrorKind::TestFailure
+                    .context(format!(
+                        "{} failed to build, see the cargo {} output above",
+                        build_failures.join(", "),
+                        tool.subcommand()
+                    ))
+                    .into(),
+            );
+        }
+        let failed = runs.iter().filter(|run| !run.success).count();
+        if failed > 0 {
+            report.fail(
+                &ErrorKind::TestFailure
+                    .context(format!(
+                        "{} of {} program(s) failed, {} test(s) failed",
+                        failed,
+                        runs.len(),
+                        total.failed
+                    ))
+                    .into(),
+            );
+        }
+        status_ok!(
+            "Completed",
+            "{} passed, {} failed, {} ignored",
+            total.passed,
+            total.failed,
+            total.ignored
+        );
+        report.finish();
+    }
+}
+
+impl RunCmd {
+    /// Initialized programs - the project itself, or the workspace members
+    /// with the framework dev-dependency.
+    fn programs(
+        &self,
+        path: &Path,
+        framework_name: &str,
+    ) -> Result<Vec<(Option<String>, PathBuf)>, Error> {
+        let members = match workspace::load_workspace(path)? {
+            Some(members) => members,
+            None => return Ok(vec![(None, path.to_path_buf())]),
+        };
+        let mut programs = Vec::new();
+        for member in members {
+            if self
+                .package
+                .as_ref()
+                .is_some_and(|package| *package != member.name)
+            {
+                continue;
+            }
+            let document = read_manifest(&member.path.join("Cargo.toml"))?;
+            let is_initialized = document
+                .get("dev-dependencies")
+                .and_then(|deps| deps.get(framework_name))
+                .is_some();
+            if is_initialized {
+                programs.push((Some(member.name), member.path));
+            }
+        }
+        if programs.is_empty() {
+            return Err(ErrorKind::MissingWorkspaceProgram
+                .context("no initialized workspace member found, run `solana-test init` first")
+                .into());
+        }
+        Ok(programs)
+    }
+
+    /// Run the tests of one program, echoing their stdout and stderr.
+    /// Returns whether they passed and the output of both streams.
+    fn run_tests(
+        &self,
+        tool: TestTool,
+        program_path: &Path,
+        out_dir: &Path,
+        test_feature: Option<&str>,
+    ) -> Result<(bool, String), Error> {
+        let mut command = process::Command::new("cargo");
+        command
+            .arg(tool.subcommand())
+            .arg("--manifest-path")
+            .arg(program_path.join("Cargo.toml"))
+            .env(tool.out_dir_var(), out_dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        // The generated tests are gated on the feature init added, which
+        // the tool does not enable by itself unless it is named after it
+        let document = read_manifest(&program_path.join("Cargo.toml"))?;
+        let features = document.get("features");
+        let candidates = [TEST_BPF_FEATURE, TEST_SBF_FEATURE]
+            .into_iter()
+            .chain(test_feature)
+            .filter(|feature| *feature != tool.subcommand());
+        for feature in candidates {
+            if features.and_then(|f| f.get(feature)).is_some() {
+                command.arg("--features").arg(feature);
+            }
+        }
+
+        command.args(test_args(self.filter.as_deref(), &self.args));
+
+        // Program logs of `solana_logger` and cargo's `Running` lines are
+        // written to stderr, so both streams are read, in arrival order
+        let mut child = command.spawn()?;
+        let (sender, receiver) = mpsc::channel();
+        let mut readers = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            readers.push(forward_lines(stdout, false, sender.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            readers.push(forward_lines(stderr, true, sender));
+        }
+        let mut output = String::new();
+        for (is_stderr, line) in receiver {
+            let line = line?;
+            // Keep stdout for the JSON report
+            if is_stderr || report::is_json() {
+                eprintln!("{}", line);
+            } else {
+                println!("{}", line);
+            }
+            output.push_str(&line);
+            output.push('\n');
+        }
+        for reader in readers {
+            let _ = reader.join();
+        }
+        let status = child.wait()?;
+        std::io::stdout().flush()?;
+        Ok((status.success(), output))
+    }
+}
+
+/// Send the lines of a child stream, tagged with whether it is stderr.
+fn forward_lines<R: Read + Send + 'static>(
+    stream: R,
+    is_stderr: bool,
+    sender: mpsc::Sender<(bool, std::io::Result<String>)>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let failed = line.is_err();
+            if sender.send((is_stderr, line)).is_err() || failed {
+                break;
+            }
+        }
+    })
+}
+
+/// Arguments after the tool's own options. The tool forwards everything
+/// after the first `--` to `cargo test`, so the test binary arguments need
+/// a second one: `cargo test-sbf -- <filter> -- <args>`.
+fn test_args(filter: Option<&str>, args: &[String]) -> Vec<String> {
+    let mut test_args = Vec::new();
+    if filter.is_none() && args.is_empty() {
+        return test_args;
+    }
+    test_args.push(String::from("--"));
+    test_args.extend(filter.map(String::from));
+    if !args.is_empty() {
+        test_args.push(String::from("--"));
+        test_args.extend(args.iter().cloned());
+    }
+    test_args
+}
+
+/// Whether the tool failed before running any test binary, which then
+/// printed no `test result:` line.
+fn build_failed(success: bool, output: &str) -> bool {
+    !success
+        && !output
+            .lines()
+            .any(|line| line.trim().starts_with("test result:"))
+}
+
+/// Directory holding the built programs - `target/deploy` of the project,
+/// or of the workspace root for members.
+fn deploy_dir(root: &Path, program_path: &Path) -> PathBuf {
+    let target_dir = env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            if program_path.starts_with(root) {
+                root.join("target")
+            } else {
+                program_path.join("target")
+            }
+        });
+    target_dir.join("deploy")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_separate_filter_and_binary_arguments() {
+        let args = vec![String::from("--nocapture")];
+        assert!(test_args(None, &[]).is_empty());
+        assert_eq!(test_args(Some("transfer"), &[]), ["--", "transfer"]);
+        assert_eq!(test_args(None, &args), ["--", "--", "--nocapture"]);
+        assert_eq!(
+            test_args(Some("transfer"), &args),
+            ["--", "transfer", "--", "--nocapture"]
+        );
+    }
+
+    #[test]
+    fn build_failure_is_a_failed_run_without_tests() {
+        assert!(build_failed(false, ""));
+        assert!(!build_failed(true, ""));
+
+        let failing =
+            "test transfer ... FAILED\n\ntest result: FAILED. 0 passed; 1 failed; 0 ignored\n";
+        assert!(!build_failed(false, failing));
+
+        // A filter matching no test still prints a result line
+        let filtered_out = "test result: ok. 0 passed; 0 failed; 0 ignored\n";
+        assert!(!build_failed(false, filtered_out));
+    }
+
+    #[test]
+    fn forward_lines_tags_streams() {
+        let (sender, receiver) = mpsc::channel();
+        let stdout = std::io::Cursor::new(b"test a ... ok\n".to_vec());
+        let stderr = std::io::Cursor::new(b"Program log: a\nProgram log: b".to_vec());
+        forward_lines(stdout, false, sender.clone()).join().unwrap();
+        forward_lines(stderr, true, sender).join().unwrap();
+        let lines: Vec<(bool, String)> = receiver
+            .into_iter()
+            .map(|(is_stderr, line)| (is_stderr, line.unwrap()))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (false, String::from("test a ... ok")),
+                (true, String::from("Program log: a")),
+                (true, String::from("Program log: b")),
+            ]
+        );
+    }
+}
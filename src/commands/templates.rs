@@ -0,0 +1,73 @@
+//! `templates` subcommand - browse available test templates
+
+use crate::prelude::*;
+
+use crate::report::{self, Report};
+use crate::{template, utility};
+use abscissa_core::{Command, Runnable};
+use clap::Parser;
+use serde_json::json;
+
+/// `templates` subcommand
+#[derive(Command, Debug, Parser, Runnable)]
+pub enum TemplatesCmd {
+    /// List built-in and user templates
+    List(TemplatesListCmd),
+}
+
+/// `templates list` subcommand
+#[derive(Command, Debug, Parser)]
+pub struct TemplatesListCmd {}
+
+impl Runnable for TemplatesListCmd {
+    fn run(&self) {
+        let config = APP.config();
+        let template_dirs = template::template_dirs(config.init.template_dir.as_deref());
+        let user_templates = template::user_templates(&template_dirs);
+
+        let mut report = Report::new("templates list");
+        report.insert(
+            "builtin",
+            utility::BUILTIN_TEMPLATES
+                .iter()
+                .map(|builtin| {
+                    json!({
+                        "name": builtin.name,
+                        "description": builtin.description,
+                        "overridden": user_templates.iter().any(|(name, _)| name == builtin.name),
+                    })
+                })
+                .collect::<Vec<_>>(),
+        );
+        report.insert(
+            "user",
+            user_templates
+                .iter()
+                .map(|(name, path)| json!({ "name": name, "path": path }))
+                .collect::<Vec<_>>(),
+        );
+        if report::is_json() {
+            report.finish();
+            return;
+        }
+
+        println!("Built-in templates:");
+        for builtin in utility::BUILTIN_TEMPLATES {
+            let overridden = user_templates.iter().any(|(name, _)| name == builtin.name);
+            println!(
+                "  {:<14}{}{}",
+                builtin.name,
+                builtin.description,
+                if overridden { " (overridden)" } else { "" }
+            );
+        }
+
+        if !user_templates.is_empty() {
+            println!("\nUser templates:");
+            for (name, path) in &user_templates {
+                println!("  {:<14}{}", name, path.display());
+            }
+        }
+        println!("\nUse `solana-test init --template <name>` to start from a template.");
+    }
+}
@@ -0,0 +1,146 @@
+//! `vendor` subcommand - store a framework archive in the local cache
+
+use crate::prelude::*;
+
+use crate::error::{Error, ErrorKind};
+use crate::report::Report;
+use crate::{cache, compat, project_toml};
+use abscissa_core::{Command, Runnable};
+use clap::Parser;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `vendor` subcommand
+///
+/// Downloads a framework zip archive once, or reads it from a local file on
+/// air-gapped machines, and unpacks it into the cache for `init
+/// --framework-cache`.
+#[derive(Command, Debug, Parser)]
+pub struct VendorCmd {
+    /// Archive URL
+    #[clap(
+        long = "url",
+        conflicts_with = "file",
+        help = "Url of the framework zip archive. Defaults to the configured repository and the branch matching the project's Solana version."
+    )]
+    url: Option<String>,
+
+    /// Local archive
+    #[clap(long = "file", help = "Path to a framework zip archive.")]
+    file: Option<PathBuf>,
+
+    /// Expected archive checksum
+    #[clap(long = "sha256", help = "Expected SHA-256 of the archive.")]
+    sha256: Option<String>,
+
+    /// Cache entry name
+    #[clap(
+        long = "name",
+        help = "Name of the cache entry. Defaults to the framework name."
+    )]
+    name: Option<String>,
+}
+
+impl Runnable for VendorCmd {
+    fn run(&self) {
+        let config = APP.config();
+        let name = self.name.as_ref().unwrap_or(&config.init.framework_name);
+        let mut report = Report::new("vendor");
+
+        let archive = match self.read_archive() {
+            Ok(archive) => archive,
+            Err(e) => report.fail(&e),
+        };
+        report.insert("sha256", cache::sha256_hex(&archive));
+        if self.sha256.is_none() {
+            status_warn!(
+                "No --sha256 given, archive SHA-256 is {}",
+                cache::sha256_hex(&archive)
+            );
+        }
+
+        match cache::vendor(&archive, name, self.sha256.as_deref()) {
+            Ok(entry) => {
+                status_ok!("Vendored", "{} to {}", entry.name, entry.path.display());
+                status_info!("Usage", "solana-test init --framework-cache {}", entry.name);
+                report.insert("entry", entry);
+                report.finish();
+            }
+            Err(e) => report.fail(&e),
+        }
+    }
+}
+
+impl VendorCmd {
+    fn read_archive(&self) -> Result<Vec<u8>, Error> {
+        if let Some(file) = &self.file {
+            return Ok(fs::read(file)?);
+        }
+        let url = match &self.url {
+            Some(url) => url.clone(),
+            None => {
+                let config = APP.config();
+                let branch = match &config.init.framework_branch {
+                    Some(branch) => branch.clone(),
+                    None => framework_branch(&config.init.path).to_string(),
+                };
+                format!(
+                    "{}/archive/refs/heads/{}.zip",
+                    config.init.framework_repo_url.trim_end_matches('/'),
+                    branch
+                )
+            }
+        };
+        status_info!("Downloading", "{}", url);
+        abscissa_tokio::run(&APP, cache::download(&url))
+            .map_err(|e| ErrorKind::Download.context(e))?
+    }
+}
+
+/// Framework branch init picks for the project at `project_path`.
+fn framework_branch(project_path: &Path) -> &'static str {
+    let solana_version = project_toml::read_manifest(&project_path.join("Cargo.toml"))
+        .ok()
+        .and_then(|document| compat::detect_solana_version(project_path, &document));
+    if let Some(solana_version) = &solana_version {
+        status_ok!(
+            "Found",
+            "Solana {} ({})",
+            solana_version.version,
+            solana_version.source
+        );
+    }
+    let (branch, fallback) = compat::pick_framework_branch(solana_version.as_ref());
+    if let Some(reason) = fallback {
+        status_warn!("{}", reason);
+    }
+    branch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn picks_branch_of_project_solana_version() {
+        testing::init_terminal();
+        let project = tempfile::tempdir().unwrap();
+        fs::write(
+            project.path().join("Cargo.toml"),
+            "[package]\nname = \"vault\"\n\n[dependencies]\nsolana-program = \"1.10\"\n",
+        )
+        .unwrap();
+        assert_eq!(framework_branch(project.path()), "solana-1.10");
+    }
+
+    #[test]
+    fn defaults_to_newest_branch() {
+        testing::init_terminal();
+        let project = tempfile::tempdir().unwrap();
+        assert_eq!(
+            framework_branch(project.path()),
+            compat::newest_framework_branch().1
+        );
+    }
+}
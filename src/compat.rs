@@ -0,0 +1,443 @@
+//! Solana version detection and framework compatibility table
+
+use semver::{Version, VersionReq};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use toml_edit::{Document, Item};
+
+/// Framework branches built against each Solana minor version, as published
+/// in the framework repository. Projects on a newer Solana than the last entry
+/// fall back to the default branch with a warning.
+const FRAMEWORK_BRANCHES: &[((u64, u64), &str)] = &[
+    ((1, 9), "solana-1.9"),
+    ((1, 10), "solana-1.10"),
+    ((1, 14), "solana-1.14"),
+];
+
+/// Solana minor version each Anchor minor version is built against.
+const ANCHOR_SOLANA_VERSIONS: &[(u64, (u64, u64))] = &[
+    (22, (1, 9)),
+    (23, (1, 9)),
+    (24, (1, 9)),
+    (25, (1, 10)),
+    (26, (1, 14)),
+    (27, (1, 14)),
+    (28, (1, 16)),
+    (29, (1, 17)),
+    (30, (1, 18)),
+    (31, (2, 1)),
+];
+
+/// First Solana version whose tooling builds with `cargo build-sbf` only,
+/// `cargo build-bpf` being deprecated.
+const SBF_VERSION: (u64, u64) = (1, 16);
+
+/// Feature gating the tests of projects built with `cargo build-bpf`
+pub const TEST_BPF_FEATURE: &str = "test-bpf";
+
+/// Feature gating the tests of projects built with `cargo build-sbf`
+pub const TEST_SBF_FEATURE: &str = "test-sbf";
+
+/// Dependencies the Solana version is read from, in order of preference.
+const SOLANA_CRATES: &[&str] = &["solana-program", "solana-sdk"];
+
+const DEFAULT_FRAMEWORK_BRANCH: &str = "main";
+
+/// Where the Solana version was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionSource {
+    CargoLock,
+    Manifest,
+    Workspace,
+    AnchorLang,
+}
+
+impl fmt::Display for VersionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionSource::CargoLock => write!(f, "Cargo.lock"),
+            VersionSource::Manifest => write!(f, "Cargo.toml"),
+            VersionSource::Workspace => write!(f, "workspace Cargo.toml"),
+            VersionSource::AnchorLang => write!(f, "anchor-lang"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SolanaVersion {
+    pub version: Version,
+    /// True when the patch version is known exactly, i.e. read from Cargo.lock
+    pub exact: bool,
+    pub source: VersionSource,
+}
+
+impl SolanaVersion {
+    /// Version requirement for `solana-program-test` that stays on the same
+    /// minor version as the program.
+    pub fn program_test_requirement(&self) -> String {
+        if self.exact {
+            format!(
+                "~{}.{}.{}",
+                self.version.major, self.version.minor, self.version.patch
+            )
+        } else {
+            format!("~{}.{}", self.version.major, self.version.minor)
+        }
+    }
+
+    /// Returns true when the project builds with `cargo build-sbf`.
+    pub fn uses_sbf(&self) -> bool {
+        (self.version.major, self.version.minor) >= SBF_VERSION
+    }
+
+    /// Feature enabled by `cargo test-sbf` or `cargo test-bpf` for this
+    /// version.
+    pub fn test_feature(&self) -> &'static str {
+        if self.uses_sbf() {
+            TEST_SBF_FEATURE
+        } else {
+            TEST_BPF_FEATURE
+        }
+    }
+
+    /// Framework branch built against this Solana version, `None` when the
+    /// version is newer than every framework branch.
+    pub fn framework_branch(&self) -> Option<&'static str> {
+        let minor = (self.version.major, self.version.minor);
+        match FRAMEWORK_BRANCHES.last() {
+            Some((latest, _)) if minor <= *latest => Some(
+                FRAMEWORK_BRANCHES
+                    .iter()
+                    .rev()
+                    .find(|(version, _)| *version <= minor)
+                    .unwrap_or(&FRAMEWORK_BRANCHES[0])
+                    .1,
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// Framework branch for a project, and the reason when it is a fallback
+/// rather than the branch built against the project's Solana version.
+pub fn pick_framework_branch(
+    solana_version: Option<&SolanaVersion>,
+) -> (&'static str, Option<String>) {
+    match solana_version {
+        Some(solana_version) => match solana_version.framework_branch() {
+            Some(branch) => (branch, None),
+            None => (
+                DEFAULT_FRAMEWORK_BRANCH,
+                Some(format!(
+                    "no framework branch for Solana {}.{}, falling back to {}",
+                    solana_version.version.major,
+                    solana_version.version.minor,
+                    DEFAULT_FRAMEWORK_BRANCH
+                )),
+            ),
+        },
+        None => {
+            let (_, branch) = newest_framework_branch();
+            (
+                branch,
+                Some(format!(
+                    "Solana version not found, falling back to {}",
+                    branch
+                )),
+            )
+        }
+    }
+}
+
+/// Newest Solana minor version with a framework branch, and that branch.
+pub fn newest_framework_branch() -> ((u64, u64), &'static str) {
+    FRAMEWORK_BRANCHES[FRAMEWORK_BRANCHES.len() - 1]
+}
+
+/// Detect the Solana version of the project, preferring the exact version
+/// resolved in `Cargo.lock` over requirements in the manifest.
+pub fn detect_solana_version(project_path: &Path, document: &Document) -> Option<SolanaVersion> {
+    if let Some(version) = find_lock_version(project_path) {
+        return Some(SolanaVersion {
+            version,
+            exact: true,
+            source: VersionSource::CargoLock,
+        });
+    }
+
+    for name in SOLANA_CRATES {
+        if let Some((version, source)) = find_manifest_version(project_path, document, name) {
+            return Some(SolanaVersion {
+                version,
+                exact: false,
+                source,
+            });
+        }
+    }
+
+    let (anchor, _) = find_manifest_version(project_path, document, "anchor-lang")?;
+    let (major, minor) = ANCHOR_SOLANA_VERSIONS
+        .iter()
+        .find(|(anchor_minor, _)| anchor.major == 0 && *anchor_minor == anchor.minor)
+        .map(|(_, solana)| *solana)?;
+    Some(SolanaVersion {
+        version: Version::new(major, minor, 0),
+        exact: false,
+        source: VersionSource::AnchorLang,
+    })
+}
+
+/// Whether the project's borsh has `borsh::to_vec`, added in borsh 0.10.
+/// Projects without a borsh requirement are assumed to use a current one.
+pub fn borsh_has_to_vec(project_path: &Path, document: &Document) -> bool {
+    find_manifest_version(project_path, document, "borsh")
+        .is_none_or(|(version, _)| version.major >= 1 || version.minor >= 10)
+}
+
+fn find_lock_version(project_path: &Path) -> Option<Version> {
+    let lock_path = project_path
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.exists())?;
+    let lock = fs::read_to_string(lock_path)
+        .ok()?
+        .parse::<Document>()
+        .ok()?;
+    let packages = lock.get("package")?.as_array_of_tables()?;
+    SOLANA_CRATES.iter().find_map(|name| {
+        packages
+            .iter()
+            .filter(|package| package.get("name").and_then(Item::as_str) == Some(name))
+            .filter_map(|package| package.get("version").and_then(Item::as_str))
+            .filter_map(|version| Version::parse(version).ok())
+            .max()
+    })
+}
+
+fn find_manifest_version(
+    project_path: &Path,
+    document: &Document,
+    name: &str,
+) -> Option<(Version, VersionSource)> {
+    let dependency = document.get("dependencies")?.get(name)?;
+    if dependency.get("workspace").and_then(Item::as_bool) == Some(true) {
+        let version = workspace_dependency_version(project_path, name)?;
+        return Some((version, VersionSource::Workspace));
+    }
+    Some((dependency_version(dependency)?, VersionSource::Manifest))
+}
+
+/// Version of a dependency declared in `[workspace.dependencies]` of the
+/// nearest workspace root above the project.
+fn workspace_dependency_version(project_path: &Path, name: &str) -> Option<Version> {
+    project_path.ancestors().skip(1).find_map(|dir| {
+        let manifest = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+        let document = manifest.parse::<Document>().ok()?;
+        dependency_version(document.get("workspace")?.get("dependencies")?.get(name)?)
+    })
+}
+
+/// Lowest version matching a dependency requirement, e.g. `1.14` for `^1.14`.
+fn dependency_version(dependency: &Item) -> Option<Version> {
+    let requirement = dependency
+        .as_str()
+        .or_else(|| dependency.get("version").and_then(Item::as_str))?;
+    let comparator = VersionReq::parse(requirement)
+        .ok()?
+        .comparators
+        .into_iter()
+        .next()?;
+    Some(Version::new(
+        comparator.major,
+        comparator.minor.unwrap_or(0),
+        comparator.patch.unwrap_or(0),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solana_version(major: u64, minor: u64, patch: u64, exact: bool) -> SolanaVersion {
+        SolanaVersion {
+            version: Version::new(major, minor, patch),
+            exact,
+            source: VersionSource::Manifest,
+        }
+    }
+
+    fn project(manifest: &str) -> (tempfile::TempDir, Document) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), manifest).unwrap();
+        (dir, manifest.parse().unwrap())
+    }
+
+    #[test]
+    fn picks_framework_branch_of_minor_version() {
+        assert_eq!(
+            solana_version(1, 9, 29, true).framework_branch(),
+            Some("solana-1.9")
+        );
+        assert_eq!(
+            solana_version(1, 11, 0, false).framework_branch(),
+            Some("solana-1.10")
+        );
+        assert_eq!(
+            solana_version(1, 14, 5, true).framework_branch(),
+            Some("solana-1.14")
+        );
+        assert_eq!(solana_version(1, 18, 0, false).framework_branch(), None);
+        assert_eq!(
+            solana_version(1, 8, 0, false).framework_branch(),
+            Some("solana-1.9")
+        );
+        assert_eq!(newest_framework_branch(), ((1, 14), "solana-1.14"));
+    }
+
+    #[test]
+    fn reports_framework_branch_fallback() {
+        assert_eq!(
+            pick_framework_branch(Some(&solana_version(1, 10, 0, false))),
+            ("solana-1.10", None)
+        );
+        assert_eq!(
+            pick_framework_branch(Some(&solana_version(1, 18, 0, false))),
+            (
+                DEFAULT_FRAMEWORK_BRANCH,
+                Some(String::from(
+                    "no framework branch for Solana 1.18, falling back to main"
+                ))
+            )
+        );
+        assert_eq!(
+            pick_framework_branch(None),
+            (
+                "solana-1.14",
+                Some(String::from(
+                    "Solana version not found, falling back to solana-1.14"
+                ))
+            )
+        );
+    }
+
+    #[test]
+    fn program_test_requirement_keeps_minor_version() {
+        assert_eq!(
+            solana_version(1, 14, 17, true).program_test_requirement(),
+            "~1.14.17"
+        );
+        assert_eq!(
+            solana_version(1, 14, 0, false).program_test_requirement(),
+            "~1.14"
+        );
+    }
+
+    #[test]
+    fn test_feature_follows_build_tool() {
+        assert_eq!(
+            solana_version(1, 14, 0, false).test_feature(),
+            TEST_BPF_FEATURE
+        );
+        assert_eq!(
+            solana_version(1, 16, 0, false).test_feature(),
+            TEST_SBF_FEATURE
+        );
+    }
+
+    #[test]
+    fn finds_highest_lock_version() {
+        let (dir, _) = project("[package]\nname = \"vault\"\n");
+        fs::write(
+            dir.path().join("Cargo.lock"),
+            "version = 3\n\n\
+             [[package]]\nname = \"solana-sdk\"\nversion = \"1.10.0\"\n\n\
+             [[package]]\nname = \"solana-program\"\nversion = \"1.14.2\"\n\n\
+             [[package]]\nname = \"solana-program\"\nversion = \"1.14.17\"\n",
+        )
+        .unwrap();
+        assert_eq!(find_lock_version(dir.path()), Some(Version::new(1, 14, 17)));
+
+        let member = dir.path().join("programs").join("vault");
+        fs::create_dir_all(&member).unwrap();
+        assert_eq!(find_lock_version(&member), Some(Version::new(1, 14, 17)));
+    }
+
+    #[test]
+    fn no_lock_version_without_solana_crates() {
+        let (dir, _) = project("[package]\nname = \"vault\"\n");
+        fs::write(
+            dir.path().join("Cargo.lock"),
+            "[[package]]\nname = \"serde\"\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+        assert_eq!(find_lock_version(dir.path()), None);
+    }
+
+    #[test]
+    fn detects_manifest_version() {
+        let (dir, document) = project(
+            "[package]\nname = \"vault\"\n\n[dependencies]\nsolana-program = { version = \"^1.10.3\" }\n",
+        );
+        let version = detect_solana_version(dir.path(), &document).unwrap();
+        assert_eq!(version.version, Version::new(1, 10, 3));
+        assert!(!version.exact);
+        assert_eq!(version.source, VersionSource::Manifest);
+    }
+
+    #[test]
+    fn detects_workspace_inherited_version() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(
+            root.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"vault\"]\n\n[workspace.dependencies]\nsolana-sdk = \"1.14\"\n",
+        )
+        .unwrap();
+        let member = root.path().join("vault");
+        fs::create_dir_all(&member).unwrap();
+        let document: Document =
+            "[package]\nname = \"vault\"\n\n[dependencies]\nsolana-sdk = { workspace = true }\n"
+                .parse()
+                .unwrap();
+        let version = detect_solana_version(&member, &document).unwrap();
+        assert_eq!(version.version, Version::new(1, 14, 0));
+        assert_eq!(version.source, VersionSource::Workspace);
+    }
+
+    #[test]
+    fn detects_version_from_anchor() {
+        let (dir, document) =
+            project("[package]\nname = \"vault\"\n\n[dependencies]\nanchor-lang = \"0.26.0\"\n");
+        let version = detect_solana_version(dir.path(), &document).unwrap();
+        assert_eq!(version.version, Version::new(1, 14, 0));
+        assert_eq!(version.source, VersionSource::AnchorLang);
+
+        let (dir, document) =
+            project("[package]\nname = \"vault\"\n\n[dependencies]\nanchor-lang = \"0.99\"\n");
+        assert!(detect_solana_version(dir.path(), &document).is_none());
+    }
+
+    #[test]
+    fn borsh_to_vec_from_manifest_requirement() {
+        let manifest = |borsh: &str| {
+            format!(
+                "[package]\nname = \"vault\"\n\n[dependencies]\n{}solana-program = \"1.18\"\n",
+                borsh
+            )
+        };
+        for (borsh, has_to_vec) in [
+            ("borsh = \"0.9.3\"\n", false),
+            ("borsh = { version = \"0.10\" }\n", true),
+            ("borsh = \"1.5\"\n", true),
+            ("", true),
+        ] {
+            let (dir, document) = project(&manifest(borsh));
+            assert_eq!(
+                borsh_has_to_vec(dir.path(), &document),
+                has_to_vec,
+                "{}",
+                borsh
+            );
+        }
+    }
+}
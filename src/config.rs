@@ -2,29 +2,35 @@
 //!
 //! See instructions in `commands.rs` to specify the path to your
 //! application's configuration file and/or command-line options
-//! for specifying it.
+//! for specifying it. Config files may set any subset of the keys, see
+//! [`layers`] for how they are merged.
+
+pub mod layers;
+pub mod schema;
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
+use std::fmt;
 use std::path::PathBuf;
 
 /// SolanaTest Configuration
-#[derive(Clone, Debug, Deserialize, Serialize)]
-#[serde(deny_unknown_fields)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct SolanaTestConfig {
     pub init: InitSection,
-}
-
-impl Default for SolanaTestConfig {
-    fn default() -> Self {
-        Self {
-            init: InitSection::default(),
-        }
-    }
+    pub accounts: AccountsSection,
+    pub keypairs: KeypairsSection,
+    /// Where the effective values came from
+    #[serde(skip)]
+    pub origins: layers::Origins,
+    /// Project config file given with `--config`
+    #[serde(skip)]
+    pub project_config: Option<PathBuf>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
-#[serde(deny_unknown_fields)]
+#[serde(default, deny_unknown_fields)]
 pub struct InitSection {
     // Path to project
     pub path: PathBuf,
@@ -34,10 +40,117 @@ pub struct InitSection {
     pub test_file_path: PathBuf,
     // Framework name
     pub framework_name: String,
-    // Framework branch to be used
-    pub framework_branch: String,
+    // Framework branch to be used, picked from the project's Solana version
+    // when not set
+    pub framework_branch: Option<String>,
+    // Framework git tag, takes precedence over the branch
+    pub framework_tag: Option<String>,
+    // Framework git revision, takes precedence over the tag
+    pub framework_rev: Option<String>,
+    // Local framework checkout, used instead of git
+    pub framework_path: Option<PathBuf>,
+    // Framework version published on crates.io, used instead of git
+    pub framework_version: Option<String>,
+    // Feature gating the generated tests, picked from the project's Solana
+    // version when not set
+    pub test_feature: Option<String>,
     // Should initialize with anchor
     pub is_anchor: Option<bool>,
+    // Test template name, picked from the project type when not set
+    pub template: Option<String>,
+    // Directory with user templates
+    pub template_dir: Option<PathBuf>,
+    // Extra template variables
+    pub vars: BTreeMap<String, String>,
+}
+
+/// Account fixtures added to the generated tests
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccountsSection {
+    // Directory with `solana account --output json` dumps
+    pub dir: Option<PathBuf>,
+    // RPC URL fetch reads accounts from, mainnet-beta when not set
+    pub url: Option<String>,
+}
+
+/// Keypairs loaded by the generated tests
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeypairsSection {
+    // Directory with the keypair files, `tests/fixtures/keys` of the project
+    // when not set
+    pub dir: Option<PathBuf>,
+    // Seed the keypairs are derived from, random when not set
+    pub seed: Option<String>,
+}
+
+impl InitSection {
+    /// Returns true when no framework source is configured, so the branch
+    /// follows the project's Solana version.
+    pub fn uses_default_branch(&self) -> bool {
+        self.framework_path.is_none()
+            && self.framework_version.is_none()
+            && self.framework_rev.is_none()
+            && self.framework_tag.is_none()
+            && self.framework_branch.is_none()
+    }
+
+    /// Where the framework dependency comes from. `default_branch` is used for
+    /// git sources when no branch, tag or revision is configured.
+    pub fn framework_source(&self, default_branch: &str) -> FrameworkSource {
+        if let Some(path) = &self.framework_path {
+            return FrameworkSource::Path(path.clone());
+        }
+        if let Some(version) = &self.framework_version {
+            return FrameworkSource::Registry(version.clone());
+        }
+        let reference = match (&self.framework_rev, &self.framework_tag) {
+            (Some(rev), _) => GitReference::Rev(rev.clone()),
+            (None, Some(tag)) => GitReference::Tag(tag.clone()),
+            (None, None) => GitReference::Branch(
+                self.framework_branch
+                    .clone()
+                    .unwrap_or_else(|| String::from(default_branch)),
+            ),
+        };
+        FrameworkSource::Git {
+            url: self.framework_repo_url.clone(),
+            reference,
+        }
+    }
+}
+
+/// Source of the framework dev-dependency
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FrameworkSource {
+    Git {
+        url: String,
+        reference: GitReference,
+    },
+    Path(PathBuf),
+    Registry(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GitReference {
+    Branch(String),
+    Tag(String),
+    Rev(String),
+}
+
+impl fmt::Display for FrameworkSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameworkSource::Git { url, reference } => match reference {
+                GitReference::Branch(branch) => write!(f, "{} branch {}", url, branch),
+                GitReference::Tag(tag) => write!(f, "{} tag {}", url, tag),
+                GitReference::Rev(rev) => write!(f, "{} rev {}", url, rev),
+            },
+            FrameworkSource::Path(path) => write!(f, "{}", path.display()),
+            FrameworkSource::Registry(version) => write!(f, "version {}", version),
+        }
+    }
 }
 
 impl Default for InitSection {
@@ -50,8 +163,58 @@ impl Default for InitSection {
                 "https://github.com/lowprivuser/solana-test-framework",
             ),
             framework_name: String::from("solana-test-framework"),
-            framework_branch: String::from("main"),
+            framework_branch: None,
+            framework_tag: None,
+            framework_rev: None,
+            framework_path: None,
+            framework_version: None,
+            test_feature: None,
             is_anchor: None,
+            template: None,
+            template_dir: None,
+            vars: BTreeMap::new(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn framework_source_precedence() {
+        let mut init = InitSection::default();
+        assert_eq!(
+            init.framework_source("solana-1.14"),
+            FrameworkSource::Git {
+                url: init.framework_repo_url.clone(),
+                reference: GitReference::Branch(String::from("solana-1.14")),
+            }
+        );
+
+        init.framework_branch = Some(String::from("develop"));
+        init.framework_tag = Some(String::from("v0.1.0"));
+        assert_eq!(
+            init.framework_source("solana-1.14").to_string(),
+            format!("{} tag v0.1.0", init.framework_repo_url)
+        );
+
+        init.framework_rev = Some(String::from("0123abc"));
+        assert_eq!(
+            init.framework_source("solana-1.14").to_string(),
+            format!("{} rev 0123abc", init.framework_repo_url)
+        );
+
+        init.framework_version = Some(String::from("0.3"));
+        assert_eq!(
+            init.framework_source("solana-1.14"),
+            FrameworkSource::Registry(String::from("0.3"))
+        );
+
+        init.framework_path = Some(PathBuf::from("/src/framework"));
+        assert_eq!(
+            init.framework_source("solana-1.14"),
+            FrameworkSource::Path(PathBuf::from("/src/framework"))
+        );
+    }
+}
@@ -0,0 +1,596 @@
+//! Layered configuration
+//!
+//! Settings are merged from, in increasing order of precedence, the defaults,
+//! the global `<config dir>/solana-test/solana-test.toml`, the
+//! `[workspace.metadata.solana-test]` and `[package.metadata.solana-test]`
+//! tables of the project's `Cargo.toml`, the project `solana-test.toml`,
+//! `SOLANA_TEST_*` environment variables and command line flags. Every layer
+//! may set any subset of the keys.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::value::{Table, Value};
+
+use super::schema::{self, KeyKind};
+use super::SolanaTestConfig;
+use crate::commands::CONFIG_FILE;
+use crate::error::{Error, ErrorKind};
+
+/// Prefix of the environment variables overriding `[init]` keys, e.g.
+/// `SOLANA_TEST_FRAMEWORK_BRANCH`
+pub const ENV_PREFIX: &str = "SOLANA_TEST_";
+
+/// Prefix of the environment variables setting template variables, e.g.
+/// `SOLANA_TEST_VAR_AUTHORITY`
+const ENV_VAR_PREFIX: &str = "VAR_";
+
+/// Layer an effective value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    Default,
+    Global(PathBuf),
+    /// Metadata table of a manifest
+    Metadata {
+        manifest: PathBuf,
+        table: &'static str,
+    },
+    Project(PathBuf),
+    Env(String),
+    Cli,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => write!(f, "default"),
+            Origin::Global(path) => write!(f, "global config {}", path.display()),
+            Origin::Metadata { manifest, table } => {
+                write!(f, "[{}] in {}", table, manifest.display())
+            }
+            Origin::Project(path) => write!(f, "project config {}", path.display()),
+            Origin::Env(name) => write!(f, "environment {}", name),
+            Origin::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+/// Origin of each value set by a layer, keyed by `<section>.<key>`. Keys
+/// missing here have their default value.
+pub type Origins = BTreeMap<String, Origin>;
+
+/// Location of the user-global config file.
+pub fn global_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("solana-test").join(CONFIG_FILE))
+}
+
+/// Project config file - the one given with `--config`, or
+/// `solana-test.toml` in the current directory when it exists.
+pub fn project_config_path(config: Option<&Path>) -> Result<Option<PathBuf>, Error> {
+    match config {
+        Some(path) if !path.exists() => Err(ErrorKind::Config
+            .context(format!("{} not found", path.display()))
+            .into()),
+        Some(path) => Ok(Some(path.to_path_buf())),
+        None => Ok(Some(PathBuf::from(CONFIG_FILE)).filter(|path| path.exists())),
+    }
+}
+
+/// Metadata tables of a manifest, in increasing order of precedence
+const METADATA_TABLES: &[&str] = &[
+    "workspace.metadata.solana-test",
+    "package.metadata.solana-test",
+];
+
+/// Keys that cannot be set in manifest metadata
+const NON_METADATA_KEYS: &[&str] = &["path"];
+
+/// Merge the global config, the metadata of the project's manifest, the
+/// project config and the environment.
+pub fn load(
+    project_config: Option<&Path>,
+    manifest: Option<&Path>,
+) -> Result<SolanaTestConfig, Error> {
+    let mut layers = Layers::default();
+    if let Some(global) = global_config_path().filter(|path| path.exists()) {
+        layers.merge_file(&global, Origin::Global(global.clone()))?;
+    }
+    if let Some(manifest) = manifest.filter(|path| path.exists()) {
+        layers.merge_metadata(manifest)?;
+    }
+    if let Some(project) = project_config {
+        layers.merge_file(project, Origin::Project(project.to_path_buf()))?;
+    }
+    layers.merge_env(std::env::vars())?;
+    layers.finish()
+}
+
+#[derive(Debug, Default)]
+struct Layers {
+    merged: Table,
+    origins: Origins,
+}
+
+impl Layers {
+    fn merge_file(&mut self, path: &Path, origin: Origin) -> Result<(), Error> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ErrorKind::Config.context(format!("{}: {}", path.display(), e)))?;
+        let mut table: Table = toml::from_str(&contents)
+            .map_err(|e| ErrorKind::Config.context(format!("{}: {}", path.display(), e)))?;
+        let config_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        for (section, values) in table.iter_mut() {
+            if let Value::Table(values) = values {
+                resolve_paths(section, values, config_dir);
+            }
+        }
+        // Check the layer on its own so errors point to the right file
+        Value::Table(table.clone())
+            .try_into::<SolanaTestConfig>()
+            .map_err(|e| ErrorKind::Config.context(format!("{}: {}", path.display(), e)))?;
+        self.merge(table, &origin);
+        Ok(())
+    }
+
+    /// Merge the `solana-test` metadata tables of a manifest. Relative paths
+    /// are resolved against the manifest directory.
+    fn merge_metadata(&mut self, manifest: &Path) -> Result<(), Error> {
+        // Manifest errors are reported by the commands reading it
+        let document: Value = match fs::read_to_string(manifest)
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok())
+        {
+            Some(document) => document,
+            None => return Ok(()),
+        };
+        let manifest_dir = manifest.parent().unwrap_or_else(|| Path::new(""));
+        for table_name in METADATA_TABLES {
+            let metadata = table_name
+                .split('.')
+                .try_fold(&document, |value, key| value.get(key));
+            let mut metadata = match metadata {
+                Some(Value::Table(metadata)) => metadata.clone(),
+                Some(_) => {
+                    return Err(ErrorKind::Config
+                        .context(format!(
+                            "{}: [{}] is not a table",
+                            manifest.display(),
+                            table_name
+                        ))
+                        .into())
+                }
+                None => continue,
+            };
+            if let Some(key) = NON_METADATA_KEYS
+                .iter()
+                .find(|key| metadata.contains_key(**key))
+            {
+                return Err(ErrorKind::Config
+                    .context(format!(
+                        "{}: {} cannot be set in [{}]",
+                        manifest.display(),
+                        key,
+                        table_name
+                    ))
+                    .into());
+            }
+
+            // Other sections are nested tables, e.g. `[package.metadata.solana-test.accounts]`
+            let mut table = Table::new();
+            for section in schema::SECTIONS
+                .iter()
+                .filter(|section| section.name != "init")
+            {
+                if let Some(Value::Table(values)) = metadata.remove(section.name) {
+                    table.insert(section.name.to_string(), Value::Table(values));
+                }
+            }
+            table.insert(String::from("init"), Value::Table(metadata));
+            for (section, values) in table.iter_mut() {
+                if let Value::Table(values) = values {
+                    resolve_paths(section, values, manifest_dir);
+                }
+            }
+            Value::Table(table.clone())
+                .try_into::<SolanaTestConfig>()
+                .map_err(|e| {
+                    ErrorKind::Config.context(format!(
+                        "{}: [{}]: {}",
+                        manifest.display(),
+                        table_name,
+                        e
+                    ))
+                })?;
+            self.merge(
+                table,
+                &Origin::Metadata {
+                    manifest: manifest.to_path_buf(),
+                    table: table_name,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Set `init` keys from `SOLANA_TEST_<KEY>`, keys of other sections from
+    /// `SOLANA_TEST_<SECTION>_<KEY>` and template variables from
+    /// `SOLANA_TEST_VAR_<NAME>`.
+    fn merge_env(&mut self, vars: impl Iterator<Item = (String, String)>) -> Result<(), Error> {
+        for (name, raw) in vars {
+            let key = match name.strip_prefix(ENV_PREFIX) {
+                Some(key) => key.to_lowercase(),
+                None => continue,
+            };
+            if let Some(var) = key.strip_prefix(&ENV_VAR_PREFIX.to_lowercase()) {
+                let mut template_vars = Table::new();
+                template_vars.insert(var.to_string(), Value::String(raw));
+                self.insert(
+                    "init",
+                    "vars",
+                    Value::Table(template_vars),
+                    &Origin::Env(name),
+                );
+                continue;
+            }
+            let (section, key) = env_key(&key);
+            let kind = match schema::key(section, &key) {
+                Some(schema_key) if schema_key.kind != KeyKind::Vars => schema_key.kind,
+                _ => continue,
+            };
+            let value = if kind == KeyKind::Bool {
+                let value = raw.parse::<bool>().map_err(|_| {
+                    ErrorKind::Config.context(format!("{} must be true or false", name))
+                })?;
+                Value::Boolean(value)
+            } else {
+                Value::String(raw)
+            };
+            self.insert(section, &key, value, &Origin::Env(name));
+        }
+        Ok(())
+    }
+
+    fn merge(&mut self, table: Table, origin: &Origin) {
+        for (section, values) in table {
+            if let Value::Table(values) = values {
+                for (key, value) in values {
+                    self.insert(&section, &key, value, origin);
+                }
+            }
+        }
+    }
+
+    /// Set `section.key`, merging the `vars` tables entry by entry.
+    fn insert(&mut self, section: &str, key: &str, value: Value, origin: &Origin) {
+        let merged = self
+            .merged
+            .entry(section.to_string())
+            .or_insert_with(|| Value::Table(Table::new()));
+        let merged = match merged {
+            Value::Table(merged) => merged,
+            _ => return,
+        };
+        match (key, value) {
+            ("vars", Value::Table(vars)) => {
+                let merged_vars = merged
+                    .entry(key.to_string())
+                    .or_insert_with(|| Value::Table(Table::new()));
+                if let Value::Table(merged_vars) = merged_vars {
+                    for (name, value) in vars {
+                        self.origins
+                            .insert(format!("{}.vars.{}", section, name), origin.clone());
+                        merged_vars.insert(name, value);
+                    }
+                }
+            }
+            (_, value) => {
+                self.origins
+                    .insert(format!("{}.{}", section, key), origin.clone());
+                merged.insert(key.to_string(), value);
+            }
+        }
+    }
+
+    fn finish(self) -> Result<SolanaTestConfig, Error> {
+        let mut config: SolanaTestConfig = Value::Table(self.merged)
+            .try_into()
+            .map_err(|e| ErrorKind::Config.context(e))?;
+        config.origins = self.origins;
+        Ok(config)
+    }
+}
+
+/// Section and key named by the lowercase name of an environment variable
+/// without the prefix.
+fn env_key(name: &str) -> (&'static str, String) {
+    for section in schema::SECTIONS
+        .iter()
+        .filter(|section| section.name != "init")
+    {
+        if let Some(key) = name.strip_prefix(&format!("{}_", section.name)) {
+            return (section.name, key.to_string());
+        }
+    }
+    ("init", name.to_string())
+}
+
+/// Resolve relative paths of a layer against the directory of the config
+/// file or manifest setting them.
+fn resolve_paths(section: &str, values: &mut Table, dir: &Path) {
+    for (name, value) in values.iter_mut() {
+        let is_path = schema::key(section, name).is_some_and(|key| {
+            matches!(
+                key.kind,
+                KeyKind::Path | KeyKind::TestFile | KeyKind::OutputDir
+            )
+        });
+        if let (true, Value::String(path)) = (is_path, &value) {
+            if Path::new(path).is_relative() {
+                let path = dir.join(path).to_string_lossy().into_owned();
+                *value = Value::String(path);
+            }
+        }
+    }
+}
+
+/// Effective values as `(key, value)` pairs with `<section>.<key>` keys,
+/// template variables flattened to `init.vars.<name>`. Unset keys are
+/// skipped.
+pub fn effective_values(config: &SolanaTestConfig) -> Vec<(String, serde_json::Value)> {
+    let mut values = Vec::new();
+    let sections = match serde_json::to_value(config) {
+        Ok(serde_json::Value::Object(sections)) => sections,
+        _ => return values,
+    };
+    for (section, keys) in sections {
+        let keys = match keys {
+            serde_json::Value::Object(keys) => keys,
+            _ => continue,
+        };
+        for (key, value) in keys {
+            match value {
+                serde_json::Value::Null => {}
+                serde_json::Value::Object(vars) => {
+                    for (name, value) in vars {
+                        values.push((format!("{}.{}.{}", section, key, name), value));
+                    }
+                }
+                value => values.push((format!("{}.{}", section, key), value)),
+            }
+        }
+    }
+    values
+}
+
+/// Mark the values that changed between `before` and `config` as set on
+/// the command line.
+pub fn mark_cli_overrides(config: &mut SolanaTestConfig, before: &SolanaTestConfig) {
+    let before: BTreeMap<String, serde_json::Value> =
+        effective_values(before).into_iter().collect();
+    for (key, value) in effective_values(config) {
+        if before.get(&key) != Some(&value) {
+            config.origins.insert(key, Origin::Cli);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn env(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn later_layers_take_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let global = write(
+            dir.path(),
+            "global.toml",
+            "[init]\nframework_branch = \"global\"\ntemplate = \"global\"\ntest_feature = \"global\"\n\
+             [init.vars]\nauthority = \"global\"\nmint = \"global\"\n",
+        );
+        let manifest = write(
+            dir.path(),
+            "Cargo.toml",
+            "[package]\nname = \"vault\"\n\n\
+             [package.metadata.solana-test]\ntemplate = \"metadata\"\ntest_feature = \"metadata\"\n",
+        );
+        let project = write(
+            dir.path(),
+            "solana-test.toml",
+            "[init]\ntest_feature = \"project\"\n[init.vars]\nmint = \"project\"\n",
+        );
+
+        let mut layers = Layers::default();
+        layers
+            .merge_file(&global, Origin::Global(global.clone()))
+            .unwrap();
+        layers.merge_metadata(&manifest).unwrap();
+        layers
+            .merge_file(&project, Origin::Project(project.clone()))
+            .unwrap();
+        layers
+            .merge_env(env(&[
+                ("SOLANA_TEST_FRAMEWORK_BRANCH", "env"),
+                ("SOLANA_TEST_VAR_AUTHORITY", "env"),
+                ("OTHER", "ignored"),
+            ]))
+            .unwrap();
+        let config = layers.finish().unwrap();
+
+        assert_eq!(config.init.framework_branch.as_deref(), Some("env"));
+        assert_eq!(config.init.template.as_deref(), Some("metadata"));
+        assert_eq!(config.init.test_feature.as_deref(), Some("project"));
+        assert_eq!(config.init.vars["authority"], "env");
+        assert_eq!(config.init.vars["mint"], "project");
+
+        let origins = &config.origins;
+        assert_eq!(
+            origins["init.framework_branch"],
+            Origin::Env(String::from("SOLANA_TEST_FRAMEWORK_BRANCH"))
+        );
+        assert_eq!(
+            origins["init.template"],
+            Origin::Metadata {
+                manifest: manifest.clone(),
+                table: "package.metadata.solana-test"
+            }
+        );
+        assert_eq!(origins["init.test_feature"], Origin::Project(project));
+        assert_eq!(
+            origins["init.vars.authority"],
+            Origin::Env(String::from("SOLANA_TEST_VAR_AUTHORITY"))
+        );
+        assert!(!origins.contains_key("init.framework_name"));
+    }
+
+    #[test]
+    fn relative_paths_are_resolved_against_the_layer() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = write(
+            dir.path(),
+            "config/solana-test.toml",
+            "[init]\npath = \"../program\"\ntest_file_path = \"tests/poc.rs\"\n\
+             framework_name = \"local/name\"\n\
+             [keypairs]\ndir = \"keys\"\n[accounts]\ndir = \"/abs/accounts\"\n",
+        );
+        let manifest = write(
+            dir.path(),
+            "program/Cargo.toml",
+            "[package]\nname = \"vault\"\n\n\
+             [package.metadata.solana-test]\ntemplate_dir = \"templates\"\n",
+        );
+
+        let mut layers = Layers::default();
+        layers.merge_metadata(&manifest).unwrap();
+        layers
+            .merge_file(&project, Origin::Project(project.clone()))
+            .unwrap();
+        let config = layers.finish().unwrap();
+
+        let config_dir = dir.path().join("config");
+        assert_eq!(config.init.path, config_dir.join("../program"));
+        assert_eq!(config.init.test_file_path, config_dir.join("tests/poc.rs"));
+        assert_eq!(config.keypairs.dir, Some(config_dir.join("keys")));
+        assert_eq!(config.accounts.dir, Some(PathBuf::from("/abs/accounts")));
+        // Only path keys are resolved
+        assert_eq!(config.init.framework_name, "local/name");
+        assert_eq!(
+            config.init.template_dir,
+            Some(dir.path().join("program/templates"))
+        );
+    }
+
+    #[test]
+    fn metadata_cannot_set_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = write(
+            dir.path(),
+            "Cargo.toml",
+            "[package]\nname = \"vault\"\n\n[package.metadata.solana-test]\npath = \"..\"\n",
+        );
+        let error = Layers::default().merge_metadata(&manifest).unwrap_err();
+        assert!(error.to_string().contains("path cannot be set"));
+    }
+
+    #[test]
+    fn env_values_are_typed_by_the_schema() {
+        let mut layers = Layers::default();
+        layers
+            .merge_env(env(&[
+                ("SOLANA_TEST_IS_ANCHOR", "true"),
+                ("SOLANA_TEST_KEYPAIRS_SEED", "seed"),
+                ("SOLANA_TEST_UNKNOWN_KEY", "ignored"),
+            ]))
+            .unwrap();
+        let config = layers.finish().unwrap();
+        assert_eq!(config.init.is_anchor, Some(true));
+        assert_eq!(config.keypairs.seed.as_deref(), Some("seed"));
+
+        let error = Layers::default()
+            .merge_env(env(&[("SOLANA_TEST_IS_ANCHOR", "yes")]))
+            .unwrap_err();
+        assert!(error.to_string().contains("must be true or false"));
+    }
+
+    #[test]
+    fn package_metadata_overrides_workspace_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = write(
+            dir.path(),
+            "Cargo.toml",
+            "[package]\nname = \"vault\"\n\n\
+             [package.metadata.solana-test]\ntemplate = \"package\"\n\n\
+             [package.metadata.solana-test.accounts]\ndir = \"fixtures\"\n\n\
+             [workspace.metadata.solana-test]\ntemplate = \"workspace\"\ntest_feature = \"workspace\"\n",
+        );
+        let mut layers = Layers::default();
+        layers.merge_metadata(&manifest).unwrap();
+        let config = layers.finish().unwrap();
+
+        assert_eq!(config.init.template.as_deref(), Some("package"));
+        assert_eq!(config.init.test_feature.as_deref(), Some("workspace"));
+        assert_eq!(config.accounts.dir, Some(dir.path().join("fixtures")));
+        assert_eq!(
+            config.origins["init.test_feature"],
+            Origin::Metadata {
+                manifest: manifest.clone(),
+                table: "workspace.metadata.solana-test"
+            }
+        );
+        assert_eq!(
+            config.origins["accounts.dir"],
+            Origin::Metadata {
+                manifest,
+                table: "package.metadata.solana-test"
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_metadata_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let not_a_table = write(
+            dir.path(),
+            "table/Cargo.toml",
+            "[package]\nname = \"vault\"\nmetadata = { solana-test = 1 }\n",
+        );
+        let error = Layers::default().merge_metadata(&not_a_table).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("[package.metadata.solana-test] is not a table"));
+
+        let unknown_key = write(
+            dir.path(),
+            "key/Cargo.toml",
+            "[package]\nname = \"vault\"\n\n[package.metadata.solana-test]\nunknown = 1\n",
+        );
+        let error = Layers::default().merge_metadata(&unknown_key).unwrap_err();
+        assert!(error.to_string().contains("[package.metadata.solana-test]"));
+        assert!(error.to_string().contains("unknown"));
+    }
+
+    #[test]
+    fn unreadable_manifest_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = write(dir.path(), "Cargo.toml", "[package\n");
+        let mut layers = Layers::default();
+        layers.merge_metadata(&manifest).unwrap();
+        layers
+            .merge_metadata(&dir.path().join("missing/Cargo.toml"))
+            .unwrap();
+        assert!(layers.finish().unwrap().origins.is_empty());
+    }
+}
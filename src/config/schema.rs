@@ -0,0 +1,468 @@
+//! Keys of the config file, used to write a commented `solana-test.toml`
+//! and to validate one
+
+use serde::Serialize;
+use std::path::Path;
+use toml::Value;
+
+use super::layers::{self, Origin};
+use super::SolanaTestConfig;
+
+/// Expected value of a key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyKind {
+    String,
+    Bool,
+    /// Path that has to exist
+    Path,
+    /// `.rs` file created by init
+    TestFile,
+    /// Directory created by init
+    OutputDir,
+    /// Table of string values
+    Vars,
+}
+
+/// Documented `[init]` key
+#[derive(Clone, Copy, Debug)]
+pub struct Key {
+    pub name: &'static str,
+    pub kind: KeyKind,
+    pub description: &'static str,
+    /// Value shown for keys without a default
+    pub example: &'static str,
+}
+
+pub const INIT_KEYS: &[Key] = &[
+    Key {
+        name: "path",
+        kind: KeyKind::Path,
+        description: "Path to the tested project",
+        example: "\".\"",
+    },
+    Key {
+        name: "framework_repo_url",
+        kind: KeyKind::String,
+        description: "URL of the framework git repository",
+        example: "\"https://github.com/lowprivuser/solana-test-framework\"",
+    },
+    Key {
+        name: "test_file_path",
+        kind: KeyKind::TestFile,
+        description: "Test file generated by init",
+        example: "\"tests/poc.rs\"",
+    },
+    Key {
+        name: "framework_name",
+        kind: KeyKind::String,
+        description: "Name of the framework dev-dependency",
+        example: "\"solana-test-framework\"",
+    },
+    Key {
+        name: "framework_branch",
+        kind: KeyKind::String,
+        description: "Framework git branch, picked from the project's Solana version when not set",
+        example: "\"solana-1.14\"",
+    },
+    Key {
+        name: "framework_tag",
+        kind: KeyKind::String,
+        description: "Framework git tag, takes precedence over the branch",
+        example: "\"v0.1.0\"",
+    },
+    Key {
+        name: "framework_rev",
+        kind: KeyKind::String,
+        description: "Framework git revision, takes precedence over the tag",
+        example: "\"0123abc\"",
+    },
+    Key {
+        name: "framework_path",
+        kind: KeyKind::Path,
+        description: "Local framework checkout, used instead of git",
+        example: "\"../solana-test-framework\"",
+    },
+    Key {
+        name: "framework_version",
+        kind: KeyKind::String,
+        description: "Framework version published on crates.io, used instead of git",
+        example: "\"0.1\"",
+    },
+    Key {
+        name: "test_feature",
+        kind: KeyKind::String,
+        description: "Feature gating the generated tests, picked from the project's Solana version when not set",
+        example: "\"test-sbf\"",
+    },
+    Key {
+        name: "is_anchor",
+        kind: KeyKind::Bool,
+        description: "Whether the project uses Anchor, detected from its dependencies when not set",
+        example: "true",
+    },
+    Key {
+        name: "template",
+        kind: KeyKind::String,
+        description: "Test template, picked from the project type when not set",
+        example: "\"spl-token\"",
+    },
+    Key {
+        name: "template_dir",
+        kind: KeyKind::Path,
+        description: "Directory with user templates",
+        example: "\"templates\"",
+    },
+    Key {
+        name: "vars",
+        kind: KeyKind::Vars,
+        description: "Extra template variables",
+        example: "",
+    },
+];
+
+pub const ACCOUNTS_KEYS: &[Key] = &[
+    Key {
+        name: "dir",
+        kind: KeyKind::Path,
+        description: "Directory with account fixtures in the `solana account --output json` format",
+        example: "\"tests/fixtures/accounts\"",
+    },
+    Key {
+        name: "url",
+        kind: KeyKind::String,
+        description: "RPC URL or moniker (mainnet-beta, devnet, testnet, localhost) fetch reads accounts from",
+        example: "\"devnet\"",
+    },
+];
+
+pub const KEYPAIRS_KEYS: &[Key] = &[
+    Key {
+        name: "dir",
+        kind: KeyKind::OutputDir,
+        description: "Directory with the keypairs of the generated tests, tests/fixtures/keys of the project when not set",
+        example: "\"tests/fixtures/keys\"",
+    },
+    Key {
+        name: "seed",
+        kind: KeyKind::String,
+        description: "Seed the keypairs are derived from, random keypairs are generated when not set",
+        example: "\"poc\"",
+    },
+];
+
+/// Documented table of the config file
+#[derive(Clone, Copy, Debug)]
+pub struct Section {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub keys: &'static [Key],
+}
+
+pub const SECTIONS: &[Section] = &[
+    Section {
+        name: "init",
+        description: "Settings of init",
+        keys: INIT_KEYS,
+    },
+    Section {
+        name: "accounts",
+        description: "Accounts added to the generated tests",
+        keys: ACCOUNTS_KEYS,
+    },
+    Section {
+        name: "keypairs",
+        description: "Keypairs loaded by the generated tests",
+        keys: KEYPAIRS_KEYS,
+    },
+];
+
+/// Documented key of a section by name.
+pub fn key(section: &str, name: &str) -> Option<&'static Key> {
+    SECTIONS
+        .iter()
+        .find(|candidate| candidate.name == section)?
+        .keys
+        .iter()
+        .find(|key| key.name == name)
+}
+
+/// Render a commented config file. Values set by a config file or the
+/// environment are written out, defaults are commented out.
+pub fn render(config: &SolanaTestConfig) -> String {
+    let values = layers::effective_values(config);
+    let is_set = |key: &str| {
+        config
+            .origins
+            .get(key)
+            .is_some_and(|origin| *origin != Origin::Default)
+    };
+    let mut contents = String::from(
+        "# solana-test configuration\n\
+         #\n\
+         # Settings here override the global config and are overridden by\n\
+         # SOLANA_TEST_* environment variables and command line flags.\n\
+         # Commented out keys show the default or an example value.\n",
+    );
+    for section in SECTIONS {
+        contents.push_str(&format!(
+            "\n# {}\n[{}]\n",
+            section.description, section.name
+        ));
+        for key in section.keys.iter().filter(|key| key.kind != KeyKind::Vars) {
+            let full_key = format!("{}.{}", section.name, key.name);
+            contents.push_str(&format!("\n# {}\n", key.description));
+            match values.iter().find(|(name, _)| *name == full_key) {
+                Some((_, value)) if is_set(&full_key) => {
+                    contents.push_str(&format!("{} = {}\n", key.name, value));
+                }
+                Some((_, value)) => contents.push_str(&format!("# {} = {}\n", key.name, value)),
+                None => contents.push_str(&format!("# {} = {}\n", key.name, key.example)),
+            }
+        }
+        for key in section.keys.iter().filter(|key| key.kind == KeyKind::Vars) {
+            let prefix = format!("{}.{}.", section.name, key.name);
+            contents.push_str(&format!(
+                "\n# {}, used as {{{{name}}}} in templates\n[{}.{}]\n",
+                key.description, section.name, key.name
+            ));
+            let vars: Vec<&(String, serde_json::Value)> = values
+                .iter()
+                .filter(|(name, _)| name.starts_with(&prefix))
+                .collect();
+            if vars.is_empty() {
+                contents.push_str("# authority = \"11111111111111111111111111111111\"\n");
+            }
+            for (name, value) in vars {
+                let var = &name[prefix.len()..];
+                if is_set(name) {
+                    contents.push_str(&format!("{} = {}\n", var, value));
+                } else {
+                    contents.push_str(&format!("# {} = {}\n", var, value));
+                }
+            }
+        }
+    }
+    contents
+}
+
+/// Problem found in a config file
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    /// 1-based line, when it could be located
+    pub line: Option<usize>,
+    pub key: String,
+    pub message: String,
+}
+
+/// Check a config file for syntax errors, unknown keys, wrong types and
+/// missing paths. Relative paths are checked against `dir`, the directory of
+/// the file.
+pub fn validate(contents: &str, dir: &Path) -> Vec<Issue> {
+    let root = match contents.parse::<Value>() {
+        Ok(Value::Table(root)) => root,
+        Ok(_) => return Vec::new(),
+        Err(e) => {
+            return vec![Issue {
+                line: e.line_col().map(|(line, _)| line + 1),
+                key: String::new(),
+                message: e.to_string(),
+            }]
+        }
+    };
+
+    let mut issues = Vec::new();
+    for (section_name, value) in &root {
+        let section = match SECTIONS.iter().find(|section| section.name == section_name) {
+            Some(section) => section,
+            None => {
+                let expected: Vec<String> = SECTIONS
+                    .iter()
+                    .map(|section| format!("[{}]", section.name))
+                    .collect();
+                issues.push(Issue {
+                    line: find_line(contents, section_name, None)
+                        .or_else(|| find_line(contents, "", Some(section_name))),
+                    key: section_name.clone(),
+                    message: format!("unknown section, expected {}", expected.join(" or ")),
+                });
+                continue;
+            }
+        };
+        let table = match value {
+            Value::Table(table) => table,
+            value => {
+                issues.push(Issue {
+                    line: find_line(contents, "", Some(section_name)),
+                    key: section_name.clone(),
+                    message: format!("expected a table, found {}", value.type_str()),
+                });
+                continue;
+            }
+        };
+        for (name, value) in table {
+            let full_key = format!("{}.{}", section.name, name);
+            let line = find_line(contents, section.name, Some(name))
+                .or_else(|| find_line(contents, "", Some(&full_key)))
+                .or_else(|| find_line(contents, &full_key, None));
+            let issue = |message: String| Issue {
+                line,
+                key: full_key.clone(),
+                message,
+            };
+            let key = match section.keys.iter().find(|key| key.name == name) {
+                Some(key) => key,
+                None => {
+                    issues.push(issue(String::from("unknown key")));
+                    continue;
+                }
+            };
+            match (key.kind, value) {
+                (KeyKind::Bool, Value::Boolean(_)) => {}
+                (KeyKind::Bool, value) => issues.push(issue(format!(
+                    "expected a boolean, found {}",
+                    value.type_str()
+                ))),
+                (KeyKind::Vars, Value::Table(vars)) => {
+                    for (var, value) in vars {
+                        if !value.is_str() {
+                            issues.push(Issue {
+                                line: find_line(contents, &full_key, Some(var)).or_else(|| {
+                                    find_line(
+                                        contents,
+                                        section.name,
+                                        Some(&format!("{}.{}", name, var)),
+                                    )
+                                }),
+                                key: format!("{}.{}", full_key, var),
+                                message: format!("expected a string, found {}", value.type_str()),
+                            });
+                        }
+                    }
+                }
+                (KeyKind::Vars, value) => issues.push(issue(format!(
+                    "expected a table, found {}",
+                    value.type_str()
+                ))),
+                (_, Value::String(string)) => match key.kind {
+                    KeyKind::Path if !dir.join(string).exists() => {
+                        issues.push(issue(format!("{} does not exist", string)))
+                    }
+                    KeyKind::TestFile if !string.ends_with(".rs") => {
+                        issues.push(issue(format!("{} is not a .rs file", string)))
+                    }
+                    _ => {}
+                },
+                (_, value) => issues.push(issue(format!(
+                    "expected a string, found {}",
+                    value.type_str()
+                ))),
+            }
+        }
+    }
+    issues.sort_by_key(|issue| issue.line);
+    issues
+}
+
+/// 1-based line of `key` in `[table]`, or of the `[table]` header itself
+/// when `key` is `None`. Use an empty table for top-level keys.
+fn find_line(contents: &str, table: &str, key: Option<&str>) -> Option<usize> {
+    let mut current = "";
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            current = header
+                .trim_start_matches('[')
+                .split(']')
+                .next()
+                .unwrap_or_default()
+                .trim();
+            if key.is_none() && current == table {
+                return Some(index + 1);
+            }
+            continue;
+        }
+        let (key, name) = match (key, line.split_once('=')) {
+            (Some(key), Some((name, _))) => (key, name),
+            _ => continue,
+        };
+        if current == table && name.trim().trim_matches('"') == key {
+            return Some(index + 1);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn messages(issues: &[Issue]) -> Vec<(Option<usize>, &str, &str)> {
+        issues
+            .iter()
+            .map(|issue| (issue.line, issue.key.as_str(), issue.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn paths_are_checked_against_the_file_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("program")).unwrap();
+        let contents = "[init]\npath = \"program\"\ntemplate_dir = \"templates\"\n";
+
+        let issues = validate(contents, dir.path());
+        assert_eq!(
+            messages(&issues),
+            [(Some(3), "init.template_dir", "templates does not exist")]
+        );
+
+        let absolute = format!("[init]\npath = \"{}\"\n", dir.path().display());
+        assert!(validate(&absolute, Path::new("/nonexistent")).is_empty());
+    }
+
+    #[test]
+    fn reports_unknown_keys_and_wrong_types() {
+        let contents = "[init]\n\
+                        is_anchor = \"yes\"\n\
+                        test_file_path = \"tests/poc.txt\"\n\
+                        unknown = 1\n\
+                        [init.vars]\n\
+                        authority = 1\n\
+                        [other]\n";
+        let issues = validate(contents, Path::new(""));
+        assert_eq!(
+            messages(&issues),
+            [
+                (
+                    Some(2),
+                    "init.is_anchor",
+                    "expected a boolean, found string"
+                ),
+                (
+                    Some(3),
+                    "init.test_file_path",
+                    "tests/poc.txt is not a .rs file"
+                ),
+                (Some(4), "init.unknown", "unknown key"),
+                (
+                    Some(6),
+                    "init.vars.authority",
+                    "expected a string, found integer"
+                ),
+                (
+                    Some(7),
+                    "other",
+                    "unknown section, expected [init] or [accounts] or [keypairs]"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_their_line() {
+        let issues = validate("[init]\npath = \n", Path::new(""));
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, Some(2));
+        assert!(issues[0].key.is_empty());
+    }
+}
@@ -1,6 +1,7 @@
 //! Error types
 
 use abscissa_core::error::{BoxError, Context};
+use serde::Serialize;
 use std::{
     fmt::{self, Display},
     io,
@@ -9,7 +10,7 @@ use std::{
 use thiserror::Error;
 
 /// Kinds of errors
-#[derive(Copy, Clone, Debug, Eq, Error, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Error, PartialEq, Serialize)]
 pub enum ErrorKind {
     /// Error in configuration file
     #[error("config error")]
@@ -23,8 +24,82 @@ pub enum ErrorKind {
     #[error("Cargo file not exists. Please specify path manually")]
     MissingCargoFile,
 
-    #[error("Incorrect project Cargo.toml - make sure to select package Cargo.toml. Workspace toml is not allowed")]
+    #[error("Incorrect project Cargo.toml - make sure to select package or workspace Cargo.toml")]
     IncorrectCargoFile,
+
+    /// Cargo.toml exists but cannot be read
+    #[error("Cannot read Cargo.toml")]
+    UnreadableManifest,
+
+    /// Cargo.toml is not valid TOML
+    #[error("Cannot parse Cargo.toml")]
+    TomlParse,
+
+    /// Test file path is not usable
+    #[error("Invalid test file path - the test file must be a .rs file")]
+    InvalidTestPath,
+
+    /// Path cannot be represented as UTF-8
+    #[error("Path is not valid UTF-8")]
+    NonUtf8Path,
+
+    /// Manifest or test file could not be written
+    #[error("Cannot write file")]
+    WriteFailed,
+
+    /// No workspace member matched
+    #[error("No program found in workspace - use --package to select a member")]
+    MissingWorkspaceProgram,
+
+    /// Init would overwrite an existing test file
+    #[error(
+        "Test file already exists - use --force to overwrite it or --suffix to write a new file"
+    )]
+    TestFileExists,
+
+    /// Framework cache error
+    #[error("Framework cache error")]
+    Cache,
+
+    /// Archive download failed
+    #[error("Download failed")]
+    Download,
+
+    /// Archive checksum does not match
+    #[error("Checksum mismatch")]
+    Checksum,
+
+    /// Archive could not be unpacked
+    #[error("Cannot unpack archive")]
+    Archive,
+
+    /// Template could not be loaded or rendered
+    #[error("Template error")]
+    Template,
+
+    /// Anchor IDL could not be parsed
+    #[error("Cannot parse Anchor IDL")]
+    Idl,
+
+    /// Init record missing or unreadable
+    #[error("Init record error")]
+    InitRecord,
+
+    /// Local toolchain is missing or does not match the project
+    #[error("Toolchain check failed")]
+    Toolchain,
+
+    /// Tests ran but did not pass
+    #[error("Tests failed")]
+    TestFailure,
+
+    /// Account fixture or keypair file missing or malformed
+    #[error("Invalid fixture")]
+    Fixture,
+
+    /// RPC request failed or returned no account
+    #[error("RPC request failed")]
+    Rpc,
 }
 
 impl ErrorKind {
@@ -32,6 +107,62 @@ impl ErrorKind {
     pub fn context(self, source: impl Into<BoxError>) -> Context<ErrorKind> {
         Context::new(self, Some(source.into()))
     }
+
+    /// Process exit code for this kind of error.
+    ///
+    /// | Code | Error |
+    /// |---|---|
+    /// | 10 | `Config` |
+    /// | 11 | `Io` |
+    /// | 12 | `MissingCargoFile` |
+    /// | 13 | `IncorrectCargoFile` |
+    /// | 14 | `UnreadableManifest` |
+    /// | 15 | `TomlParse` |
+    /// | 16 | `InvalidTestPath` |
+    /// | 17 | `NonUtf8Path` |
+    /// | 18 | `WriteFailed` |
+    /// | 19 | `MissingWorkspaceProgram` |
+    /// | 20 | `TestFileExists` |
+    /// | 21 | `Cache` |
+    /// | 22 | `Download` |
+    /// | 23 | `Checksum` |
+    /// | 24 | `Archive` |
+    /// | 25 | `Template` |
+    /// | 26 | `Idl` |
+    /// | 27 | `InitRecord` |
+    /// | 28 | `Toolchain` |
+    /// | 29 | `TestFailure` |
+    /// | 30 | `Fixture` |
+    /// | 31 | `Rpc` |
+    ///
+    /// Codes below 10 are left to the framework, e.g. 2 for invalid command
+    /// line arguments.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Config => 10,
+            ErrorKind::Io => 11,
+            ErrorKind::MissingCargoFile => 12,
+            ErrorKind::IncorrectCargoFile => 13,
+            ErrorKind::UnreadableManifest => 14,
+            ErrorKind::TomlParse => 15,
+            ErrorKind::InvalidTestPath => 16,
+            ErrorKind::NonUtf8Path => 17,
+            ErrorKind::WriteFailed => 18,
+            ErrorKind::MissingWorkspaceProgram => 19,
+            ErrorKind::TestFileExists => 20,
+            ErrorKind::Cache => 21,
+            ErrorKind::Download => 22,
+            ErrorKind::Checksum => 23,
+            ErrorKind::Archive => 24,
+            ErrorKind::Template => 25,
+            ErrorKind::Idl => 26,
+            ErrorKind::InitRecord => 27,
+            ErrorKind::Toolchain => 28,
+            ErrorKind::TestFailure => 29,
+            ErrorKind::Fixture => 30,
+            ErrorKind::Rpc => 31,
+        }
+    }
 }
 
 /// Error type
@@ -46,6 +177,13 @@ impl Deref for Error {
     }
 }
 
+impl Error {
+    /// Process exit code for this error, see [`ErrorKind::exit_code`].
+    pub fn exit_code(&self) -> i32 {
+        self.kind().exit_code()
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.0.fmt(f)
@@ -75,3 +213,59 @@ impl From<io::Error> for Error {
         ErrorKind::Io.context(err).into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project_toml::{parse_manifest, read_manifest};
+    use std::path::Path;
+
+    const KINDS: &[ErrorKind] = &[
+        ErrorKind::Config,
+        ErrorKind::Io,
+        ErrorKind::MissingCargoFile,
+        ErrorKind::IncorrectCargoFile,
+        ErrorKind::UnreadableManifest,
+        ErrorKind::TomlParse,
+        ErrorKind::InvalidTestPath,
+        ErrorKind::NonUtf8Path,
+        ErrorKind::WriteFailed,
+        ErrorKind::MissingWorkspaceProgram,
+        ErrorKind::TestFileExists,
+        ErrorKind::Cache,
+        ErrorKind::Download,
+        ErrorKind::Checksum,
+        ErrorKind::Archive,
+        ErrorKind::Template,
+        ErrorKind::Idl,
+        ErrorKind::InitRecord,
+        ErrorKind::Toolchain,
+        ErrorKind::TestFailure,
+        ErrorKind::Fixture,
+        ErrorKind::Rpc,
+    ];
+
+    #[test]
+    fn exit_codes_are_stable_and_distinct() {
+        let codes: Vec<i32> = KINDS.iter().map(|kind| kind.exit_code()).collect();
+        assert_eq!(codes, (10..32).collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn io_errors_keep_their_message() {
+        let e: Error = io::Error::new(io::ErrorKind::NotFound, "tests/poc.rs").into();
+        assert_eq!(*e.kind(), ErrorKind::Io);
+        assert_eq!(e.exit_code(), 11);
+        assert!(e.to_string().contains("tests/poc.rs"));
+    }
+
+    #[test]
+    fn manifest_errors_have_their_own_codes() {
+        let e = read_manifest(Path::new("/nonexistent/Cargo.toml")).unwrap_err();
+        assert_eq!(e.exit_code(), 12);
+
+        let e = parse_manifest("[package]\nname = \n", Path::new("Cargo.toml")).unwrap_err();
+        assert_eq!(e.exit_code(), 15);
+        assert!(e.to_string().contains("line 2"), "{}", e);
+    }
+}
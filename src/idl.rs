@@ -0,0 +1,477 @@
+//! Anchor IDL parsing and typed test generation
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, ErrorKind};
+use crate::utility::{to_ident, to_pascal_case, to_snake_case};
+
+/// Subset of the Anchor IDL needed to generate tests.
+///
+/// Both the legacy (`isMut`/`isSigner`) and the 0.30+ (`writable`/`signer`)
+/// layouts are accepted.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Idl {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub instructions: Vec<IdlInstruction>,
+    #[serde(default)]
+    pub accounts: Vec<IdlAccountDef>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlInstruction {
+    pub name: String,
+    #[serde(default)]
+    pub accounts: Vec<IdlAccountItem>,
+    #[serde(default)]
+    pub args: Vec<IdlField>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum IdlAccountItem {
+    Composite(IdlAccounts),
+    Account(IdlAccount),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlAccounts {
+    pub name: String,
+    pub accounts: Vec<IdlAccountItem>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlAccount {
+    pub name: String,
+    #[serde(default, alias = "isMut")]
+    pub writable: bool,
+    #[serde(default, alias = "isSigner")]
+    pub signer: bool,
+    #[serde(default)]
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlAccountDef {
+    pub name: String,
+}
+
+impl Idl {
+    pub fn load(path: &Path) -> Result<Idl, Error> {
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents).map_err(|e| ErrorKind::Idl.context(e).into())
+    }
+}
+
+/// Look for `target/idl/<program_name>.json` in the project directory and its
+/// ancestors, since Anchor writes the IDL to the workspace target directory.
+pub fn find_idl(project_path: &Path, program_name: &str) -> Option<PathBuf> {
+    project_path
+        .ancestors()
+        .map(|dir| {
+            dir.join("target")
+                .join("idl")
+                .join(format!("{}.json", program_name))
+        })
+        .find(|idl_path| idl_path.exists())
+}
+
+/// Render one test function per IDL instruction, used as the
+/// `{{instruction_tests}}` template variable.
+pub fn render_tests(idl: &Idl, crate_ident: &str) -> String {
+    idl.instructions
+        .iter()
+        .map(|instruction| render_instruction_test(idl, instruction, crate_ident))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_instruction_test(idl: &Idl, instruction: &IdlInstruction, crate_ident: &str) -> String {
+    let fn_name = to_ident(&instruction.name);
+    let struct_name = to_pascal_case(&instruction.name);
+
+    let mut keys = String::new();
+    let mut signers = vec![String::from("&payer")];
+    let mut accounts = Vec::new();
+    collect_accounts(&instruction.accounts, &mut accounts);
+    for account in &accounts {
+        let name = to_snake_case(&account.name);
+        if name == "payer" || known_account(&name).is_some() || account.address.is_some() {
+            continue;
+        }
+        let ident = to_ident(&account.name);
+        if account.signer {
+            let _ = writeln!(keys, "    let {} = keypair(\"{}\");", ident, name);
+            signers.push(format!("&{}", ident));
+        } else {
+            let _ = writeln!(keys, "    let {} = Pubkey::new_unique();", ident);
+        }
+    }
+
+    let mut args = String::new();
+    for arg in &instruction.args {
+        let _ = writeln!(
+            args,
+            "            {}: {},",
+            to_ident(&arg.name),
+            placeholder(&arg.ty)
+        );
+    }
+
+    let mut metas = String::new();
+    render_account_fields(&instruction.accounts, 3, &mut metas);
+
+    let mut out = String::new();
+    let _ = writeln!(out, "#[tokio::test]");
+    let _ = writeln!(out, "async fn {}() {{", fn_name);
+    let _ = writeln!(out, "    let payer = keypair(\"payer\");");
+    let _ = writeln!(out, "    let (program, program_id) = program_test(&payer);");
+    let _ = writeln!(
+        out,
+        "    let mut program_context = program.start_with_context().await;"
+    );
+    let _ = writeln!(
+        out,
+        "    let recent_blockhash = program_context.last_blockhash;"
+    );
+    out.push('\n');
+    if !keys.is_empty() {
+        let _ = writeln!(out, "    // @fixme set up accounts");
+        out.push_str(&keys);
+        out.push('\n');
+    }
+    let _ = writeln!(out, "    let ix = Instruction {{");
+    let _ = writeln!(out, "        program_id,");
+    if args.is_empty() {
+        let _ = writeln!(
+            out,
+            "        data: instruction::{} {{}}.data(),",
+            struct_name
+        );
+    } else {
+        let _ = writeln!(out, "        data: instruction::{} {{", struct_name);
+        out.push_str(&args);
+        let _ = writeln!(out, "        }}");
+        let _ = writeln!(out, "        .data(),");
+    }
+    let _ = writeln!(out, "        accounts: accounts::{} {{", struct_name);
+    out.push_str(&metas);
+    let _ = writeln!(out, "        }}");
+    let _ = writeln!(out, "        .to_account_metas(None),");
+    let _ = writeln!(out, "    }};");
+    out.push('\n');
+    let _ = writeln!(
+        out,
+        "    let mut tx = Transaction::new_with_payer(&[ix], Some(&payer.pubkey()));"
+    );
+    let _ = writeln!(
+        out,
+        "    tx.partial_sign(&[{}], recent_blockhash);",
+        signers.join(", ")
+    );
+    let _ = writeln!(out, "    program_context");
+    let _ = writeln!(out, "        .banks_client");
+    let _ = writeln!(out, "        .process_transaction(tx)");
+    let _ = writeln!(out, "        .await");
+    let _ = writeln!(out, "        .unwrap();");
+
+    for account in &accounts {
+        let name = to_snake_case(&account.name);
+        let account_type = idl
+            .accounts
+            .iter()
+            .find(|def| to_snake_case(&def.name) == name);
+        if let Some(account_type) = account_type {
+            let key = account_key(account);
+            out.push('\n');
+            let _ = writeln!(
+                out,
+                "    let {}_data: {}::{} = program_context",
+                name, crate_ident, account_type.name
+            );
+            let _ = writeln!(out, "        .banks_client");
+            let _ = writeln!(out, "        .get_anchor_account_data({})", key);
+            let _ = writeln!(out, "        .await");
+            let _ = writeln!(out, "        .unwrap();");
+            let _ = writeln!(out, "    println!(\"{{:?}}\", {}_data);", name);
+        }
+    }
+    let _ = writeln!(out, "}}");
+    out
+}
+
+fn collect_accounts<'a>(items: &'a [IdlAccountItem], accounts: &mut Vec<&'a IdlAccount>) {
+    for item in items {
+        match item {
+            IdlAccountItem::Account(account) => accounts.push(account),
+            IdlAccountItem::Composite(composite) => collect_accounts(&composite.accounts, accounts),
+        }
+    }
+}
+
+fn render_account_fields(items: &[IdlAccountItem], depth: usize, out: &mut String) {
+    let indent = "    ".repeat(depth);
+    for item in items {
+        match item {
+            IdlAccountItem::Account(account) => {
+                let name = to_ident(&account.name);
+                let key = account_key(account);
+                if key == name {
+                    let _ = writeln!(out, "{}{},", indent, name);
+                } else {
+                    let _ = writeln!(out, "{}{}: {},", indent, name, key);
+                }
+            }
+            IdlAccountItem::Composite(composite) => {
+                // Composite account structs are named after their type, which
+                // the IDL does not record, so the field name is our best guess
+                let _ = writeln!(
+                    out,
+                    "{}// @fixme check the composite accounts struct name",
+                    indent
+                );
+                let _ = writeln!(
+                    out,
+                    "{}{}: accounts::{} {{",
+                    indent,
+                    to_ident(&composite.name),
+                    to_pascal_case(&composite.name)
+                );
+                render_account_fields(&composite.accounts, depth + 1, out);
+                let _ = writeln!(out, "{}}},", indent);
+            }
+        }
+    }
+}
+
+fn account_key(account: &IdlAccount) -> String {
+    let name = to_snake_case(&account.name);
+    if let Some(address) = &account.address {
+        return format!("Pubkey::from_str(\"{}\").unwrap()", address);
+    }
+    if let Some(key) = known_account(&name) {
+        return key.to_string();
+    }
+    let ident = to_ident(&account.name);
+    if name == "payer" || account.signer {
+        return format!("{}.pubkey()", ident);
+    }
+    ident
+}
+
+/// Well known programs and sysvars referenced by their conventional names.
+fn known_account(name: &str) -> Option<&'static str> {
+    match name {
+        "system_program" => Some("system_program::ID"),
+        "rent" => Some("anchor_lang::solana_program::sysvar::rent::ID"),
+        "clock" => Some("anchor_lang::solana_program::sysvar::clock::ID"),
+        "instructions" => Some("anchor_lang::solana_program::sysvar::instructions::ID"),
+        "token_program" => Some("anchor_spl::token::ID"),
+        "associated_token_program" => Some("anchor_spl::associated_token::ID"),
+        _ => None,
+    }
+}
+
+/// Typed placeholder value for an IDL argument type.
+fn placeholder(ty: &Value) -> String {
+    match ty {
+        Value::String(name) => match name.as_str() {
+            "bool" => String::from("false"),
+            "u8" | "i8" | "u16" | "i16" | "u32" | "i32" | "u64" | "i64" | "u128" | "i128"
+            | "u256" | "i256" => String::from("0"),
+            "f32" | "f64" => String::from("0.0"),
+            "string" => String::from("String::new()"),
+            "publicKey" | "pubkey" => String::from("Pubkey::default()"),
+            "bytes" => String::from("Vec::new()"),
+            _ => String::from("Default::default()"),
+        },
+        Value::Object(map) => {
+            if map.contains_key("vec") {
+                String::from("Vec::new()")
+            } else if map.contains_key("option") || map.contains_key("coption") {
+                String::from("None")
+            } else if let Some(Value::Array(array)) = map.get("array") {
+                match (array.first(), array.get(1).and_then(Value::as_u64)) {
+                    (Some(inner), Some(len)) => format!("[{}; {}]", placeholder(inner), len),
+                    _ => String::from("Default::default()"),
+                }
+            } else {
+                String::from("Default::default()")
+            }
+        }
+        _ => String::from("Default::default()"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const LEGACY_IDL: &str = r#"{
+        "version": "0.1.0",
+        "name": "vault",
+        "instructions": [
+            {
+                "name": "initialize",
+                "accounts": [
+                    { "name": "vault", "isMut": true, "isSigner": true },
+                    { "name": "authority", "isMut": false, "isSigner": false },
+                    { "name": "systemProgram", "isMut": false, "isSigner": false }
+                ],
+                "args": [
+                    { "name": "amount", "type": "u64" },
+                    { "name": "label", "type": "string" }
+                ]
+            }
+        ],
+        "accounts": [{ "name": "Vault", "type": { "kind": "struct", "fields": [] } }]
+    }"#;
+
+    const ANCHOR_030_IDL: &str = r#"{
+        "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+        "metadata": { "name": "vault", "version": "0.1.0", "spec": "0.1.0" },
+        "instructions": [
+            {
+                "name": "deposit",
+                "discriminator": [242, 35, 198, 137, 82, 225, 242, 182],
+                "accounts": [
+                    { "name": "user", "writable": true, "signer": true },
+                    {
+                        "name": "pool",
+                        "accounts": [{ "name": "pool_state", "writable": true }]
+                    },
+                    { "name": "system_program", "address": "11111111111111111111111111111111" }
+                ],
+                "args": [{ "name": "amount", "type": "u64" }]
+            }
+        ],
+        "accounts": [{ "name": "Vault", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8] }]
+    }"#;
+
+    fn accounts(instruction: &IdlInstruction) -> Vec<&IdlAccount> {
+        let mut accounts = Vec::new();
+        collect_accounts(&instruction.accounts, &mut accounts);
+        accounts
+    }
+
+    #[test]
+    fn parses_legacy_account_flags() {
+        let idl: Idl = serde_json::from_str(LEGACY_IDL).unwrap();
+        assert_eq!(idl.name, "vault");
+        let accounts = accounts(&idl.instructions[0]);
+        assert_eq!(accounts.len(), 3);
+        assert!(accounts[0].writable && accounts[0].signer);
+        assert!(!accounts[1].writable && !accounts[1].signer);
+        assert_eq!(idl.instructions[0].args.len(), 2);
+    }
+
+    #[test]
+    fn parses_anchor_030_layout() {
+        let idl: Idl = serde_json::from_str(ANCHOR_030_IDL).unwrap();
+        let accounts = accounts(&idl.instructions[0]);
+        let names: Vec<&str> = accounts.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["user", "pool_state", "system_program"]);
+        assert!(accounts[0].writable && accounts[0].signer);
+        assert!(accounts[1].writable && !accounts[1].signer);
+        assert_eq!(
+            accounts[2].address.as_deref(),
+            Some("11111111111111111111111111111111")
+        );
+    }
+
+    #[test]
+    fn renders_legacy_instruction_test() {
+        let idl: Idl = serde_json::from_str(LEGACY_IDL).unwrap();
+        let test = render_tests(&idl, "vault");
+        assert!(test.contains("async fn initialize() {"));
+        assert!(test.contains("    let vault = keypair(\"vault\");\n"));
+        assert!(test.contains("    let authority = Pubkey::new_unique();\n"));
+        assert!(test.contains("            amount: 0,\n"));
+        assert!(test.contains("            label: String::new(),\n"));
+        assert!(test.contains("            vault: vault.pubkey(),\n"));
+        assert!(test.contains("            authority,\n"));
+        assert!(test.contains("            system_program: system_program::ID,\n"));
+        assert!(test.contains("tx.partial_sign(&[&payer, &vault], recent_blockhash);"));
+        assert!(test.contains("let vault_data: vault::Vault = program_context"));
+        assert!(test.contains(".get_anchor_account_data(vault.pubkey())"));
+    }
+
+    #[test]
+    fn renders_anchor_030_instruction_test() {
+        let idl: Idl = serde_json::from_str(ANCHOR_030_IDL).unwrap();
+        let test = render_tests(&idl, "vault");
+        assert!(test.contains("async fn deposit() {"));
+        assert!(test.contains("            pool: accounts::Pool {\n"));
+        assert!(test.contains("                pool_state,\n"));
+        assert!(test.contains(
+            "            system_program: Pubkey::from_str(\"11111111111111111111111111111111\").unwrap(),\n"
+        ));
+    }
+
+    #[test]
+    fn escapes_keyword_names() {
+        let idl: Idl = serde_json::from_value(json!({
+            "instructions": [{
+                "name": "move",
+                "accounts": [
+                    { "name": "type", "writable": true },
+                    { "name": "self", "signer": true }
+                ],
+                "args": [{ "name": "ref", "type": "u8" }]
+            }]
+        }))
+        .unwrap();
+        let test = render_tests(&idl, "vault");
+        assert!(test.contains("async fn r#move() {"));
+        assert!(test.contains("    let r#type = Pubkey::new_unique();\n"));
+        assert!(test.contains("    let self_ = keypair(\"self\");\n"));
+        assert!(test.contains("            r#ref: 0,\n"));
+        assert!(test.contains("            r#type,\n"));
+        assert!(test.contains("            self_: self_.pubkey(),\n"));
+        assert!(test.contains("tx.partial_sign(&[&payer, &self_], recent_blockhash);"));
+    }
+
+    #[test]
+    fn placeholders_match_argument_types() {
+        assert_eq!(placeholder(&json!("bool")), "false");
+        assert_eq!(placeholder(&json!("i128")), "0");
+        assert_eq!(placeholder(&json!("publicKey")), "Pubkey::default()");
+        assert_eq!(placeholder(&json!("pubkey")), "Pubkey::default()");
+        assert_eq!(placeholder(&json!({ "vec": "u8" })), "Vec::new()");
+        assert_eq!(placeholder(&json!({ "option": "u64" })), "None");
+        assert_eq!(placeholder(&json!({ "array": ["u8", 32] })), "[0; 32]");
+        assert_eq!(
+            placeholder(&json!({ "defined": "Config" })),
+            "Default::default()"
+        );
+    }
+
+    #[test]
+    fn finds_idl_in_workspace_target() {
+        let workspace = tempfile::tempdir().unwrap();
+        let program = workspace.path().join("programs").join("vault");
+        fs::create_dir_all(&program).unwrap();
+        assert_eq!(find_idl(&program, "vault"), None);
+
+        let idl_dir = workspace.path().join("target").join("idl");
+        fs::create_dir_all(&idl_dir).unwrap();
+        fs::write(idl_dir.join("vault.json"), LEGACY_IDL).unwrap();
+        assert_eq!(
+            find_idl(&program, "vault"),
+            Some(idl_dir.join("vault.json"))
+        );
+    }
+}
@@ -0,0 +1,592 @@
+//! Native program instruction enums and typed test generation
+
+use quote::ToTokens;
+use std::fmt::Write;
+use syn::{Fields, GenericArgument, ItemEnum, Lit, Meta, PathArguments, Type};
+
+use crate::utility::{to_ident, to_snake_case};
+
+/// Variables of the generated tests that account names must not shadow
+const RESERVED_NAMES: &[&str] = &[
+    "payer",
+    "program",
+    "program_id",
+    "program_context",
+    "recent_blockhash",
+    "ix",
+    "tx",
+    "keypair",
+];
+
+/// Words ending the name part of an account description, as in "The mint
+/// to initialize"
+const STOP_WORDS: &[&str] = &[
+    "to", "of", "for", "that", "which", "whose", "who", "being", "with", "from", "in", "on", "by",
+    "is", "must", "will", "if", "as", "and", "or", "at",
+];
+
+/// Borsh instruction enum of a native program
+#[derive(Debug, Clone)]
+pub struct InstructionEnum {
+    /// Path of the enum as used from an integration test
+    pub path: String,
+    pub variants: Vec<InstructionVariant>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InstructionVariant {
+    pub name: String,
+    pub fields: VariantFields,
+    /// Accounts listed in the variant doc comment
+    pub accounts: Vec<InstructionAccount>,
+}
+
+/// Variant fields with typed placeholder values
+#[derive(Debug, Clone)]
+pub enum VariantFields {
+    Unit,
+    Named(Vec<(String, String)>),
+    Unnamed(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionAccount {
+    pub writable: bool,
+    pub signer: bool,
+    pub description: String,
+}
+
+impl InstructionEnum {
+    pub fn from_item(path: String, item: &ItemEnum) -> InstructionEnum {
+        let variants = item
+            .variants
+            .iter()
+            .map(|variant| InstructionVariant {
+                name: variant.ident.to_string(),
+                fields: match &variant.fields {
+                    Fields::Unit => VariantFields::Unit,
+                    Fields::Named(fields) => VariantFields::Named(
+                        fields
+                            .named
+                            .iter()
+                            .map(|field| {
+                                (
+                                    field.ident.as_ref().unwrap().to_string(),
+                                    placeholder(&field.ty),
+                                )
+                            })
+                            .collect(),
+                    ),
+                    Fields::Unnamed(fields) => VariantFields::Unnamed(
+                        fields
+                            .unnamed
+                            .iter()
+                            .map(|field| placeholder(&field.ty))
+                            .collect(),
+                    ),
+                },
+                accounts: parse_accounts(&doc_lines(&variant.attrs)),
+            })
+            .collect();
+        InstructionEnum { path, variants }
+    }
+}
+
+/// Returns true for enums deriving `BorshSerialize` or `BorshDeserialize`.
+pub fn is_borsh_enum(item: &ItemEnum) -> bool {
+    item.attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("derive"))
+        .any(|attr| {
+            let derives = attr.tokens.to_string();
+            derives.contains("BorshSerialize") || derives.contains("BorshDeserialize")
+        })
+}
+
+fn doc_lines(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(meta)) => match meta.lit {
+                Lit::Str(doc) => Some(doc.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Parse account lists in the SPL doc comment style:
+///
+/// ```text
+///   0. `[writable, signer]` The account paying for the mint
+///   1. `[]` Rent sysvar
+/// ```
+fn parse_accounts(doc_lines: &[String]) -> Vec<InstructionAccount> {
+    doc_lines
+        .iter()
+        .filter_map(|line| {
+            let line = line.trim();
+            let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits == 0 {
+                return None;
+            }
+            let rest = line[digits..].strip_prefix('.')?.trim_start();
+            let rest = rest.strip_prefix("`[")?;
+            let end = rest.find("]`")?;
+            let flags: Vec<String> = rest[..end]
+                .split(',')
+                .map(|flag| flag.trim().to_lowercase())
+                .collect();
+            let has_flag = |names: &[&str]| flags.iter().any(|flag| names.contains(&&flag[..]));
+            Some(InstructionAccount {
+                writable: has_flag(&["writable", "writeable", "write", "w", "mut"]),
+                signer: has_flag(&["signer", "s"]),
+                description: rest[end + 2..].trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Borsh function serializing the instruction data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorshSerialize {
+    /// `borsh::to_vec`, borsh 0.10 and later
+    ToVec,
+    /// `BorshSerialize::try_to_vec`, borsh 0.9, removed in borsh 1.0
+    TryToVec,
+}
+
+impl BorshSerialize {
+    fn function(self) -> &'static str {
+        match self {
+            BorshSerialize::ToVec => "borsh::to_vec",
+            BorshSerialize::TryToVec => "borsh::BorshSerialize::try_to_vec",
+        }
+    }
+}
+
+/// Render one test function per instruction variant, used as the
+/// `{{instruction_tests}}` template variable.
+pub fn render_tests(instruction_enum: &InstructionEnum, borsh: BorshSerialize) -> String {
+    instruction_enum
+        .variants
+        .iter()
+        .map(|variant| render_variant_test(&instruction_enum.path, variant, borsh))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_variant_test(
+    enum_path: &str,
+    variant: &InstructionVariant,
+    borsh: BorshSerialize,
+) -> String {
+    let mut keys = String::new();
+    let mut signers = vec![String::from("&payer")];
+    let mut metas = String::new();
+    let mut names: Vec<String> = Vec::new();
+    for (index, account) in variant.accounts.iter().enumerate() {
+        // Keypairs named after their role are shared by the tests, the
+        // numbered fallback is kept per variant
+        let (name, keypair_name) = match account_name(&account.description) {
+            Some(name) if RESERVED_NAMES.contains(&name.as_str()) || names.contains(&name) => {
+                let name = format!("{}_{}", name, index);
+                (to_ident(&name), name)
+            }
+            Some(name) => (to_ident(&name), name),
+            None => (
+                format!("account_{}", index),
+                format!("{}_account_{}", to_snake_case(&variant.name), index),
+            ),
+        };
+        names.push(keypair_name.clone());
+        let key = match known_account(&account.description) {
+            Some(key) => key.to_string(),
+            None if is_payer(&account.description) => String::from("payer.pubkey()"),
+            None if account.signer => {
+                let _ = writeln!(keys, "    let {} = keypair(\"{}\");", name, keypair_name);
+                signers.push(format!("&{}", name));
+                format!("{}.pubkey()", name)
+            }
+            None => {
+                let _ = writeln!(keys, "    let {} = Pubkey::new_unique();", name);
+                name
+            }
+        };
+        if !account.description.is_empty() {
+            let _ = writeln!(metas, "            // {}", account.description);
+        }
+        let constructor = if account.writable {
+            "new"
+        } else {
+            "new_readonly"
+        };
+        let _ = writeln!(
+            metas,
+            "            AccountMeta::{}({}, {}),",
+            constructor, key, account.signer
+        );
+    }
+    if variant.accounts.is_empty() {
+        let _ = writeln!(metas, "            // @fixme add the instruction accounts");
+    }
+
+    let data = match &variant.fields {
+        VariantFields::Unit => format!("{}::{}", enum_path, variant.name),
+        VariantFields::Named(fields) => {
+            let mut data = format!("{}::{} {{\n", enum_path, variant.name);
+            for (name, value) in fields {
+                let _ = writeln!(data, "            {}: {},", name, value);
+            }
+            data.push_str("        }");
+            data
+        }
+        VariantFields::Unnamed(fields) => {
+            let mut data = format!("{}::{}(\n", enum_path, variant.name);
+            for value in fields {
+                let _ = writeln!(data, "            {},", value);
+            }
+            data.push_str("        )");
+            data
+        }
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(out, "#[tokio::test]");
+    let _ = writeln!(out, "async fn {}() {{", to_ident(&variant.name));
+    let _ = writeln!(out, "    let payer = keypair(\"payer\");");
+    let _ = writeln!(out, "    let (program, program_id) = program_test(&payer);");
+    let _ = writeln!(
+        out,
+        "    let mut program_context = program.start_with_context().await;"
+    );
+    let _ = writeln!(
+        out,
+        "    let recent_blockhash = program_context.last_blockhash;"
+    );
+    out.push('\n');
+    if !keys.is_empty() {
+        let _ = writeln!(out, "    // @fixme set up accounts");
+        out.push_str(&keys);
+        out.push('\n');
+    }
+    let _ = writeln!(out, "    let ix = Instruction {{");
+    let _ = writeln!(out, "        program_id,");
+    if let VariantFields::Unit = variant.fields {
+        let _ = writeln!(
+            out,
+            "        data: {}(&{}).unwrap(),",
+            borsh.function(),
+            data
+        );
+    } else {
+        let _ = writeln!(out, "        data: {}(&{})", borsh.function(), data);
+        let _ = writeln!(out, "        .unwrap(),");
+    }
+    let _ = writeln!(out, "        accounts: vec![");
+    out.push_str(&metas);
+    let _ = writeln!(out, "        ],");
+    let _ = writeln!(out, "    }};");
+    out.push('\n');
+    let _ = writeln!(
+        out,
+        "    let mut tx = Transaction::new_with_payer(&[ix], Some(&payer.pubkey()));"
+    );
+    let _ = writeln!(
+        out,
+        "    tx.partial_sign(&[{}], recent_blockhash);",
+        signers.join(", ")
+    );
+    let _ = writeln!(out, "    program_context");
+    let _ = writeln!(out, "        .banks_client");
+    let _ = writeln!(out, "        .process_transaction(tx)");
+    let _ = writeln!(out, "        .await");
+    let _ = writeln!(out, "        .unwrap();");
+    let _ = writeln!(out, "}}");
+    out
+}
+
+/// Variable name of an account from its doc description, e.g. `mint` for
+/// "The mint to initialize." or `source_account` for "Source account".
+fn account_name(description: &str) -> Option<String> {
+    let description = description.to_lowercase();
+    let phrase = description
+        .split(['.', ',', ';', ':', '(', '`'])
+        .next()
+        .unwrap_or_default();
+    let words: Vec<String> = phrase
+        .split_whitespace()
+        .skip_while(|word| matches!(*word, "the" | "a" | "an"))
+        .take_while(|word| !STOP_WORDS.contains(word))
+        .take(3)
+        .map(|word| {
+            word.chars()
+                .map(|c| if c == '-' || c == '/' { '_' } else { c })
+                .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect();
+    let name = words.join("_");
+    match name.chars().next() {
+        Some(first) if first.is_ascii_alphabetic() => Some(name),
+        _ => None,
+    }
+}
+
+/// Whether an account description names the transaction fee payer, which
+/// the tests already have as `payer`.
+fn is_payer(description: &str) -> bool {
+    let description = description.to_lowercase();
+    description.contains("payer")
+        || description.contains("paying")
+        || description.contains("funding account")
+}
+
+/// Well known programs and sysvars, recognised by their doc description.
+fn known_account(description: &str) -> Option<&'static str> {
+    let description = description.to_lowercase();
+    if description.starts_with("system program") || description.starts_with("the system program") {
+        Some("solana_program::system_program::ID")
+    } else if description.starts_with("rent sysvar") || description.starts_with("the rent sysvar") {
+        Some("solana_program::sysvar::rent::ID")
+    } else if description.starts_with("clock sysvar") || description.starts_with("the clock sysvar")
+    {
+        Some("solana_program::sysvar::clock::ID")
+    } else {
+        None
+    }
+}
+
+/// Typed placeholder value for a field type.
+fn placeholder(ty: &Type) -> String {
+    match ty {
+        Type::Path(type_path) => {
+            let segment = match type_path.path.segments.last() {
+                Some(segment) => segment,
+                None => return String::from("Default::default()"),
+            };
+            match segment.ident.to_string().as_str() {
+                "bool" => String::from("false"),
+                "u8" | "i8" | "u16" | "i16" | "u32" | "i32" | "u64" | "i64" | "u128" | "i128"
+                | "usize" | "isize" => String::from("0"),
+                "f32" | "f64" => String::from("0.0"),
+                "String" => String::from("String::new()"),
+                "Pubkey" => String::from("Pubkey::default()"),
+                "Vec" => String::from("Vec::new()"),
+                "Option" | "COption" => String::from("None"),
+                "Box" => match &segment.arguments {
+                    PathArguments::AngleBracketed(args) => match args.args.first() {
+                        Some(GenericArgument::Type(inner)) => {
+                            format!("Box::new({})", placeholder(inner))
+                        }
+                        _ => String::from("Default::default()"),
+                    },
+                    _ => String::from("Default::default()"),
+                },
+                _ => String::from("Default::default()"),
+            }
+        }
+        Type::Array(array) => format!(
+            "[{}; {}]",
+            placeholder(&array.elem),
+            array.len.to_token_stream()
+        ),
+        Type::Tuple(tuple) if tuple.elems.is_empty() => String::from("()"),
+        Type::Tuple(tuple) => format!(
+            "({},)",
+            tuple
+                .elems
+                .iter()
+                .map(placeholder)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        _ => String::from("Default::default()"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN_INSTRUCTION: &str = r#"
+        #[derive(BorshSerialize, BorshDeserialize, Debug)]
+        pub enum TokenInstruction {
+            /// Initialize a mint.
+            ///
+            ///   0. `[writable, signer]` The mint to initialize.
+            ///   1. `[]` Rent sysvar
+            InitializeMint { decimals: u8, authority: Pubkey, freeze: Option<Pubkey> },
+            /// Move tokens.
+            ///
+            ///   0. `[w]` Source account
+            ///   1. `[s]` Owner
+            ///   2. `[]` The system program
+            Move(u64, [u8; 32]),
+            Type,
+        }
+    "#;
+
+    fn token_instruction() -> InstructionEnum {
+        let item: ItemEnum = syn::parse_str(TOKEN_INSTRUCTION).unwrap();
+        assert!(is_borsh_enum(&item));
+        InstructionEnum::from_item(String::from("token::TokenInstruction"), &item)
+    }
+
+    #[test]
+    fn parses_variants_and_doc_accounts() {
+        let instruction_enum = token_instruction();
+        let names: Vec<&str> = instruction_enum
+            .variants
+            .iter()
+            .map(|variant| variant.name.as_str())
+            .collect();
+        assert_eq!(names, ["InitializeMint", "Move", "Type"]);
+
+        let initialize_mint = &instruction_enum.variants[0];
+        match &initialize_mint.fields {
+            VariantFields::Named(fields) => assert_eq!(
+                fields,
+                &[
+                    (String::from("decimals"), String::from("0")),
+                    (String::from("authority"), String::from("Pubkey::default()")),
+                    (String::from("freeze"), String::from("None")),
+                ]
+            ),
+            fields => panic!("unexpected fields {:?}", fields),
+        }
+        assert_eq!(
+            initialize_mint.accounts,
+            [
+                InstructionAccount {
+                    writable: true,
+                    signer: true,
+                    description: String::from("The mint to initialize."),
+                },
+                InstructionAccount {
+                    writable: false,
+                    signer: false,
+                    description: String::from("Rent sysvar"),
+                },
+            ]
+        );
+
+        let flags: Vec<(bool, bool)> = instruction_enum.variants[1]
+            .accounts
+            .iter()
+            .map(|account| (account.writable, account.signer))
+            .collect();
+        assert_eq!(flags, [(true, false), (false, true), (false, false)]);
+    }
+
+    #[test]
+    fn skips_enums_without_borsh() {
+        let item: ItemEnum = syn::parse_str("#[derive(Debug)] pub enum Error { A }").unwrap();
+        assert!(!is_borsh_enum(&item));
+    }
+
+    #[test]
+    fn renders_variant_tests() {
+        let tests = render_tests(&token_instruction(), BorshSerialize::ToVec);
+        assert!(tests.contains("async fn initialize_mint() {"));
+        assert!(tests.contains("    let mint = keypair(\"mint\");\n"));
+        assert!(tests.contains("            AccountMeta::new(mint.pubkey(), true),\n"));
+        assert!(tests.contains(
+            "            AccountMeta::new_readonly(solana_program::sysvar::rent::ID, false),\n"
+        ));
+        assert!(tests.contains("            decimals: 0,\n"));
+        assert!(tests.contains("            [0; 32],\n"));
+        assert!(tests.contains(
+            "            AccountMeta::new_readonly(solana_program::system_program::ID, false),\n"
+        ));
+        assert!(tests.contains("    let source_account = Pubkey::new_unique();\n"));
+        assert!(tests.contains("    let owner = keypair(\"owner\");\n"));
+        assert!(tests.contains("tx.partial_sign(&[&payer, &owner], recent_blockhash);"));
+        assert!(tests
+            .contains("        data: borsh::to_vec(&token::TokenInstruction::Type).unwrap(),\n"));
+        assert!(tests
+            .contains("        data: borsh::to_vec(&token::TokenInstruction::InitializeMint {\n"));
+        assert!(tests.contains("        })\n        .unwrap(),\n"));
+        assert!(!tests.contains("try_to_vec"));
+    }
+
+    #[test]
+    fn renders_borsh_09_serialization() {
+        let tests = render_tests(&token_instruction(), BorshSerialize::TryToVec);
+        assert!(tests.contains(
+            "        data: borsh::BorshSerialize::try_to_vec(&token::TokenInstruction::Type).unwrap(),\n"
+        ));
+        assert!(!tests.contains("borsh::to_vec"));
+        assert!(tests.contains("            // @fixme add the instruction accounts\n"));
+    }
+
+    #[test]
+    fn escapes_keyword_variant_names() {
+        let tests = render_tests(&token_instruction(), BorshSerialize::ToVec);
+        assert!(tests.contains("async fn r#move() {"));
+        assert!(tests.contains("async fn r#type() {"));
+        assert!(!tests.contains("async fn move()"));
+    }
+
+    #[test]
+    fn names_accounts_after_their_description() {
+        let names: Vec<Option<String>> = [
+            "The mint to initialize.",
+            "Source account",
+            "the new account's owner",
+            "Fee-payer",
+            "Multisignature owner/delegate",
+            "`[]` 2nd",
+            "",
+        ]
+        .iter()
+        .map(|description| account_name(description))
+        .collect();
+        assert_eq!(
+            names,
+            [
+                Some(String::from("mint")),
+                Some(String::from("source_account")),
+                Some(String::from("new_accounts_owner")),
+                Some(String::from("fee_payer")),
+                Some(String::from("multisignature_owner_delegate")),
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn reuses_payer_and_dedups_names() {
+        let item: ItemEnum = syn::parse_str(
+            r#"
+            #[derive(BorshSerialize)]
+            pub enum VaultInstruction {
+                ///   0. `[writable, signer]` The account paying for the vault
+                ///   1. `[writable]` The vault
+                ///   2. `[]` The vault to copy
+                ///   3. `[signer]` Program
+                ///   4. `[signer]`
+                Create,
+            }
+            "#,
+        )
+        .unwrap();
+        let tests = render_tests(
+            &InstructionEnum::from_item(String::from("VaultInstruction"), &item),
+            BorshSerialize::ToVec,
+        );
+        assert!(tests.contains("            AccountMeta::new(payer.pubkey(), true),\n"));
+        assert!(!tests.contains("keypair(\"the_account\")"));
+        assert!(tests.contains("    let vault = Pubkey::new_unique();\n"));
+        assert!(tests.contains("    let vault_2 = Pubkey::new_unique();\n"));
+        assert!(tests.contains("    let program_3 = keypair(\"program_3\");\n"));
+        assert!(tests.contains("    let account_4 = keypair(\"create_account_4\");\n"));
+        assert!(
+            tests.contains("tx.partial_sign(&[&payer, &program_3, &account_4], recent_blockhash);")
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Named keypairs of the generated tests
+//!
+//! The templates call `keypair("payer")`, a helper reading
+//! `<keys dir>/payer.json` at test time. Init collects the names from the
+//! rendered test and writes the missing files in the `solana-keygen` JSON
+//! format, an array of the 32 secret key bytes followed by the 32 public key
+//! bytes. Keys are random unless a seed is configured, in which case the
+//! secret key of `<name>` is the SHA-256 of `<seed>:<name>`.
+
+use ed25519_dalek::SigningKey;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Error, ErrorKind};
+
+/// Keypair in the `solana-keygen` byte layout
+pub type KeypairBytes = [u8; 64];
+
+/// Names passed to `keypair("<name>")` in a rendered test, sorted.
+pub fn keypair_names(contents: &str) -> Vec<String> {
+    let mut names: Vec<String> = contents
+        .split("keypair(\"")
+        .skip(1)
+        .filter_map(|rest| rest.split_once('"'))
+        .map(|(name, _)| name)
+        .filter(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        })
+        .map(String::from)
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Keypair of `name` derived from `seed`.
+pub fn derive(seed: &str, name: &str) -> KeypairBytes {
+    let secret: [u8; 32] = Sha256::digest(format!("{}:{}", seed, name).as_bytes()).into();
+    SigningKey::from_bytes(&secret).to_keypair_bytes()
+}
+
+/// New random keypair.
+pub fn generate() -> Result<KeypairBytes, Error> {
+    let mut secret = [0u8; 32];
+    getrandom::getrandom(&mut secret).map_err(|e| ErrorKind::Io.context(e.to_string()))?;
+    Ok(SigningKey::from_bytes(&secret).to_keypair_bytes())
+}
+
+/// Base58 public key of a keypair.
+pub fn pubkey(keypair: &KeypairBytes) -> String {
+    bs58::encode(&keypair[32..]).into_string()
+}
+
+/// Keypair file contents, e.g. `[12,201,...]` as written by `solana-keygen`.
+pub fn to_json(keypair: &KeypairBytes) -> String {
+    let bytes: Vec<String> = keypair.iter().map(u8::to_string).collect();
+    format!("[{}]", bytes.join(","))
+}
+
+/// Read a keypair file, `None` when it is not in the `solana-keygen`
+/// format.
+pub fn read(path: &Path) -> Option<KeypairBytes> {
+    let contents = fs::read_to_string(path).ok()?;
+    let bytes: Vec<u8> = serde_json::from_str(&contents).ok()?;
+    bytes.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derived_keypairs_are_stable() {
+        let payer = derive("poc", "payer");
+        assert_eq!(
+            pubkey(&payer),
+            "GoS983oqfuK5VXmzLno51Rpfq4vBDKeWXKhz6XsFExVx"
+        );
+        assert_eq!(payer[..32], Sha256::digest(b"poc:payer")[..]);
+        assert_eq!(derive("poc", "payer"), payer);
+        assert_ne!(derive("poc", "authority"), payer);
+        assert_ne!(derive("other", "payer"), payer);
+    }
+
+    #[test]
+    fn generated_keypairs_are_valid() {
+        let keypair = generate().unwrap();
+        assert_ne!(generate().unwrap(), keypair);
+        let secret: [u8; 32] = keypair[..32].try_into().unwrap();
+        assert_eq!(SigningKey::from_bytes(&secret).to_keypair_bytes(), keypair);
+        assert!(crate::utility::is_pubkey(&pubkey(&keypair)));
+    }
+
+    #[test]
+    fn scans_keypair_names() {
+        let contents = r#"
+            let payer = keypair("payer");
+            let authority = keypair("authority");
+            let again = keypair("payer");
+            let helper = keypair(name);
+            let invalid = keypair("../escape");
+            let empty = keypair("");
+            let dashed = keypair("mint-2");
+        "#;
+        assert_eq!(keypair_names(contents), ["authority", "mint-2", "payer"]);
+        assert!(keypair_names("fn keypair(name: &str) -> Keypair").is_empty());
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let keypair = derive("poc", "payer");
+        let json = to_json(&keypair);
+        assert!(json.starts_with('[') && json.ends_with(']'));
+        assert_eq!(json.split(',').count(), 64);
+
+        let path = dir.path().join("payer.json");
+        fs::write(&path, &json).unwrap();
+        assert_eq!(read(&path), Some(keypair));
+
+        fs::write(&path, "[1, 2, 3]").unwrap();
+        assert_eq!(read(&path), None);
+        fs::write(&path, "not json").unwrap();
+        assert_eq!(read(&path), None);
+        assert_eq!(read(&dir.path().join("missing.json")), None);
+    }
+}
@@ -14,10 +14,31 @@
     unused_qualifications
 )]
 
+pub mod accounts;
 pub mod application;
+pub mod cache;
+// The abscissa 0.6 `Command` derive emits its impls inside an anonymous
+// const, which current toolchains report for every command. The lint cannot
+// be allowed on the derived item itself, so it is allowed for the commands
+// only.
+#[allow(non_local_definitions)]
 pub mod commands;
+pub mod compat;
 pub mod config;
 pub mod error;
+pub mod idl;
+pub mod instruction;
+pub mod keys;
+pub mod libtest;
 pub mod prelude;
+pub mod program;
 pub mod project_toml;
+pub mod record;
+pub mod report;
+pub mod rpc;
+pub mod template;
+#[cfg(test)]
+mod testing;
+pub mod toolchain;
 pub mod utility;
+pub mod workspace;
@@ -0,0 +1,672 @@
+//! Parsing of the libtest output of `cargo test-sbf`/`cargo test-bpf`
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::error::{Error, ErrorKind};
+
+/// Totals of one or more `test result:` lines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TestSummary {
+    pub passed: u64,
+    pub failed: u64,
+    pub ignored: u64,
+}
+
+impl TestSummary {
+    /// Sum the `test result: ok. 1 passed; 0 failed; 0 ignored; ...` lines,
+    /// one per test binary.
+    pub fn parse(output: &str) -> TestSummary {
+        let mut summary = TestSummary::default();
+        for line in output.lines() {
+            let counts = match line.trim().strip_prefix("test result:") {
+                Some(counts) => counts,
+                None => continue,
+            };
+            for count in counts.split(';') {
+                let mut words = count.split_whitespace().rev();
+                let (name, value) = match (words.next(), words.next()) {
+                    (Some(name), Some(value)) => (name, value),
+                    _ => continue,
+                };
+                let value: u64 = match value.parse() {
+                    Ok(value) => value,
+                    Err(_) => continue,
+                };
+                match name {
+                    "passed" => summary.passed += value,
+                    "failed" => summary.failed += value,
+                    "ignored" => summary.ignored += value,
+                    _ => {}
+                }
+            }
+        }
+        summary
+    }
+
+    pub fn add(&mut self, other: TestSummary) {
+        self.passed += other.passed;
+        self.failed += other.failed;
+        self.ignored += other.ignored;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+/// Outcome of a single test
+#[derive(Debug, Clone, Serialize)]
+pub struct TestCase {
+    pub name: String,
+    /// Test binary, e.g. `tests/transfer.rs`, when cargo's `Running` lines
+    /// are part of the output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary: Option<String>,
+    /// Position of the test binary in the output, telling apart tests of
+    /// the same name in different binaries
+    #[serde(skip)]
+    binary_index: usize,
+    pub status: TestStatus,
+    /// Seconds, only reported by libtest with `--report-time`
+    pub duration: Option<f64>,
+    /// Program logs, from the captured output of a failed test and from the
+    /// log lines written to stderr before the test's result. With parallel
+    /// tests the latter go to the next test finishing, run with
+    /// `--test-threads=1` to attribute them exactly.
+    pub logs: Vec<String>,
+    pub panic_message: Option<String>,
+}
+
+/// Tests of one `cargo test-sbf`/`cargo test-bpf` run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TestRun {
+    pub tests: Vec<TestCase>,
+    #[serde(flatten)]
+    pub summary: TestSummary,
+    /// Seconds, summed over the test binaries
+    pub duration: Option<f64>,
+}
+
+impl TestRun {
+    /// Parse the libtest output, e.g. saved with `cargo test-sbf > out.txt`.
+    pub fn parse(output: &str) -> TestRun {
+        let mut run = TestRun {
+            summary: TestSummary::parse(output),
+            ..TestRun::default()
+        };
+        // Output of failed tests, keyed by binary and test name
+        let mut captured: Vec<(usize, String, Vec<String>)> = Vec::new();
+        let mut capturing = false;
+        let mut binary = None;
+        let mut binary_index = 0;
+        // Logs written to stderr by `solana_logger`, outside libtest's capture
+        let mut pending_logs = Vec::new();
+
+        for line in output.lines() {
+            if let Some(name) = line
+                .strip_prefix("---- ")
+                .and_then(|rest| rest.strip_suffix(" stdout ----"))
+            {
+                captured.push((binary_index, name.to_string(), Vec::new()));
+                capturing = true;
+                continue;
+            }
+            if line == "failures:" || line.starts_with("test result:") {
+                capturing = false;
+            }
+            if capturing {
+                if let Some((_, _, lines)) = captured.last_mut() {
+                    lines.push(line.to_string());
+                }
+                continue;
+            }
+
+            if let Some(finished) = line
+                .split("finished in ")
+                .nth(1)
+                .and_then(|time| parse_seconds(time.trim()))
+            {
+                run.duration = Some(run.duration.unwrap_or(0.0) + finished);
+            }
+            if let Some(name) = parse_binary_line(line) {
+                binary = Some(name);
+            } else if is_running_line(line) {
+                binary_index += 1;
+                pending_logs.clear();
+            } else if let Some(mut test) = parse_test_line(line) {
+                test.binary = binary.clone();
+                test.binary_index = binary_index;
+                test.logs = std::mem::take(&mut pending_logs);
+                run.tests.push(test);
+            } else if let Some(log) = program_log(line) {
+                pending_logs.push(log.to_string());
+            }
+        }
+
+        for (binary_index, name, lines) in captured {
+            let test = run
+                .tests
+                .iter_mut()
+                .find(|test| test.binary_index == binary_index && test.name == name);
+            if let Some(test) = test {
+                test.logs.extend(
+                    lines
+                        .iter()
+                        .filter_map(|line| program_log(line))
+                        .map(String::from),
+                );
+                test.panic_message = panic_message(&lines);
+            }
+        }
+        run
+    }
+}
+
+/// Parse `test name ... ok`, optionally followed by `<0.012s>`.
+fn parse_test_line(line: &str) -> Option<TestCase> {
+    let (name, result) = line.strip_prefix("test ")?.split_once(" ... ")?;
+    let status = if result.starts_with("ok") {
+        TestStatus::Passed
+    } else if result.starts_with("FAILED") {
+        TestStatus::Failed
+    } else if result.starts_with("ignored") {
+        TestStatus::Ignored
+    } else {
+        return None;
+    };
+    let duration = result
+        .split_once('<')
+        .and_then(|(_, time)| time.strip_suffix('>'))
+        .and_then(parse_seconds);
+    Some(TestCase {
+        name: name.to_string(),
+        binary: None,
+        binary_index: 0,
+        status,
+        duration,
+        logs: Vec::new(),
+        panic_message: None,
+    })
+}
+
+/// Program log of a line, e.g. `Program log: Instruction: Transfer` of a
+/// `solana_runtime` debug line.
+fn program_log(line: &str) -> Option<&str> {
+    line.find("Program ").map(|start| &line[start..])
+}
+
+/// Test binary named by cargo's `Running tests/transfer.rs (target/...)`
+/// or `Doc-tests vault` line, printed before the tests of each binary.
+fn parse_binary_line(line: &str) -> Option<String> {
+    let line = line.trim();
+    if let Some(crate_name) = line.strip_prefix("Doc-tests ") {
+        return Some(format!("doc-tests {}", crate_name));
+    }
+    let binary = line.strip_prefix("Running ")?;
+    let binary = binary.split(" (").next().unwrap_or(binary);
+    Some(
+        binary
+            .strip_prefix("unittests ")
+            .unwrap_or(binary)
+            .to_string(),
+    )
+}
+
+/// libtest's `running 3 tests` line starting the output of a test binary.
+fn is_running_line(line: &str) -> bool {
+    line.strip_prefix("running ")
+        .and_then(|rest| rest.split_once(' '))
+        .is_some_and(|(count, unit)| {
+            count.parse::<u64>().is_ok() && (unit == "test" || unit == "tests")
+        })
+}
+
+fn parse_seconds(time: &str) -> Option<f64> {
+    time.strip_suffix('s')?.parse().ok()
+}
+
+/// Panic message of a test, in both the `panicked at 'message', location`
+/// format and the newer `panicked at location:` format with the message on
+/// the following lines.
+fn panic_message(lines: &[String]) -> Option<String> {
+    let index = lines
+        .iter()
+        .position(|line| line.starts_with("thread '") && line.contains("' panicked at "))?;
+    let rest = lines[index].split("' panicked at ").nth(1)?;
+    if let Some(quoted) = rest.strip_prefix('\'') {
+        let end = quoted.rfind("', ").unwrap_or(quoted.len());
+        return Some(quoted[..end].to_string());
+    }
+    let message: Vec<&str> = lines[index + 1..]
+        .iter()
+        .map(String::as_str)
+        .take_while(|line| !line.is_empty() && !line.starts_with("note: "))
+        .collect();
+    if message.is_empty() {
+        Some(rest.to_string())
+    } else {
+        Some(message.join("\n"))
+    }
+}
+
+/// Report file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Junit,
+    Json,
+}
+
+/// Report requested with `--report <format>:<path>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportTarget {
+    pub format: ReportFormat,
+    pub path: PathBuf,
+}
+
+impl FromStr for ReportTarget {
+    type Err = String;
+
+    fn from_str(target: &str) -> Result<Self, Self::Err> {
+        let (format, path) = target
+            .split_once(':')
+            .ok_or_else(|| format!("{} is not in <format>:<path> format", target))?;
+        let format = match format {
+            "junit" => ReportFormat::Junit,
+            "json" => ReportFormat::Json,
+            _ => {
+                return Err(format!(
+                    "unknown report format {}, use junit or json",
+                    format
+                ))
+            }
+        };
+        if path.is_empty() {
+            return Err(String::from("missing report path"));
+        }
+        Ok(ReportTarget {
+            format,
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+impl ReportTarget {
+    /// Write the test runs, one suite per program.
+    pub fn write(&self, suites: &[(String, TestRun)]) -> Result<(), Error> {
+        let contents = match self.format {
+            ReportFormat::Junit => junit(suites),
+            ReportFormat::Json => {
+                let suites: Vec<Value> = suites
+                    .iter()
+                    .map(|(name, run)| json!({ "name": name, "run": run }))
+                    .collect();
+                serde_json::to_string_pretty(&suites).map_err(|e| ErrorKind::Io.context(e))?
+            }
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, contents).map_err(|e| {
+            ErrorKind::WriteFailed
+                .context(format!("{}: {}", self.path.display(), e))
+                .into()
+        })
+    }
+}
+
+/// Render JUnit XML with one `<testsuite>` per program.
+pub fn junit(suites: &[(String, TestRun)]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let total = suites
+        .iter()
+        .fold(TestSummary::default(), |mut total, (_, run)| {
+            total.add(run.summary);
+            total
+        });
+    let _ = writeln!(
+        xml,
+        "<testsuites tests=\"{}\" failures=\"{}\" skipped=\"{}\">",
+        total.passed + total.failed + total.ignored,
+        total.failed,
+        total.ignored
+    );
+    for (name, run) in suites {
+        let _ = write!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\"",
+            escape_xml(name),
+            run.tests.len(),
+            run.summary.failed,
+            run.summary.ignored
+        );
+        if let Some(duration) = run.duration {
+            let _ = write!(xml, " time=\"{:.3}\"", duration);
+        }
+        xml.push_str(">\n");
+        for test in &run.tests {
+            // Tests of the same name in different binaries stay apart
+            let classname = match &test.binary {
+                Some(binary) => format!("{}::{}", name, binary),
+                None => name.clone(),
+            };
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\"",
+                escape_xml(&test.name),
+                escape_xml(&classname)
+            );
+            if let Some(duration) = test.duration {
+                let _ = write!(xml, " time=\"{:.3}\"", duration);
+            }
+            xml.push_str(">\n");
+            match test.status {
+                TestStatus::Passed => {}
+                TestStatus::Ignored => xml.push_str("      <skipped/>\n"),
+                TestStatus::Failed => {
+                    let message = test.panic_message.as_deref().unwrap_or("test failed");
+                    let _ = writeln!(
+                        xml,
+                        "      <failure message=\"{}\">{}</failure>",
+                        escape_xml(message.lines().next().unwrap_or_default()),
+                        escape_xml(message)
+                    );
+                }
+            }
+            if !test.logs.is_empty() {
+                let _ = writeln!(
+                    xml,
+                    "      <system-out>{}</system-out>",
+                    escape_xml(&test.logs.join("\n"))
+                );
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// Escape text for XML, dropping ANSI escape sequences such as colors and
+/// other control characters.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // CSI sequence, `ESC [` parameters and a final byte in `@`..=`~`
+            '\u{1b}' if chars.as_str().starts_with('[') => {
+                chars.next();
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' | '\t' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "\
+running 3 tests
+test test_close ... ignored
+test test_initialize ... ok <0.012s>
+test test_transfer ... FAILED <0.250s>
+
+failures:
+
+---- test_transfer stdout ----
+[2024-01-01T00:00:00Z DEBUG solana_runtime] Program 11111111111111111111111111111111 invoke [1]
+Program log: Instruction: Transfer
+thread 'test_transfer' panicked at 'insufficient funds', tests/test.rs:42:5
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+failures:
+    test_transfer
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.30s
+
+running 1 test
+test test_other ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.10s
+";
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn summary_sums_result_lines() {
+        assert_eq!(
+            TestSummary::parse(OUTPUT),
+            TestSummary {
+                passed: 2,
+                failed: 1,
+                ignored: 1
+            }
+        );
+        assert_eq!(
+            TestSummary::parse("error: could not compile"),
+            TestSummary::default()
+        );
+    }
+
+    #[test]
+    fn run_parses_tests_logs_and_durations() {
+        let run = TestRun::parse(OUTPUT);
+        let statuses: Vec<(&str, TestStatus)> = run
+            .tests
+            .iter()
+            .map(|test| (test.name.as_str(), test.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("test_close", TestStatus::Ignored),
+                ("test_initialize", TestStatus::Passed),
+                ("test_transfer", TestStatus::Failed),
+                ("test_other", TestStatus::Passed),
+            ]
+        );
+        assert_eq!(run.tests[1].duration, Some(0.012));
+        assert_eq!(run.tests[3].duration, None);
+        assert!((run.duration.unwrap() - 0.4).abs() < 1e-9);
+
+        let failed = &run.tests[2];
+        assert_eq!(
+            failed.logs,
+            [
+                "Program 11111111111111111111111111111111 invoke [1]",
+                "Program log: Instruction: Transfer"
+            ]
+        );
+        assert_eq!(failed.panic_message.as_deref(), Some("insufficient funds"));
+    }
+
+    #[test]
+    fn panic_message_in_quoted_format() {
+        let quoted = lines("thread 'main' panicked at 'left != right', tests/test.rs:1:1");
+        assert_eq!(panic_message(&quoted).as_deref(), Some("left != right"));
+    }
+
+    #[test]
+    fn panic_message_in_location_format() {
+        let multiline = lines(
+            "thread 'test_transfer' panicked at tests/test.rs:42:5:\n\
+             assertion `left == right` failed\n  left: 1\n right: 2\n\
+             note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace",
+        );
+        assert_eq!(
+            panic_message(&multiline).as_deref(),
+            Some("assertion `left == right` failed\n  left: 1\n right: 2")
+        );
+
+        let location_only = lines("thread 'test_transfer' panicked at tests/test.rs:42:5:");
+        assert_eq!(
+            panic_message(&location_only).as_deref(),
+            Some("tests/test.rs:42:5:")
+        );
+        assert_eq!(panic_message(&lines("no panic here")), None);
+    }
+
+    #[test]
+    fn junit_escapes_names_and_messages() {
+        let mut run = TestRun::parse(OUTPUT);
+        run.tests[2].panic_message = Some(String::from("\u{1b}[31m<a & 'b'>\"\u{1b}[0m\nmore"));
+        let xml = junit(&[(String::from("my<program>"), run)]);
+
+        assert!(xml.contains("<testsuites tests=\"4\" failures=\"1\" skipped=\"1\">"));
+        assert!(xml.contains(
+            "<testsuite name=\"my&lt;program&gt;\" tests=\"4\" failures=\"1\" skipped=\"1\" time=\"0.400\">"
+        ));
+        assert!(xml.contains("<skipped/>"));
+        assert!(xml.contains(
+            "<failure message=\"&lt;a &amp; &apos;b&apos;&gt;&quot;\">\
+             &lt;a &amp; &apos;b&apos;&gt;&quot;\nmore</failure>"
+        ));
+        assert!(!xml.contains('\u{1b}'));
+        assert!(!xml.contains("[31m") && !xml.contains("[0m"));
+        assert_eq!(escape_xml("\u{1b}[1;32mok\u{1b}[0m\u{7}"), "ok");
+    }
+
+    #[test]
+    fn report_target_parsing() {
+        assert_eq!(
+            "junit:out/report.xml".parse::<ReportTarget>(),
+            Ok(ReportTarget {
+                format: ReportFormat::Junit,
+                path: PathBuf::from("out/report.xml")
+            })
+        );
+        assert!("xml:report.xml".parse::<ReportTarget>().is_err());
+        assert!("json:".parse::<ReportTarget>().is_err());
+        assert!("report.json".parse::<ReportTarget>().is_err());
+    }
+
+    #[test]
+    fn tests_of_the_same_name_are_kept_per_binary() {
+        let output = "\
+     Running tests/a.rs (target/debug/deps/a-0123456789abcdef)
+
+running 1 test
+test it_works ... FAILED
+
+failures:
+
+---- it_works stdout ----
+Program log: from a
+thread 'it_works' panicked at 'a failed', tests/a.rs:3:5
+
+failures:
+    it_works
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+
+     Running tests/b.rs (target/debug/deps/b-0123456789abcdef)
+
+running 1 test
+test it_works ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+";
+        let run = TestRun::parse(output);
+        let tests: Vec<(Option<&str>, &str, TestStatus)> = run
+            .tests
+            .iter()
+            .map(|test| (test.binary.as_deref(), test.name.as_str(), test.status))
+            .collect();
+        assert_eq!(
+            tests,
+            [
+                (Some("tests/a.rs"), "it_works", TestStatus::Failed),
+                (Some("tests/b.rs"), "it_works", TestStatus::Passed),
+            ]
+        );
+        assert_eq!(run.tests[0].logs, ["Program log: from a"]);
+        assert_eq!(run.tests[0].panic_message.as_deref(), Some("a failed"));
+        assert!(run.tests[1].logs.is_empty());
+        assert_eq!(run.tests[1].panic_message, None);
+
+        // Without cargo's lines the binaries are told apart by position
+        let libtest_only: String = output
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("Running "))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        let run = TestRun::parse(&libtest_only);
+        assert_eq!(run.tests[0].status, TestStatus::Failed);
+        assert_eq!(run.tests[0].panic_message.as_deref(), Some("a failed"));
+        assert_eq!(run.tests[1].panic_message, None);
+
+        let xml = junit(&[(String::from("vault"), TestRun::parse(output))]);
+        assert!(xml.contains("<testcase name=\"it_works\" classname=\"vault::tests/a.rs\">"));
+        assert!(xml.contains("<testcase name=\"it_works\" classname=\"vault::tests/b.rs\">"));
+    }
+
+    #[test]
+    fn binary_lines() {
+        assert_eq!(
+            parse_binary_line("     Running unittests src/lib.rs (target/debug/deps/vault-1)")
+                .as_deref(),
+            Some("src/lib.rs")
+        );
+        assert_eq!(
+            parse_binary_line("     Running target/debug/deps/transfer-1").as_deref(),
+            Some("target/debug/deps/transfer-1")
+        );
+        assert_eq!(
+            parse_binary_line("   Doc-tests vault").as_deref(),
+            Some("doc-tests vault")
+        );
+        assert_eq!(parse_binary_line("running 1 test"), None);
+        assert!(is_running_line("running 1 test"));
+        assert!(is_running_line("running 12 tests"));
+        assert!(!is_running_line("running tests"));
+    }
+
+    #[test]
+    fn stderr_logs_go_to_the_next_result() {
+        let output = "\
+running 2 tests
+[2024-01-01T00:00:00Z DEBUG solana_runtime::message_processor] Program log: Instruction: Initialize
+test test_initialize ... ok
+Program log: Instruction: Transfer
+test test_transfer ... ok
+Program log: after the last result
+
+test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.30s
+
+running 1 test
+test test_other ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.10s
+";
+        let run = TestRun::parse(output);
+        assert_eq!(run.tests[0].logs, ["Program log: Instruction: Initialize"]);
+        assert_eq!(run.tests[1].logs, ["Program log: Instruction: Transfer"]);
+        assert!(run.tests[2].logs.is_empty());
+    }
+}
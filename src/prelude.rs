@@ -5,5 +5,8 @@
 /// Abscissa core prelude
 pub use abscissa_core::prelude::*;
 
+/// Status macros aware of `--output json`, shadowing the abscissa ones
+pub use crate::{status_info, status_ok, status_warn};
+
 /// Application state
 pub use crate::application::APP;
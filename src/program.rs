@@ -0,0 +1,562 @@
+//! Detection of program properties from the tested project sources
+
+use quote::ToTokens;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::{Block, ImplItem, Item, ItemEnum, LitStr, Visibility};
+use toml_edit::Document;
+
+use crate::instruction::{self, InstructionEnum};
+use crate::utility;
+
+/// Where the program ID was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgramIdSource {
+    DeclareId,
+    AnchorToml,
+    DeployKeypair,
+    Generated,
+}
+
+impl fmt::Display for ProgramIdSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProgramIdSource::DeclareId => write!(f, "declare_id!"),
+            ProgramIdSource::AnchorToml => write!(f, "Anchor.toml"),
+            ProgramIdSource::DeployKeypair => write!(f, "deploy keypair"),
+            ProgramIdSource::Generated => write!(f, "generated"),
+        }
+    }
+}
+
+/// Resolve the program ID, trying `declare_id!` in the crate sources,
+/// `[programs.*]` in `Anchor.toml` and `target/deploy/<name>-keypair.json`.
+///
+/// When none of them is available a stable ID is derived from the program name
+/// so the generated test still compiles.
+pub fn find_program_id(project_path: &Path, program_name: &str) -> (String, ProgramIdSource) {
+    if let Some(id) = find_declare_id(&project_path.join("src")) {
+        return (id, ProgramIdSource::DeclareId);
+    }
+    if let Some(id) = find_anchor_toml_id(project_path, program_name) {
+        return (id, ProgramIdSource::AnchorToml);
+    }
+    if let Some(id) = find_deploy_keypair_id(project_path, program_name) {
+        return (id, ProgramIdSource::DeployKeypair);
+    }
+    let seed = Sha256::digest(format!("solana-test:{}", program_name).as_bytes());
+    (
+        bs58::encode(seed.as_slice()).into_string(),
+        ProgramIdSource::Generated,
+    )
+}
+
+fn find_declare_id(src_dir: &Path) -> Option<String> {
+    rust_files(src_dir).iter().find_map(|file| {
+        let syntax = syn::parse_file(&fs::read_to_string(file).ok()?).ok()?;
+        find_declare_id_in(&syntax.items)
+    })
+}
+
+/// Value of a `declare_id!("...")` item, skipping ones that are not a valid
+/// public key.
+fn find_declare_id_in(items: &[Item]) -> Option<String> {
+    items.iter().find_map(|item| match item {
+        Item::Macro(item_macro)
+            if item_macro
+                .mac
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "declare_id") =>
+        {
+            let id = item_macro.mac.parse_body::<LitStr>().ok()?.value();
+            utility::is_pubkey(&id).then_some(id)
+        }
+        Item::Mod(item_mod) => find_declare_id_in(&item_mod.content.as_ref()?.1),
+        _ => None,
+    })
+}
+
+fn find_anchor_toml_id(project_path: &Path, program_name: &str) -> Option<String> {
+    let anchor_toml = project_path
+        .ancestors()
+        .map(|dir| dir.join("Anchor.toml"))
+        .find(|path| path.exists())?;
+    let document = fs::read_to_string(anchor_toml)
+        .ok()?
+        .parse::<Document>()
+        .ok()?;
+    let programs = document.get("programs")?.as_table_like()?;
+
+    // Prefer localnet as that is what tests run against
+    let clusters = programs
+        .get("localnet")
+        .into_iter()
+        .chain(programs.iter().map(|(_, cluster)| cluster));
+    for cluster in clusters {
+        if let Some(id) = cluster
+            .as_table_like()
+            .and_then(|cluster| cluster.get(program_name))
+            .and_then(|id| id.as_str())
+            .filter(|id| utility::is_pubkey(id))
+        {
+            return Some(id.to_string());
+        }
+    }
+    None
+}
+
+fn find_deploy_keypair_id(project_path: &Path, program_name: &str) -> Option<String> {
+    let keypair_path = project_path
+        .ancestors()
+        .map(|dir| {
+            dir.join("target")
+                .join("deploy")
+                .join(format!("{}-keypair.json", program_name))
+        })
+        .find(|path| path.exists())?;
+    let bytes: Vec<u8> = serde_json::from_str(&fs::read_to_string(keypair_path).ok()?).ok()?;
+    // Keypair files hold the 32 byte secret followed by the 32 byte public key
+    if bytes.len() != 64 {
+        return None;
+    }
+    Some(bs58::encode(&bytes[32..]).into_string())
+}
+
+/// Find the function to pass to `processor!` for running the program natively.
+///
+/// For Anchor programs this is the `entry` function the `#[program]` macro
+/// generates next to the program module. For native programs it is the
+/// `entrypoint!` target, or a public `process_instruction` function or
+/// `Processor` method. Only items reachable through public modules are
+/// returned, as the path is used from an integration test.
+pub fn find_entrypoint(project_path: &Path, crate_ident: &str, is_anchor: bool) -> Option<String> {
+    let lib_rs = project_path.join("src").join("lib.rs");
+    let mut crate_items = CrateItems::default();
+    collect_items(&lib_rs, &[], true, &mut crate_items);
+
+    let to_path = |module: &[String], name: &str| {
+        let mut segments = vec![crate_ident.to_string()];
+        segments.extend(module.iter().cloned());
+        segments.push(name.to_string());
+        segments.join("::")
+    };
+
+    if is_anchor {
+        return crate_items
+            .program_modules
+            .iter()
+            .find(|module| module.public)
+            .map(|module| to_path(&module.path[..module.path.len() - 1], "entry"));
+    }
+
+    // `entrypoint!(process_instruction)` refers to a function in the same module
+    for (module, target) in &crate_items.entrypoints {
+        let mut path = module.clone();
+        path.extend(target.iter().take(target.len() - 1).cloned());
+        let name = target.last()?;
+        if crate_items.functions.iter().any(|f| {
+            f.public
+                && &f.path[..f.path.len() - 1] == path.as_slice()
+                && f.path.last() == Some(name)
+        }) {
+            return Some(to_path(&path, name));
+        }
+    }
+
+    let mut candidates: Vec<&ItemPath> = crate_items
+        .functions
+        .iter()
+        .filter(|f| {
+            f.public
+                && f.inputs == 3
+                && matches!(
+                    f.path.last().map(String::as_str),
+                    Some("process_instruction") | Some("process")
+                )
+        })
+        .collect();
+    // Prefer free `process_instruction` functions and the shallowest paths
+    candidates.sort_by_key(|f| {
+        (
+            f.path.last().map(String::as_str) != Some("process_instruction"),
+            f.path.len(),
+        )
+    });
+    candidates
+        .first()
+        .map(|f| to_path(&f.path[..f.path.len() - 1], f.path.last().unwrap()))
+}
+
+/// Find the Borsh instruction enum of a native program.
+///
+/// Of the public enums deriving `BorshSerialize` or `BorshDeserialize`, the one
+/// referred to by the `process*` functions is preferred, then one whose name
+/// ends in `Instruction`.
+pub fn find_instruction_enum(project_path: &Path, crate_ident: &str) -> Option<InstructionEnum> {
+    let lib_rs = project_path.join("src").join("lib.rs");
+    let mut crate_items = CrateItems::default();
+    collect_items(&lib_rs, &[], true, &mut crate_items);
+
+    let candidates: Vec<&(ItemPath, ItemEnum)> = crate_items
+        .enums
+        .iter()
+        .filter(|(path, item)| path.public && instruction::is_borsh_enum(item))
+        .collect();
+    let (path, item) = candidates
+        .iter()
+        .find(|(_, item)| {
+            crate_items
+                .processor_idents
+                .contains(&item.ident.to_string())
+        })
+        .or_else(|| {
+            candidates
+                .iter()
+                .find(|(_, item)| item.ident.to_string().ends_with("Instruction"))
+        })?;
+
+    let mut segments = vec![crate_ident.to_string()];
+    segments.extend(path.path.iter().cloned());
+    Some(InstructionEnum::from_item(segments.join("::"), item))
+}
+
+#[derive(Default)]
+struct CrateItems {
+    entrypoints: Vec<(Vec<String>, Vec<String>)>,
+    functions: Vec<ItemPath>,
+    program_modules: Vec<ItemPath>,
+    enums: Vec<(ItemPath, ItemEnum)>,
+    /// Identifiers used in the bodies of `process*` functions
+    processor_idents: Vec<String>,
+}
+
+struct ItemPath {
+    path: Vec<String>,
+    public: bool,
+    inputs: usize,
+}
+
+impl CrateItems {
+    fn add_function(&mut self, path: Vec<String>, public: bool, inputs: usize, block: &Block) {
+        if path.last().is_some_and(|name| name.starts_with("process")) {
+            self.processor_idents.extend(
+                block
+                    .to_token_stream()
+                    .to_string()
+                    .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .filter(|token| !token.is_empty())
+                    .map(String::from),
+            );
+        }
+        self.functions.push(ItemPath {
+            path,
+            public,
+            inputs,
+        });
+    }
+}
+
+fn collect_items(file: &Path, module: &[String], public: bool, crate_items: &mut CrateItems) {
+    let syntax = match fs::read_to_string(file)
+        .ok()
+        .and_then(|contents| syn::parse_file(&contents).ok())
+    {
+        Some(syntax) => syntax,
+        None => return,
+    };
+    // Submodules of `lib.rs` and `mod.rs` live next to them, others in a
+    // directory named after the module
+    let is_mod_root = matches!(
+        file.file_name().and_then(|name| name.to_str()),
+        Some("lib.rs") | Some("mod.rs")
+    );
+    let dir = if is_mod_root {
+        file.parent().unwrap().to_path_buf()
+    } else {
+        file.with_extension("")
+    };
+    collect_module_items(&syntax.items, &dir, module, public, crate_items);
+}
+
+fn collect_module_items(
+    items: &[Item],
+    dir: &Path,
+    module: &[String],
+    public: bool,
+    crate_items: &mut CrateItems,
+) {
+    let item_path = |name: String| {
+        let mut path = module.to_vec();
+        path.push(name);
+        path
+    };
+    for item in items {
+        match item {
+            Item::Mod(item_mod) => {
+                let name = item_mod.ident.to_string();
+                let path = item_path(name.clone());
+                let is_public = public && is_pub(&item_mod.vis);
+                if item_mod
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path.is_ident("program"))
+                {
+                    crate_items.program_modules.push(ItemPath {
+                        path: path.clone(),
+                        public: is_public,
+                        inputs: 0,
+                    });
+                }
+                match &item_mod.content {
+                    Some((_, content)) => collect_module_items(
+                        content,
+                        &dir.join(&name),
+                        &path,
+                        is_public,
+                        crate_items,
+                    ),
+                    None => {
+                        let file = dir.join(format!("{}.rs", name));
+                        let file = if file.exists() {
+                            file
+                        } else {
+                            dir.join(&name).join("mod.rs")
+                        };
+                        collect_items(&file, &path, is_public, crate_items);
+                    }
+                }
+            }
+            Item::Macro(item_macro) => {
+                let is_entrypoint = item_macro
+                    .mac
+                    .path
+                    .segments
+                    .last()
+                    .is_some_and(|segment| segment.ident == "entrypoint");
+                if let (true, Ok(target)) =
+                    (is_entrypoint, item_macro.mac.parse_body::<syn::Path>())
+                {
+                    let target = target
+                        .segments
+                        .iter()
+                        .map(|segment| segment.ident.to_string())
+                        .collect();
+                    crate_items.entrypoints.push((module.to_vec(), target));
+                }
+            }
+            Item::Fn(item_fn) => crate_items.add_function(
+                item_path(item_fn.sig.ident.to_string()),
+                public && is_pub(&item_fn.vis),
+                item_fn.sig.inputs.len(),
+                &item_fn.block,
+            ),
+            Item::Enum(item_enum) => crate_items.enums.push((
+                ItemPath {
+                    path: item_path(item_enum.ident.to_string()),
+                    public: public && is_pub(&item_enum.vis),
+                    inputs: 0,
+                },
+                item_enum.clone(),
+            )),
+            Item::Impl(item_impl) if item_impl.trait_.is_none() => {
+                let self_ty = match &*item_impl.self_ty {
+                    syn::Type::Path(type_path) => match type_path.path.segments.last() {
+                        Some(segment) => segment.ident.to_string(),
+                        None => continue,
+                    },
+                    _ => continue,
+                };
+                for impl_item in &item_impl.items {
+                    if let ImplItem::Method(method) = impl_item {
+                        let mut path = item_path(self_ty.clone());
+                        path.push(method.sig.ident.to_string());
+                        crate_items.add_function(
+                            path,
+                            public && is_pub(&method.vis),
+                            method.sig.inputs.len(),
+                            &method.block,
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_pub(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+/// All `.rs` files below `dir`, sorted so that `lib.rs` is visited first.
+pub fn rust_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    collect_rust_files(dir, &mut files);
+    files.sort_by_key(|file| (file.file_name() != Some("lib.rs".as_ref()), file.clone()));
+    files
+}
+
+fn collect_rust_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_rust_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM_ID: &str = "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS";
+
+    fn project(lib_rs: &str) -> tempfile::TempDir {
+        let project = tempfile::tempdir().unwrap();
+        fs::create_dir_all(project.path().join("src")).unwrap();
+        fs::write(project.path().join("src").join("lib.rs"), lib_rs).unwrap();
+        project
+    }
+
+    #[test]
+    fn finds_declare_id() {
+        let project = project(&format!(
+            "use solana_program::declare_id;\ndeclare_id!(\"{}\");\n",
+            PROGRAM_ID
+        ));
+        assert_eq!(
+            find_program_id(project.path(), "my_prog"),
+            (PROGRAM_ID.to_string(), ProgramIdSource::DeclareId)
+        );
+    }
+
+    #[test]
+    fn finds_declare_id_in_inline_module() {
+        let project = project(&format!(
+            "pub mod id {{\n    solana_program::declare_id!(\"{}\");\n}}\n",
+            PROGRAM_ID
+        ));
+        assert_eq!(
+            find_declare_id(&project.path().join("src")),
+            Some(PROGRAM_ID.to_string())
+        );
+    }
+
+    #[test]
+    fn ignores_commented_out_and_invalid_declare_id() {
+        let project = project(&format!(
+            "// declare_id!(\"{}\");\n/* declare_id!(\"{}\"); */\ndeclare_id!(\"not a key\");\n",
+            PROGRAM_ID, PROGRAM_ID
+        ));
+        let (_, source) = find_program_id(project.path(), "my_prog");
+        assert_eq!(source, ProgramIdSource::Generated);
+    }
+
+    #[test]
+    fn falls_back_to_anchor_toml() {
+        let project = project("");
+        fs::write(
+            project.path().join("Anchor.toml"),
+            format!(
+                "[programs.devnet]\nmy_prog = \"invalid\"\n\n[programs.localnet]\nmy_prog = \"{}\"\n",
+                PROGRAM_ID
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            find_program_id(project.path(), "my_prog"),
+            (PROGRAM_ID.to_string(), ProgramIdSource::AnchorToml)
+        );
+    }
+
+    #[test]
+    fn falls_back_to_deploy_keypair() {
+        let project = project("");
+        let deploy = project.path().join("target").join("deploy");
+        fs::create_dir_all(&deploy).unwrap();
+        let keypair = crate::keys::derive("seed", "program");
+        fs::write(
+            deploy.join("my_prog-keypair.json"),
+            crate::keys::to_json(&keypair),
+        )
+        .unwrap();
+        assert_eq!(
+            find_program_id(project.path(), "my_prog"),
+            (
+                crate::keys::pubkey(&keypair),
+                ProgramIdSource::DeployKeypair
+            )
+        );
+    }
+
+    #[test]
+    fn generated_id_is_stable_public_key() {
+        let project = project("");
+        let (id, source) = find_program_id(project.path(), "my_prog");
+        assert_eq!(source, ProgramIdSource::Generated);
+        assert!(utility::is_pubkey(&id));
+        assert_eq!(find_program_id(project.path(), "my_prog").0, id);
+        assert_ne!(find_program_id(project.path(), "other").0, id);
+    }
+
+    #[test]
+    fn finds_anchor_entry() {
+        let project = project("use anchor_lang::prelude::*;\n#[program]\npub mod my_prog {}\n");
+        assert_eq!(
+            find_entrypoint(project.path(), "my_prog", true),
+            Some(String::from("my_prog::entry"))
+        );
+    }
+
+    #[test]
+    fn finds_entrypoint_macro_target() {
+        let project = project("pub mod entrypoint;\npub mod processor;\n");
+        let src = project.path().join("src");
+        fs::write(
+            src.join("entrypoint.rs"),
+            "entrypoint!(process_instruction);\npub fn process_instruction(a: A, b: B, c: C) {}\n",
+        )
+        .unwrap();
+        fs::write(
+            src.join("processor.rs"),
+            "pub fn process_instruction(a: A, b: B, c: C) {}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            find_entrypoint(project.path(), "my_prog", false),
+            Some(String::from("my_prog::entrypoint::process_instruction"))
+        );
+    }
+
+    #[test]
+    fn falls_back_to_public_processor() {
+        let project = project(
+            "mod private { pub fn process_instruction(a: A, b: B, c: C) {} }\n\
+             pub mod processor {\n\
+                 pub struct Processor;\n\
+                 impl Processor { pub fn process(a: A, b: B, c: C) {} }\n\
+             }\n",
+        );
+        assert_eq!(
+            find_entrypoint(project.path(), "my_prog", false),
+            Some(String::from("my_prog::processor::Processor::process"))
+        );
+    }
+
+    #[test]
+    fn no_entrypoint_in_private_modules() {
+        let project =
+            project("mod processor { pub fn process_instruction(a: A, b: B, c: C) {} }\n");
+        assert_eq!(find_entrypoint(project.path(), "my_prog", false), None);
+    }
+}
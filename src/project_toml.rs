@@ -1,7 +1,10 @@
+use similar::TextDiff;
 use std::fs;
-use std::path::PathBuf;
-use toml_edit::{table, value, Array, Document};
+use std::path::{Path, PathBuf};
+use toml_edit::{table, value, Array, Document, InlineTable};
 
+use crate::compat::{TEST_BPF_FEATURE, TEST_SBF_FEATURE};
+use crate::config::{FrameworkSource, GitReference};
 use crate::error::{Error, ErrorKind};
 
 #[derive(Debug, Clone)]
@@ -11,24 +14,14 @@ pub struct ProjectToml {
     pub is_anchor: bool,
 }
 impl ProjectToml {
-    pub fn new(path: PathBuf, is_anchor: &Option<bool>) -> Result<ProjectToml, ErrorKind> {
+    pub fn new(path: PathBuf, is_anchor: &Option<bool>) -> Result<ProjectToml, Error> {
         let path_to_project_toml = PathBuf::new().join(path.clone()).join("Cargo.toml");
-
-        if !path_to_project_toml.exists() {
-            return Err(ErrorKind::MissingCargoFile);
-        }
-
-        let project_toml = fs::read_to_string(
-            path_to_project_toml
-                .to_str()
-                .expect("Cannot convert path to str"),
-        )
-        .expect("Something went wrong reading the file");
-
-        let project_toml_parsed = project_toml.parse::<Document>().unwrap();
+        let project_toml_parsed = read_manifest(&path_to_project_toml)?;
 
         if !is_correct_cargo_toml(project_toml_parsed.clone()) {
-            return Err(ErrorKind::IncorrectCargoFile);
+            return Err(ErrorKind::IncorrectCargoFile
+                .context(path_to_project_toml.display().to_string())
+                .into());
         }
 
         let is_anchor: bool = if is_anchor.is_some() {
@@ -43,68 +36,253 @@ impl ProjectToml {
             is_anchor,
         })
     }
-    pub fn add_test_bpf_feature(&mut self) -> Result<(), ErrorKind> {
+    /// Returns `false` when the feature was already present.
+    pub fn add_test_feature(&mut self, feature: &str) -> Result<bool, ErrorKind> {
         let is_features = self.document.get("features").is_some();
 
-        if is_features && self.document["features"].get("test-bpf").is_some() {
-            return Ok(());
+        if is_features && self.document["features"].get(feature).is_some() {
+            return Ok(false);
         }
 
         if !is_features {
             self.document["features"] = table();
         }
         let empty_arr = Array::default();
-        self.document["features"]["test-bpf"] = value(empty_arr);
-        Ok(())
+        self.document["features"][feature] = value(empty_arr);
+        Ok(true)
+    }
+
+    /// Test feature already declared in `[features]`, if any.
+    pub fn existing_test_feature(&self) -> Option<&'static str> {
+        let features = self.document.get("features")?;
+        [TEST_SBF_FEATURE, TEST_BPF_FEATURE]
+            .into_iter()
+            .find(|feature| features.get(feature).is_some())
+    }
+
+    /// Rename a feature, keeping its value, and the references to it in other
+    /// features such as `default = ["test-bpf"]`. When `to` already exists,
+    /// `from` is only removed. Returns `false` when `from` is not declared.
+    pub fn rename_feature(&mut self, from: &str, to: &str) -> bool {
+        let features = match self
+            .document
+            .get_mut("features")
+            .and_then(|item| item.as_table_like_mut())
+        {
+            Some(features) => features,
+            None => return false,
+        };
+        let item = match features.remove(from) {
+            Some(item) => item,
+            None => return false,
+        };
+        if !features.contains_key(to) {
+            features.insert(to, item);
+        }
+        for (_, enabled) in features.iter_mut() {
+            let enabled = match enabled.as_array_mut() {
+                Some(enabled) => enabled,
+                None => continue,
+            };
+            let has_to = enabled.iter().any(|feature| feature.as_str() == Some(to));
+            let position = enabled
+                .iter()
+                .position(|feature| feature.as_str() == Some(from));
+            match position {
+                Some(index) if has_to => {
+                    // The next entry takes over the spacing of the removed one
+                    let removed = enabled.remove(index);
+                    if let (Some(prefix), Some(next)) =
+                        (removed.decor().prefix(), enabled.get_mut(index))
+                    {
+                        next.decor_mut().set_prefix(prefix);
+                    }
+                }
+                Some(index) => {
+                    enabled.replace(index, to);
+                }
+                None => {}
+            }
+        }
+        true
     }
+    /// Returns `false` when the dev-dependencies were already up to date.
     pub fn add_framework_as_dev_dependency(
         &mut self,
-        framework_url: &str,
-        framework_branch: &str,
+        framework_source: &FrameworkSource,
         framework_name: &str,
-    ) -> Result<(), ErrorKind> {
-        if self.document.get("dev-dependnecies").is_none() {
+    ) -> Result<bool, Error> {
+        let before = self.document.get("dev-dependencies").map(|d| d.to_string());
+
+        if self.document.get("dev-dependencies").is_none() {
             self.document["dev-dependencies"] = table();
         }
-        self.document["dev-dependencies"][framework_name]["git"] = value(framework_url);
-        self.document["dev-dependencies"][framework_name]["branch"] = value(framework_branch);
 
+        // Drop entries for the same repository added under a different name
+        if let FrameworkSource::Git { url, .. } = framework_source {
+            if let Some(dev_dependencies) = self.document["dev-dependencies"].as_table_like_mut() {
+                let duplicates: Vec<String> = dev_dependencies
+                    .iter()
+                    .filter(|(name, dependency)| {
+                        *name != framework_name
+                            && dependency.get("git").and_then(|git| git.as_str())
+                                == Some(url.as_str())
+                    })
+                    .map(|(name, _)| name.to_string())
+                    .collect();
+                for name in duplicates {
+                    dev_dependencies.remove(&name);
+                }
+            }
+        }
+
+        // Replace the whole entry so keys of a previous source do not linger
+        let mut dependency = InlineTable::new();
+        match framework_source {
+            FrameworkSource::Git { url, reference } => {
+                dependency.insert("git", url.as_str().into());
+                let (key, reference) = match reference {
+                    GitReference::Branch(branch) => ("branch", branch),
+                    GitReference::Tag(tag) => ("tag", tag),
+                    GitReference::Rev(rev) => ("rev", rev),
+                };
+                dependency.insert(key, reference.as_str().into());
+            }
+            FrameworkSource::Path(path) => {
+                let path = path.to_str().ok_or_else(|| {
+                    ErrorKind::NonUtf8Path.context(path.to_string_lossy().into_owned())
+                })?;
+                dependency.insert("path", path.into());
+            }
+            FrameworkSource::Registry(version) => {
+                dependency.insert("version", version.as_str().into());
+            }
+        }
         if self.is_anchor {
-            self.document["dev-dependencies"][framework_name]["features"] = table();
             let mut arr = Array::default();
             arr.push("anchor");
-            self.document["dev-dependencies"][framework_name]["features"] = value(arr);
+            dependency.insert("features", arr.into());
         }
+        dependency.fmt();
+        self.document["dev-dependencies"][framework_name] = value(dependency);
 
-        // Add solana test framework - it's crucial for framework to work
-        // @todo when switch between solana version - this should be aligned
-        self.document["dev-dependencies"]["solana-program-test"]["version"] = value("1.9");
+        let after = self.document.get("dev-dependencies").map(|d| d.to_string());
+        Ok(before != after)
+    }
 
-        Ok(())
+    /// Add `solana-program-test` - it's crucial for framework to work - or
+    /// align the version of an existing entry with `version`. Entries without
+    /// a version, e.g. inherited from the workspace, are left alone.
+    ///
+    /// Returns a description of the change, `None` when up to date.
+    pub fn set_program_test_version(&mut self, version: &str) -> Option<String> {
+        if self.document.get("dev-dependencies").is_none() {
+            self.document["dev-dependencies"] = table();
+        }
+        let dependency = &mut self.document["dev-dependencies"]["solana-program-test"];
+        if dependency.is_none() {
+            dependency["version"] = value(version);
+            return Some(format!("added solana-program-test {}", version));
+        }
+        let previous = match dependency.as_str() {
+            Some(previous) => previous.to_string(),
+            None => dependency.get("version")?.as_str()?.to_string(),
+        };
+        if previous == version {
+            return None;
+        }
+        if dependency.is_str() {
+            *dependency = value(version);
+        } else {
+            dependency["version"] = value(version);
+        }
+        Some(format!(
+            "updated solana-program-test from {} to {}",
+            previous, version
+        ))
     }
-    pub fn save_toml(self) {
+
+    pub fn save_toml(self) -> Result<(), Error> {
         let contents = self.document.to_string();
-        fs::write(self.path, contents).expect("Could not write to file!");
+        fs::write(&self.path, contents).map_err(|e| {
+            ErrorKind::WriteFailed
+                .context(format!("{}: {}", self.path.display(), e))
+                .into()
+        })
     }
 
     pub fn get_is_anchor(self) -> bool {
-        return self.is_anchor;
+        self.is_anchor
+    }
+
+    /// Crate identifier as used in Rust paths - `[lib].name` if set,
+    /// otherwise `[package].name` with dashes replaced.
+    pub fn crate_name(&self) -> Option<String> {
+        let name = self
+            .document
+            .get("lib")
+            .and_then(|lib| lib.get("name"))
+            .or_else(|| self.document.get("package").and_then(|p| p.get("name")))
+            .and_then(|name| name.as_str())?;
+        Some(name.replace('-', "_"))
     }
 
-    pub fn modify_project_toml(
-        mut self,
-        framework_repo_url: &String,
-        framework_branch: &String,
-        framework_name: &String,
-    ) -> Result<(), Error> {
-        self.add_test_bpf_feature()?;
+    /// Apply all changes needed by the framework to the in-memory document.
+    pub fn apply_changes(
+        &mut self,
+        test_feature: &str,
+        framework_source: &FrameworkSource,
+        framework_name: &str,
+        program_test_version: &str,
+    ) -> Result<Vec<String>, Error> {
+        let mut changes = Vec::new();
+        if self.add_test_feature(test_feature)? {
+            changes.push(format!("added {} feature", test_feature));
+        }
 
-        self.add_framework_as_dev_dependency(framework_repo_url, framework_branch, framework_name)?;
+        if self.add_framework_as_dev_dependency(framework_source, framework_name)? {
+            changes.push(format!(
+                "set {} dev-dependency to {}",
+                framework_name, framework_source
+            ));
+        }
+        changes.extend(self.set_program_test_version(program_test_version));
 
-        self.save_toml();
+        Ok(changes)
+    }
+
+    /// Unified diff between the manifest on disk and the in-memory document.
+    pub fn diff(&self) -> Result<String, Error> {
+        let original = fs::read_to_string(&self.path)?;
+        let modified = self.document.to_string();
+        let path = self.path.display().to_string();
+        Ok(TextDiff::from_lines(&original, &modified)
+            .unified_diff()
+            .header(&path, &path)
+            .to_string())
+    }
+}
 
-        Ok(())
+/// Read and parse a manifest.
+pub fn read_manifest(path: &Path) -> Result<Document, Error> {
+    if !path.exists() {
+        return Err(ErrorKind::MissingCargoFile
+            .context(path.display().to_string())
+            .into());
     }
+    let contents = fs::read_to_string(path)
+        .map_err(|e| ErrorKind::UnreadableManifest.context(format!("{}: {}", path.display(), e)))?;
+    parse_manifest(&contents, path)
+}
+
+/// Parse a manifest, reporting the line and column of syntax errors.
+pub fn parse_manifest(contents: &str, path: &Path) -> Result<Document, Error> {
+    contents.parse::<Document>().map_err(|e| {
+        // The parser message starts with "TOML parse error at line X, column Y"
+        ErrorKind::TomlParse
+            .context(format!("{}: {}", path.display(), e.to_string().trim_end()))
+            .into()
+    })
 }
 
 pub fn is_correct_cargo_toml(project_toml: Document) -> bool {
@@ -122,3 +300,219 @@ pub fn check_if_is_anchor(project_toml: &Document) -> bool {
     }
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAMEWORK_URL: &str = "https://github.com/lowprivuser/solana-test-framework";
+
+    fn project(manifest: &str) -> (tempfile::TempDir, ProjectToml) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), manifest).unwrap();
+        let project_toml = ProjectToml::new(dir.path().to_path_buf(), &None).unwrap();
+        (dir, project_toml)
+    }
+
+    fn git_source(branch: &str) -> FrameworkSource {
+        FrameworkSource::Git {
+            url: String::from(FRAMEWORK_URL),
+            reference: GitReference::Branch(String::from(branch)),
+        }
+    }
+
+    #[test]
+    fn diff_shows_planned_changes_without_writing() {
+        let manifest = "[package]\nname = \"vault\"\n";
+        let (dir, mut project_toml) = project(manifest);
+        let changes = project_toml
+            .apply_changes(
+                TEST_SBF_FEATURE,
+                &git_source("solana-1.17"),
+                "solana-test-framework",
+                "~1.17",
+            )
+            .unwrap();
+        assert_eq!(changes.len(), 3);
+
+        let diff = project_toml.diff().unwrap();
+        assert!(diff.contains("+[features]\n"));
+        assert!(diff.contains("+test-sbf = []\n"));
+        assert!(diff.contains("+[dev-dependencies]\n"));
+        assert!(diff.contains(&format!(
+            "+solana-test-framework = {{ git = \"{}\", branch = \"solana-1.17\" }}\n",
+            FRAMEWORK_URL
+        )));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("Cargo.toml")).unwrap(),
+            manifest
+        );
+    }
+
+    #[test]
+    fn no_changes_when_up_to_date() {
+        let (_dir, mut project_toml) = project("[package]\nname = \"vault\"\n");
+        let source = git_source("solana-1.17");
+        project_toml
+            .apply_changes(TEST_SBF_FEATURE, &source, "solana-test-framework", "~1.17")
+            .unwrap();
+        let changes = project_toml
+            .apply_changes(TEST_SBF_FEATURE, &source, "solana-test-framework", "~1.17")
+            .unwrap();
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn adds_program_test() {
+        let (_dir, mut project_toml) = project("[package]\nname = \"vault\"\n");
+        assert_eq!(
+            project_toml.set_program_test_version("~1.14"),
+            Some(String::from("added solana-program-test ~1.14"))
+        );
+        assert_eq!(
+            project_toml.document["dev-dependencies"]["solana-program-test"]["version"].as_str(),
+            Some("~1.14")
+        );
+        assert_eq!(project_toml.set_program_test_version("~1.14"), None);
+    }
+
+    #[test]
+    fn updates_stale_program_test() {
+        let (_dir, mut project_toml) = project(
+            "[package]\nname = \"vault\"\n\n[dev-dependencies]\nsolana-program-test = \"1.9\"\n",
+        );
+        assert_eq!(
+            project_toml.set_program_test_version("~1.14.17"),
+            Some(String::from(
+                "updated solana-program-test from 1.9 to ~1.14.17"
+            ))
+        );
+        assert_eq!(
+            project_toml.document["dev-dependencies"]["solana-program-test"].as_str(),
+            Some("~1.14.17")
+        );
+
+        let (_dir, mut project_toml) = project(
+            "[package]\nname = \"vault\"\n\n[dev-dependencies]\nsolana-program-test = { version = \"1.9\", features = [\"x\"] }\n",
+        );
+        assert!(project_toml.set_program_test_version("~1.14").is_some());
+        assert!(project_toml
+            .document
+            .to_string()
+            .contains("solana-program-test = { version = \"~1.14\", features = [\"x\"] }"));
+    }
+
+    #[test]
+    fn keeps_program_test_without_version() {
+        let manifest = "[package]\nname = \"vault\"\n\n[dev-dependencies]\nsolana-program-test = { workspace = true }\n";
+        let (_dir, mut project_toml) = project(manifest);
+        assert_eq!(project_toml.set_program_test_version("~1.14"), None);
+        assert_eq!(project_toml.document.to_string(), manifest);
+    }
+
+    fn framework_entry(project_toml: &ProjectToml) -> String {
+        project_toml.document["dev-dependencies"]["solana-test-framework"]
+            .to_string()
+            .trim()
+            .to_string()
+    }
+
+    #[test]
+    fn writes_each_framework_source() {
+        let (_dir, mut project_toml) = project("[package]\nname = \"vault\"\n");
+        let sources = [
+            (
+                FrameworkSource::Git {
+                    url: String::from(FRAMEWORK_URL),
+                    reference: GitReference::Tag(String::from("v0.1.0")),
+                },
+                format!("{{ git = \"{}\", tag = \"v0.1.0\" }}", FRAMEWORK_URL),
+            ),
+            (
+                FrameworkSource::Git {
+                    url: String::from(FRAMEWORK_URL),
+                    reference: GitReference::Rev(String::from("0123abc")),
+                },
+                format!("{{ git = \"{}\", rev = \"0123abc\" }}", FRAMEWORK_URL),
+            ),
+            (
+                FrameworkSource::Path(PathBuf::from("/src/framework")),
+                String::from("{ path = \"/src/framework\" }"),
+            ),
+            (
+                FrameworkSource::Registry(String::from("0.3")),
+                String::from("{ version = \"0.3\" }"),
+            ),
+        ];
+        // Each source replaces the whole entry of the previous one
+        for (source, expected) in sources {
+            assert!(project_toml
+                .add_framework_as_dev_dependency(&source, "solana-test-framework")
+                .unwrap());
+            assert_eq!(framework_entry(&project_toml), expected);
+        }
+    }
+
+    #[test]
+    fn anchor_projects_enable_anchor_feature() {
+        let (_dir, mut project_toml) =
+            project("[package]\nname = \"vault\"\n\n[dependencies]\nanchor-lang = \"0.26\"\n");
+        assert!(project_toml.is_anchor);
+        project_toml
+            .add_framework_as_dev_dependency(
+                &FrameworkSource::Registry(String::from("0.3")),
+                "solana-test-framework",
+            )
+            .unwrap();
+        assert_eq!(
+            framework_entry(&project_toml),
+            "{ version = \"0.3\", features = [\"anchor\"] }"
+        );
+    }
+
+    #[test]
+    fn replaces_framework_added_under_other_name() {
+        let (_dir, mut project_toml) = project(&format!(
+            "[package]\nname = \"vault\"\n\n[dev-dependencies]\nstf = {{ git = \"{}\" }}\nserde = \"1\"\n",
+            FRAMEWORK_URL
+        ));
+        project_toml
+            .add_framework_as_dev_dependency(&git_source("solana-1.14"), "solana-test-framework")
+            .unwrap();
+        let dev_dependencies = project_toml.document["dev-dependencies"]
+            .as_table_like()
+            .unwrap();
+        let names: Vec<&str> = dev_dependencies.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["serde", "solana-test-framework"]);
+    }
+
+    #[test]
+    fn rename_feature_updates_references() {
+        let manifest = "[package]\nname = \"vault\"\n\n[features]\n\
+                        default = [\"test-bpf\", \"serde\"]\n\
+                        test-bpf = []\n\
+                        full = [\"test-bpf\", \"test-sbf\"]\n\
+                        test-sbf = []\n";
+        let (_dir, mut project_toml) = project(manifest);
+        assert!(project_toml.rename_feature("test-bpf", "test-sbf"));
+        assert_eq!(
+            project_toml.document.to_string(),
+            "[package]\nname = \"vault\"\n\n[features]\n\
+             default = [\"test-sbf\", \"serde\"]\n\
+             full = [\"test-sbf\"]\n\
+             test-sbf = []\n"
+        );
+    }
+
+    #[test]
+    fn rename_feature_keeps_value() {
+        let manifest = "[package]\nname = \"vault\"\n\n[features]\ntest-bpf = [\"dep/feature\"]\n";
+        let (_dir, mut project_toml) = project(manifest);
+        assert!(project_toml.rename_feature("test-bpf", "test-sbf"));
+        assert_eq!(
+            project_toml.document.to_string(),
+            "[package]\nname = \"vault\"\n\n[features]\ntest-sbf = [\"dep/feature\"]\n"
+        );
+        assert!(!project_toml.rename_feature("test-bpf", "test-sbf"));
+    }
+}
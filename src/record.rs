@@ -0,0 +1,486 @@
+//! Record of the changes init made to a project
+//!
+//! Init writes `.solana-test/init.json` next to the project's `Cargo.toml`
+//! so that deinit can remove exactly what was added, even across several
+//! init runs.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{Document, Item, Value};
+
+use crate::cache::sha256_hex;
+use crate::error::{Error, ErrorKind};
+
+const RECORD_DIR: &str = ".solana-test";
+const RECORD_FILE: &str = "init.json";
+
+/// Manifest tables init adds keys to
+const RECORDED_TABLES: &[&str] = &["features", "dev-dependencies"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InitRecord {
+    /// Tables that did not exist before init
+    #[serde(default)]
+    pub added_tables: Vec<String>,
+    /// Keys that did not exist before init
+    #[serde(default)]
+    pub added_keys: Vec<ManifestKey>,
+    /// Keys init changed or removed, such as duplicates of the framework
+    #[serde(default)]
+    pub replaced_keys: Vec<ReplacedKey>,
+    /// Generated test files
+    #[serde(default)]
+    pub files: Vec<GeneratedFile>,
+    /// Directories that did not exist before init
+    #[serde(default)]
+    pub created_dirs: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestKey {
+    pub table: String,
+    pub key: String,
+}
+
+/// Manifest key with the value it had before init, as an inline TOML value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplacedKey {
+    pub table: String,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedFile {
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+impl InitRecord {
+    pub fn path(project_path: &Path) -> PathBuf {
+        project_path.join(RECORD_DIR).join(RECORD_FILE)
+    }
+
+    /// Load the record of a project, `None` when init has not run there.
+    pub fn load(project_path: &Path) -> Result<Option<InitRecord>, Error> {
+        let path = InitRecord::path(project_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(&path)?;
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|e| ErrorKind::InitRecord.context(e).into())
+    }
+
+    pub fn save(&self, project_path: &Path) -> Result<(), Error> {
+        let path = InitRecord::path(project_path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        let contents =
+            serde_json::to_string_pretty(self).map_err(|e| ErrorKind::InitRecord.context(e))?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Remove the record, and its directory when nothing else is in it.
+    pub fn remove(project_path: &Path) -> Result<(), Error> {
+        let path = InitRecord::path(project_path);
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        let dir = project_path.join(RECORD_DIR);
+        if dir.exists() && fs::read_dir(&dir)?.next().is_none() {
+            fs::remove_dir(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Record the tables and keys present in `after` but not in `before`,
+    /// and the previous value of keys changed or removed. Keys recorded by an
+    /// earlier run are kept.
+    pub fn add_manifest_changes(&mut self, before: &Document, after: &Document) {
+        for table in RECORDED_TABLES {
+            let before_table = before.get(table).and_then(|item| item.as_table_like());
+            let after_table = match after.get(table).and_then(|item| item.as_table_like()) {
+                Some(after_table) => after_table,
+                None => continue,
+            };
+            if before_table.is_none() && !self.added_tables.iter().any(|t| t == table) {
+                self.added_tables.push(table.to_string());
+            }
+            for (key, _) in after_table.iter() {
+                let is_new =
+                    before_table.is_none_or(|before_table| !before_table.contains_key(key));
+                let manifest_key = ManifestKey {
+                    table: table.to_string(),
+                    key: key.to_string(),
+                };
+                if is_new && !self.added_keys.contains(&manifest_key) {
+                    self.added_keys.push(manifest_key);
+                }
+            }
+            for (key, before_item) in before_table.into_iter().flat_map(|table| table.iter()) {
+                let is_unchanged = after_table.get(key).is_some_and(|after_item| {
+                    inline_value(after_item) == inline_value(before_item)
+                });
+                let is_recorded = self
+                    .added_keys
+                    .iter()
+                    .any(|added| added.table == *table && added.key == key)
+                    || self
+                        .replaced_keys
+                        .iter()
+                        .any(|replaced| replaced.table == *table && replaced.key == key);
+                if is_unchanged || is_recorded {
+                    continue;
+                }
+                if let Some(value) = inline_value(before_item) {
+                    self.replaced_keys.push(ReplacedKey {
+                        table: table.to_string(),
+                        key: key.to_string(),
+                        value,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Create `dir` and its parents, recording the ones that did not exist.
+    pub fn create_dir_all(&mut self, dir: &Path) -> Result<(), Error> {
+        let missing: Vec<PathBuf> = dir
+            .ancestors()
+            .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+            .map(Path::to_path_buf)
+            .collect();
+        fs::create_dir_all(dir)?;
+        for dir in missing {
+            let dir = fs::canonicalize(&dir).unwrap_or(dir);
+            if !self.created_dirs.contains(&dir) {
+                self.created_dirs.push(dir);
+            }
+        }
+        Ok(())
+    }
+
+    /// Record a generated test or account data file, replacing an earlier
+    /// entry for it.
+    pub fn add_file(&mut self, path: &Path, contents: impl AsRef<[u8]>) {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.files.retain(|file| file.path != path);
+        self.files.push(GeneratedFile {
+            path,
+            sha256: sha256_hex(contents.as_ref()),
+        });
+    }
+
+    /// Follow a key renamed by migrate. When `to` existed before, only the
+    /// record of `from` is dropped so deinit leaves `to` alone.
+    pub fn rename_key(&mut self, table: &str, from: &str, to: &str, to_existed: bool) {
+        let is_from = |key: &ManifestKey| key.table == table && key.key == from;
+        if to_existed {
+            self.added_keys.retain(|key| !is_from(key));
+        } else if let Some(key) = self.added_keys.iter_mut().find(|key| is_from(key)) {
+            key.key = to.to_string();
+        }
+    }
+
+    /// Follow a generated file rewritten in place, as long as it still held
+    /// the generated contents.
+    pub fn update_file(&mut self, path: &Path, before: &str, after: &str) {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let before = sha256_hex(before.as_bytes());
+        if let Some(file) = self
+            .files
+            .iter_mut()
+            .find(|file| file.path == path && file.sha256 == before)
+        {
+            file.sha256 = sha256_hex(after.as_bytes());
+        }
+    }
+
+    /// Remove the recorded keys, restore the previous value of the replaced
+    /// ones, and remove the recorded tables once they are empty. Returns the
+    /// removed and the restored keys.
+    pub fn revert_manifest(&self, document: &mut Document) -> (Vec<String>, Vec<String>) {
+        let mut removed = Vec::new();
+        let mut restored = Vec::new();
+        for manifest_key in &self.added_keys {
+            let table = match document
+                .get_mut(&manifest_key.table)
+                .and_then(|item| item.as_table_like_mut())
+            {
+                Some(table) => table,
+                None => continue,
+            };
+            if table.remove(&manifest_key.key).is_some() {
+                removed.push(format!("{}.{}", manifest_key.table, manifest_key.key));
+            }
+        }
+        for replaced in &self.replaced_keys {
+            let table = match document
+                .get_mut(&replaced.table)
+                .and_then(|item| item.as_table_like_mut())
+            {
+                Some(table) => table,
+                None => continue,
+            };
+            if let Ok(value) = replaced.value.parse::<Value>() {
+                table.insert(&replaced.key, Item::Value(value));
+                restored.push(format!("{}.{}", replaced.table, replaced.key));
+            }
+        }
+        for table in &self.added_tables {
+            let is_empty = document
+                .get(table)
+                .and_then(|item| item.as_table_like())
+                .is_some_and(|table| table.is_empty());
+            if is_empty {
+                document.remove(table);
+                removed.push(format!("[{}]", table));
+            }
+        }
+        (removed, restored)
+    }
+
+    /// Remove the recorded directories that are empty, deepest first.
+    /// Returns the removed directories.
+    pub fn remove_created_dirs(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut dirs = self.created_dirs.clone();
+        dirs.sort_by_key(|dir| Reverse(dir.components().count()));
+        let mut removed = Vec::new();
+        for dir in dirs {
+            if dir.is_dir() && fs::read_dir(&dir)?.next().is_none() {
+                fs::remove_dir(&dir)?;
+                removed.push(dir);
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Value of a manifest entry without its formatting, tables written inline.
+fn inline_value(item: &Item) -> Option<String> {
+    let mut value = item.clone().into_value().ok()?;
+    value.decor_mut().clear();
+    if let Value::InlineTable(table) = &mut value {
+        table.fmt();
+    }
+    Some(value.to_string())
+}
+
+impl GeneratedFile {
+    /// Returns true when the file still holds the generated contents.
+    pub fn is_unmodified(&self) -> Result<bool, Error> {
+        let contents = fs::read(&self.path)?;
+        Ok(sha256_hex(&contents) == self.sha256)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"[package]
+name = "vault"
+
+[dev-dependencies]
+stf = { git = "https://github.com/lowprivuser/solana-test-framework" }
+solana-program-test = "1.9"
+serde = "1"
+"#;
+
+    fn init(before: &str) -> Document {
+        let mut after: Document = before.parse().unwrap();
+        after["features"] = toml_edit::table();
+        after["features"]["test-sbf"] = toml_edit::value(toml_edit::Array::new());
+        let dev_dependencies = after["dev-dependencies"].as_table_like_mut().unwrap();
+        dev_dependencies.remove("stf");
+        dev_dependencies.insert("solana-test-framework", toml_edit::value("0.3"));
+        after["dev-dependencies"]["solana-program-test"] = toml_edit::value("~1.14");
+        after
+    }
+
+    #[test]
+    fn reverts_added_and_replaced_keys() {
+        let before: Document = MANIFEST.parse().unwrap();
+        let mut document = init(MANIFEST);
+        let mut record = InitRecord::default();
+        record.add_manifest_changes(&before, &document);
+        assert_eq!(record.added_tables, ["features"]);
+        assert_eq!(record.added_keys.len(), 2);
+        assert_eq!(
+            record.replaced_keys,
+            [
+                ReplacedKey {
+                    table: String::from("dev-dependencies"),
+                    key: String::from("stf"),
+                    value: String::from(
+                        "{ git = \"https://github.com/lowprivuser/solana-test-framework\" }"
+                    ),
+                },
+                ReplacedKey {
+                    table: String::from("dev-dependencies"),
+                    key: String::from("solana-program-test"),
+                    value: String::from("\"1.9\""),
+                },
+            ]
+        );
+
+        let (removed, restored) = record.revert_manifest(&mut document);
+        assert_eq!(
+            removed,
+            [
+                "features.test-sbf",
+                "dev-dependencies.solana-test-framework",
+                "[features]"
+            ]
+        );
+        assert_eq!(
+            restored,
+            [
+                "dev-dependencies.stf",
+                "dev-dependencies.solana-program-test"
+            ]
+        );
+        let dev_dependencies = &document["dev-dependencies"];
+        assert_eq!(
+            dev_dependencies["solana-program-test"].as_str(),
+            Some("1.9")
+        );
+        assert_eq!(dev_dependencies["serde"].as_str(), Some("1"));
+        assert_eq!(
+            dev_dependencies["stf"]["git"].as_str(),
+            Some("https://github.com/lowprivuser/solana-test-framework")
+        );
+        assert!(document.get("features").is_none());
+    }
+
+    #[test]
+    fn keeps_values_from_before_the_first_run() {
+        let before: Document = MANIFEST.parse().unwrap();
+        let first = init(MANIFEST);
+        let mut record = InitRecord::default();
+        record.add_manifest_changes(&before, &first);
+
+        let mut second = first.clone();
+        second["dev-dependencies"]["solana-program-test"] = toml_edit::value("~1.16");
+        second["dev-dependencies"]["solana-test-framework"] = toml_edit::value("0.4");
+        record.add_manifest_changes(&first, &second);
+        assert_eq!(record.added_keys.len(), 2);
+        assert_eq!(record.replaced_keys.len(), 2);
+        assert_eq!(record.replaced_keys[1].value, "\"1.9\"");
+    }
+
+    #[test]
+    fn unchanged_formatting_is_not_a_replacement() {
+        let before: Document = "[dev-dependencies]\nsolana-program-test = {version=\"1.9\"}\n"
+            .parse()
+            .unwrap();
+        let mut after = before.clone();
+        after["dev-dependencies"]["solana-program-test"]
+            .as_inline_table_mut()
+            .unwrap()
+            .fmt();
+        let mut record = InitRecord::default();
+        record.add_manifest_changes(&before, &after);
+        assert!(record.replaced_keys.is_empty());
+    }
+
+    #[test]
+    fn removes_created_dirs_once_empty() {
+        let project = tempfile::tempdir().unwrap();
+        let project_path = fs::canonicalize(project.path()).unwrap();
+        fs::create_dir(project_path.join("tests")).unwrap();
+        let keys_dir = project_path.join("tests").join("fixtures").join("keys");
+
+        let mut record = InitRecord::default();
+        record.create_dir_all(&keys_dir).unwrap();
+        record.create_dir_all(&keys_dir).unwrap();
+        assert_eq!(
+            record.created_dirs,
+            [
+                keys_dir.clone(),
+                project_path.join("tests").join("fixtures")
+            ]
+        );
+
+        fs::write(
+            project_path
+                .join("tests")
+                .join("fixtures")
+                .join("mint.json"),
+            "{}",
+        )
+        .unwrap();
+        assert_eq!(record.remove_created_dirs().unwrap(), [keys_dir]);
+        assert!(project_path.join("tests").join("fixtures").exists());
+
+        fs::remove_file(
+            project_path
+                .join("tests")
+                .join("fixtures")
+                .join("mint.json"),
+        )
+        .unwrap();
+        assert_eq!(
+            record.remove_created_dirs().unwrap(),
+            [project_path.join("tests").join("fixtures")]
+        );
+        assert!(project_path.join("tests").exists());
+    }
+
+    #[test]
+    fn tracks_generated_files() {
+        let project = tempfile::tempdir().unwrap();
+        let path = project.path().join("test.rs");
+        fs::write(&path, "// generated").unwrap();
+
+        let mut record = InitRecord::default();
+        record.add_file(&path, "// generated");
+        record.add_file(&path, "// generated");
+        assert_eq!(record.files.len(), 1);
+        assert!(record.files[0].is_unmodified().unwrap());
+
+        fs::write(&path, "// migrated").unwrap();
+        record.update_file(&path, "// generated", "// migrated");
+        assert!(record.files[0].is_unmodified().unwrap());
+
+        fs::write(&path, "// edited").unwrap();
+        record.update_file(&path, "// generated", "// edited");
+        assert!(!record.files[0].is_unmodified().unwrap());
+    }
+
+    #[test]
+    fn follows_renamed_keys() {
+        let mut record = InitRecord {
+            added_keys: vec![ManifestKey {
+                table: String::from("features"),
+                key: String::from("test-bpf"),
+            }],
+            ..InitRecord::default()
+        };
+        record.rename_key("features", "test-bpf", "test-sbf", false);
+        assert_eq!(record.added_keys[0].key, "test-sbf");
+        record.rename_key("features", "test-sbf", "test-bpf", true);
+        assert!(record.added_keys.is_empty());
+    }
+
+    #[test]
+    fn saves_and_removes_record() {
+        let project = tempfile::tempdir().unwrap();
+        assert!(InitRecord::load(project.path()).unwrap().is_none());
+
+        let record = InitRecord {
+            added_tables: vec![String::from("features")],
+            ..InitRecord::default()
+        };
+        record.save(project.path()).unwrap();
+        let loaded = InitRecord::load(project.path()).unwrap().unwrap();
+        assert_eq!(loaded.added_tables, ["features"]);
+
+        InitRecord::remove(project.path()).unwrap();
+        assert!(!project.path().join(RECORD_DIR).exists());
+    }
+}
@@ -0,0 +1,246 @@
+//! Machine-readable command reports
+//!
+//! With `--output json` the status macros below stay quiet, warnings are
+//! collected, and each command prints a single JSON [`Report`] on stdout
+//! instead. The macros replace the abscissa ones through the prelude.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+use std::process::exit;
+use std::sync::{Mutex, OnceLock};
+
+use crate::error::{Error, ErrorKind};
+use toml_edit::Document;
+
+/// Output format selected with `--output`
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+static OUTPUT_FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub fn set_output_format(format: OutputFormat) {
+    let _ = OUTPUT_FORMAT.set(format);
+}
+
+pub fn is_json() -> bool {
+    OUTPUT_FORMAT.get() == Some(&OutputFormat::Json)
+}
+
+/// Collect a warning for the report.
+pub fn add_warning(warning: String) {
+    WARNINGS.lock().unwrap().push(warning);
+}
+
+/// Print a status message, suppressed in JSON output.
+#[macro_export]
+macro_rules! status_ok {
+    ($($arg:tt)+) => {
+        if !$crate::report::is_json() {
+            abscissa_core::status_ok!($($arg)+);
+        }
+    };
+}
+
+/// Print an informational status message, suppressed in JSON output.
+#[macro_export]
+macro_rules! status_info {
+    ($($arg:tt)+) => {
+        if !$crate::report::is_json() {
+            abscissa_core::status_info!($($arg)+);
+        }
+    };
+}
+
+/// Print a warning, or add it to the report in JSON output.
+#[macro_export]
+macro_rules! status_warn {
+    ($($arg:tt)+) => {
+        if $crate::report::is_json() {
+            $crate::report::add_warning(format!($($arg)+));
+        } else {
+            abscissa_core::status_warn!($($arg)+);
+        }
+    };
+}
+
+/// What a command did with a manifest key or file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeStatus {
+    Added,
+    Updated,
+    Skipped,
+    Written,
+    Planned,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyReport {
+    pub table: String,
+    pub key: String,
+    pub status: ChangeStatus,
+}
+
+impl KeyReport {
+    /// Compare a manifest key before and after a change, `None` when the
+    /// key is in neither document.
+    pub fn compare(
+        before: &Document,
+        after: &Document,
+        table: &str,
+        key: &str,
+    ) -> Option<KeyReport> {
+        let get = |document: &Document| {
+            document
+                .get(table)
+                .and_then(|item| item.get(key))
+                .map(|item| item.to_string().trim().to_string())
+        };
+        let status = match (get(before), get(after)?) {
+            (None, _) => ChangeStatus::Added,
+            (Some(before), after) if before == after => ChangeStatus::Skipped,
+            (Some(_), _) => ChangeStatus::Updated,
+        };
+        Some(KeyReport {
+            table: table.to_string(),
+            key: key.to_string(),
+            status,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub status: ChangeStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub command: &'static str,
+    pub success: bool,
+    /// Command specific fields
+    #[serde(flatten)]
+    pub data: Map<String, Value>,
+    pub warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    pub kind: ErrorKind,
+    pub code: i32,
+    pub message: String,
+}
+
+impl Report {
+    pub fn new(command: &'static str) -> Report {
+        Report {
+            command,
+            success: true,
+            data: Map::new(),
+            warnings: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Set a command specific field.
+    pub fn insert(&mut self, key: &str, value: impl Serialize) {
+        let value = serde_json::to_value(value).unwrap_or(Value::Null);
+        self.data.insert(key.to_string(), value);
+    }
+
+    /// Print the report in JSON output.
+    pub fn finish(mut self) {
+        if is_json() {
+            self.warnings = WARNINGS.lock().unwrap().drain(..).collect();
+            println!("{}", serde_json::to_string_pretty(&self).unwrap());
+        }
+    }
+
+    /// Report the error and exit with its exit code.
+    pub fn fail(mut self, error: &Error) -> ! {
+        if is_json() {
+            self.success = false;
+            self.error = Some(ErrorReport {
+                kind: *error.kind(),
+                code: error.exit_code(),
+                message: error.to_string(),
+            });
+            self.finish();
+        } else {
+            abscissa_core::status_err!("{}", error);
+        }
+        exit(error.exit_code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn compares_manifest_keys() {
+        let before: Document = "[dev-dependencies]\nsolana-program-test = \"1.9\"\nserde = \"1\"\n"
+            .parse()
+            .unwrap();
+        let after: Document = "[features]\ntest-sbf = []\n\n[dev-dependencies]\nsolana-program-test = \"~1.14\"\nserde = \"1\"\n"
+            .parse()
+            .unwrap();
+        let status = |table, key| KeyReport::compare(&before, &after, table, key).map(|r| r.status);
+        assert_eq!(status("features", "test-sbf"), Some(ChangeStatus::Added));
+        assert_eq!(
+            status("dev-dependencies", "solana-program-test"),
+            Some(ChangeStatus::Updated)
+        );
+        assert_eq!(
+            status("dev-dependencies", "serde"),
+            Some(ChangeStatus::Skipped)
+        );
+        assert_eq!(status("dev-dependencies", "missing"), None);
+    }
+
+    #[test]
+    fn serializes_command_fields_at_top_level() {
+        let mut report = Report::new("init");
+        report.insert("dry_run", true);
+        report.insert(
+            "files",
+            [FileReport {
+                path: PathBuf::from("tests/genereted_test.rs"),
+                status: ChangeStatus::Planned,
+            }],
+        );
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            json!({
+                "command": "init",
+                "success": true,
+                "dry_run": true,
+                "files": [{ "path": "tests/genereted_test.rs", "status": "planned" }],
+                "warnings": [],
+            })
+        );
+    }
+
+    #[test]
+    fn serializes_error_kind_and_code() {
+        let error: Error = ErrorKind::TestFileExists.into();
+        let report = ErrorReport {
+            kind: *error.kind(),
+            code: error.exit_code(),
+            message: error.to_string(),
+        };
+        let value = serde_json::to_value(&report).unwrap();
+        assert_eq!(value["kind"], "TestFileExists");
+        assert_eq!(value["code"], 20);
+    }
+}
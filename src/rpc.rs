@@ -0,0 +1,173 @@
+//! Minimal Solana JSON RPC client
+//!
+//! Only the account methods fetch needs are implemented, with the account
+//! data requested in base64.
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::{Error, ErrorKind};
+
+/// Endpoint used when no URL is configured
+pub const DEFAULT_URL: &str = "https://api.mainnet-beta.solana.com";
+
+/// Most accounts `getMultipleAccounts` accepts in one request
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Resolve the monikers accepted by `solana --url`.
+pub fn resolve_url(url: &str) -> String {
+    match url {
+        "m" | "mainnet-beta" => String::from(DEFAULT_URL),
+        "d" | "devnet" => String::from("https://api.devnet.solana.com"),
+        "t" | "testnet" => String::from("https://api.testnet.solana.com"),
+        "l" | "localhost" => String::from("http://localhost:8899"),
+        _ => url.to_string(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Response<T> {
+    result: Option<T>,
+    error: Option<ResponseError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WithContext<T> {
+    value: T,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UiAccount {
+    lamports: u64,
+    data: (String, String),
+    owner: String,
+    executable: bool,
+    rent_epoch: u64,
+}
+
+/// Account as returned by the RPC, with decoded data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: String,
+    pub executable: bool,
+    pub rent_epoch: u64,
+}
+
+impl TryFrom<UiAccount> for Account {
+    type Error = Error;
+
+    fn try_from(account: UiAccount) -> Result<Account, Error> {
+        let (data, encoding) = account.data;
+        if encoding != "base64" {
+            return Err(ErrorKind::Rpc
+                .context(format!("unexpected account data encoding {}", encoding))
+                .into());
+        }
+        let data = base64::decode(data).map_err(|e| ErrorKind::Rpc.context(e))?;
+        Ok(Account {
+            lamports: account.lamports,
+            data,
+            owner: account.owner,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+        })
+    }
+}
+
+/// JSON RPC client of a single endpoint
+pub struct RpcClient {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl RpcClient {
+    pub fn new(url: &str) -> RpcClient {
+        RpcClient {
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    async fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, Error> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let fail = |e: String| -> Error {
+            ErrorKind::Rpc
+                .context(format!("{} {}: {}", method, self.url, e))
+                .into()
+        };
+        let response = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| fail(e.to_string()))?;
+        let bytes = response.bytes().await.map_err(|e| fail(e.to_string()))?;
+        let response: Response<T> =
+            serde_json::from_slice(&bytes).map_err(|e| fail(e.to_string()))?;
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(fail(format!("{} (code {})", error.message, error.code))),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(fail(String::from("response without result"))),
+        }
+    }
+
+    /// Fetch a single account, `None` when it does not exist.
+    pub async fn get_account_info(&self, pubkey: &str) -> Result<Option<Account>, Error> {
+        let response: WithContext<Option<UiAccount>> = self
+            .request("getAccountInfo", json!([pubkey, { "encoding": "base64" }]))
+            .await?;
+        response.value.map(Account::try_from).transpose()
+    }
+
+    /// Fetch accounts in batches, `None` for the ones that do not exist.
+    pub async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[String],
+    ) -> Result<Vec<Option<Account>>, Error> {
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let response: WithContext<Vec<Option<UiAccount>>> = self
+                .request(
+                    "getMultipleAccounts",
+                    json!([chunk, { "encoding": "base64" }]),
+                )
+                .await?;
+            if response.value.len() != chunk.len() {
+                return Err(ErrorKind::Rpc
+                    .context(format!(
+                        "getMultipleAccounts {}: expected {} accounts, got {}",
+                        self.url,
+                        chunk.len(),
+                        response.value.len()
+                    ))
+                    .into());
+            }
+            for account in response.value {
+                accounts.push(account.map(Account::try_from).transpose()?);
+            }
+        }
+        Ok(accounts)
+    }
+}
@@ -0,0 +1,207 @@
+//! Test templates with `{{variable}}` placeholders
+//!
+//! User templates are `<name>.rs` files in the configured template directory
+//! or in `<config dir>/solana-test/templates`. They take precedence over the
+//! built-in templates with the same name.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, ErrorKind};
+use crate::utility;
+
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    pub contents: String,
+    /// File the template was loaded from, `None` for built-in templates
+    pub path: Option<PathBuf>,
+}
+
+/// Directories searched for user templates, in order of precedence.
+pub fn template_dirs(project_template_dir: Option<&Path>) -> Vec<PathBuf> {
+    project_template_dir
+        .map(Path::to_path_buf)
+        .into_iter()
+        .chain(dirs::config_dir().map(|dir| dir.join("solana-test").join("templates")))
+        .collect()
+}
+
+/// Load a template by name from the template directories, falling back to the
+/// built-in templates.
+pub fn load(name: &str, template_dirs: &[PathBuf]) -> Result<Template, Error> {
+    for dir in template_dirs {
+        let path = dir.join(format!("{}.rs", name));
+        if path.exists() {
+            return Ok(Template {
+                name: name.to_string(),
+                contents: fs::read_to_string(&path)?,
+                path: Some(path),
+            });
+        }
+    }
+    builtin(name).ok_or_else(|| {
+        ErrorKind::Template
+            .context(format!("template {} not found", name))
+            .into()
+    })
+}
+
+/// Built-in template by name.
+pub fn builtin(name: &str) -> Option<Template> {
+    utility::BUILTIN_TEMPLATES
+        .iter()
+        .find(|template| template.name == name)
+        .map(|template| Template {
+            name: name.to_string(),
+            contents: template.contents.to_string(),
+            path: None,
+        })
+}
+
+/// Names and paths of the user templates in the template directories. A
+/// template shadows templates with the same name in later directories.
+pub fn user_templates(template_dirs: &[PathBuf]) -> Vec<(String, PathBuf)> {
+    let mut templates: Vec<(String, PathBuf)> = Vec::new();
+    for dir in template_dirs {
+        let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
+            Err(_) => continue,
+        };
+        paths.sort();
+        for path in paths {
+            if path.extension().is_none_or(|ext| ext != "rs") {
+                continue;
+            }
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            if !templates.iter().any(|(existing, _)| *existing == name) {
+                templates.push((name, path));
+            }
+        }
+    }
+    templates
+}
+
+/// Replace `{{name}}` placeholders with their values. Whitespace inside the
+/// braces is ignored and unknown variables are an error.
+pub fn render(template: &str, vars: &BTreeMap<String, String>) -> Result<String, Error> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        let name = rest[start + 2..end].trim();
+        let is_variable = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        rendered.push_str(&rest[..start]);
+        if is_variable {
+            match vars.get(name) {
+                Some(value) => rendered.push_str(value),
+                None => {
+                    return Err(ErrorKind::Template
+                        .context(format!("unknown template variable {}", name))
+                        .into())
+                }
+            }
+        } else {
+            rendered.push_str(&rest[start..end + 2]);
+        }
+        rest = &rest[end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// Parse a `key=value` variable given on the command line.
+pub fn parse_var(var: &str) -> Result<(String, String), Error> {
+    match var.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(ErrorKind::Template
+            .context(format!("variable {} is not in key=value format", var))
+            .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(vars: &[(&str, &str)]) -> BTreeMap<String, String> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn renders_variables() {
+        let rendered = render(
+            "use {{ crate_ident }};\nconst ID: &str = \"{{program_id}}\";\n",
+            &vars(&[("crate_ident", "vault"), ("program_id", "Vault111")]),
+        )
+        .unwrap();
+        assert_eq!(rendered, "use vault;\nconst ID: &str = \"Vault111\";\n");
+    }
+
+    #[test]
+    fn keeps_braces_that_are_not_variables() {
+        let template = "format!(\"{{}}\", x); let s = S {{ a: 1 }}; {{";
+        assert_eq!(render(template, &vars(&[])).unwrap(), template);
+    }
+
+    #[test]
+    fn unknown_variable_is_error() {
+        let e = render("{{program_id}} {{missing}}", &vars(&[("program_id", "x")])).unwrap_err();
+        assert_eq!(*e.kind(), ErrorKind::Template);
+        assert!(e.to_string().contains("unknown template variable missing"));
+    }
+
+    #[test]
+    fn parses_key_value_variables() {
+        assert_eq!(
+            parse_var(" mint =So11111111111111111111111111111111111111112").unwrap(),
+            (
+                String::from("mint"),
+                String::from("So11111111111111111111111111111111111111112")
+            )
+        );
+        assert_eq!(
+            parse_var("empty=").unwrap(),
+            (String::from("empty"), String::new())
+        );
+        assert!(parse_var("no-value").is_err());
+        assert!(parse_var("=value").is_err());
+    }
+
+    #[test]
+    fn user_templates_shadow_builtin_and_later_dirs() {
+        let project = tempfile::tempdir().unwrap();
+        let global = tempfile::tempdir().unwrap();
+        fs::write(project.path().join("anchor.rs"), "// project anchor").unwrap();
+        fs::write(global.path().join("anchor.rs"), "// global anchor").unwrap();
+        fs::write(global.path().join("oracle.rs"), "// oracle").unwrap();
+        fs::write(global.path().join("notes.txt"), "").unwrap();
+        let template_dirs = [project.path().to_path_buf(), global.path().to_path_buf()];
+
+        let anchor = load("anchor", &template_dirs).unwrap();
+        assert_eq!(anchor.contents, "// project anchor");
+        assert_eq!(anchor.path, Some(project.path().join("anchor.rs")));
+        assert!(load("solana", &template_dirs).unwrap().path.is_none());
+        assert_eq!(
+            *load("missing", &template_dirs).unwrap_err().kind(),
+            ErrorKind::Template
+        );
+
+        let names: Vec<String> = user_templates(&template_dirs)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["anchor", "oracle"]);
+    }
+}
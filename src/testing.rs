@@ -0,0 +1,14 @@
+//! Helpers shared by the unit tests
+
+use abscissa_core::terminal::component::Terminal;
+use abscissa_core::terminal::ColorChoice;
+use std::sync::Once;
+
+/// Set up the terminal streams the status macros print to, which the
+/// application does on startup.
+pub fn init_terminal() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        Terminal::new(ColorChoice::Never);
+    });
+}
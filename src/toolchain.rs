@@ -0,0 +1,97 @@
+//! Local Solana and Rust toolchain discovery
+//!
+//! Only `PATH` and files on disk are inspected, so this works offline.
+
+use semver::Version;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Find an executable in `PATH`.
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    let file_name = format!("{}{}", name, env::consts::EXE_SUFFIX);
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.is_file())
+}
+
+/// Run `<executable> --version` and return the first semantic version in
+/// its output, e.g. `1.16.0` for `solana-cli 1.16.0 (src:...)`.
+pub fn executable_version(executable: &Path) -> Option<Version> {
+    let output = Command::new(executable).arg("--version").output().ok()?;
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_version(output: &str) -> Option<Version> {
+    output
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        .map(|word| word.trim_start_matches('v'))
+        .find_map(|word| Version::parse(word).ok())
+}
+
+/// Directory the Solana tools download platform-tools into.
+pub fn solana_cache_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".cache").join("solana"))
+}
+
+/// Installed `platform-tools` and legacy `bpf-tools`, e.g.
+/// `~/.cache/solana/v1.41/platform-tools`.
+pub fn platform_tools() -> Vec<PathBuf> {
+    let cache_dir = match solana_cache_dir() {
+        Some(cache_dir) => cache_dir,
+        None => return Vec::new(),
+    };
+    let mut tools: Vec<PathBuf> = fs::read_dir(cache_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .flat_map(|version| {
+            ["platform-tools", "bpf-tools"]
+                .iter()
+                .map(move |name| version.path().join(name))
+        })
+        .filter(|path| path.is_dir())
+        .collect();
+    tools.sort();
+    tools
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tool_versions() {
+        assert_eq!(
+            parse_version("solana-cli 1.16.0 (src:3b2c0b3c; feat:4033350765, client:SolanaLabs)"),
+            Some(Version::new(1, 16, 0))
+        );
+        assert_eq!(
+            parse_version("rustc 1.75.0 (82e1608df 2023-12-21)"),
+            Some(Version::new(1, 75, 0))
+        );
+        assert_eq!(
+            parse_version("anchor-cli v0.29.0"),
+            Some(Version::new(0, 29, 0))
+        );
+        assert_eq!(parse_version("solana-cli unknown"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_executable_for_version() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let solana = dir.path().join("solana");
+        fs::write(
+            &solana,
+            "#!/bin/sh\necho 'solana-cli 1.14.17 (src:devbuild)'\n",
+        )
+        .unwrap();
+        fs::set_permissions(&solana, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(executable_version(&solana), Some(Version::new(1, 14, 17)));
+        assert_eq!(executable_version(&dir.path().join("missing")), None);
+    }
+}
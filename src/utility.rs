@@ -1,34 +1,44 @@
-pub const SOLANA_TEMPLATE: &str = r#"#![cfg(feature = "test-bpf")]
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+/// Helper loading the keypairs init writes, inserted in the templates as
+/// `{{keypair_helper}}`.
+pub const KEYPAIR_HELPER: &str = r#"/// Keypair saved by `solana-test init` in {{keys_dir}}
+fn keypair(name: &str) -> Keypair {
+    let path = format!("{}/{{keys_dir}}/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+    read_keypair_file(&path).unwrap_or_else(|e| panic!("cannot read {}: {}", path, e))
+}
+"#;
+
+pub const SOLANA_TEMPLATE: &str = r#"#![cfg(feature = "{{test_feature}}")]
 
-use borsh::ser::BorshSerialize;
 use solana_program::instruction::AccountMeta;
 use solana_program::{system_program, sysvar};
+use std::str::FromStr;
 use solana_sdk::{
     account::Account,
     instruction::Instruction,
     pubkey::Pubkey,
-    signature::{Keypair, Signature, Signer},
+    signature::{read_keypair_file, Keypair, Signature, Signer},
     transaction::Transaction,
 };
 use solana_test_framework::*;
 
 #[tokio::test]
 async fn poc() {
-    // @fixme modify
-    let program_id = "";
-    let program_name = "";
+    let program_id = Pubkey::from_str("{{program_id}}").unwrap();
+    let program_name = "{{program_name}}";
 
     let solana_program_id = system_program::ID;
 
     // generate some keys
-    let payer = Keypair::new();
+    let payer = keypair("payer");
 
     // Deploy program
-    // @fixme MODIFY PROCESS INSTRUCTION
     let mut program = ProgramTest::new(
         program_name,
         program_id,
-        processor!(MODIFY PROCESS INSTRUCTION),
+        {{processor}},
     );
 
     program.add_account(
@@ -39,7 +49,7 @@ async fn poc() {
         },
     );
 
-    let mut program_context = program.start_with_context().await;
+{{accounts}}    let mut program_context = program.start_with_context().await;
     let mut recent_blockhash = program_context.last_blockhash.clone();
 
     // Get PDA
@@ -56,7 +66,7 @@ async fn poc() {
     // @fixme modify INSTRUCTION
     let mut ix = Instruction {
         program_id,
-        data: INSTRUCTION {}.try_to_vec().unwrap(),
+        data: borsh::to_vec(&INSTRUCTION {}).unwrap(),
         accounts: acc,
     };
 
@@ -82,19 +92,21 @@ async fn poc() {
         TYPE::try_deserialize(&mut acc.data.as_ref()).unwrap();
     println!("{:?}", pda_state);
 }
-"#;
-pub const ANCHOR_TEMPLATE: &str = r#"#![cfg(feature = "test-bpf")]
+
+{{keypair_helper}}"#;
+pub const ANCHOR_TEMPLATE: &str = r#"#![cfg(feature = "{{test_feature}}")]
 
 use anchor_client::Program;
 use anchor_lang::AnchorDeserialize;
 use anchor_lang::{solana_program, system_program};
+use std::str::FromStr;
 use {
     anchor_client::solana_sdk::{
         account::Account,
         instruction::Instruction,
         program_pack::Pack,
         pubkey::Pubkey,
-        signature::{Keypair, Signer},
+        signature::{read_keypair_file, Keypair, Signer},
         sysvar::clock::Clock,
         sysvar::{clock, rent::Rent},
         transaction::Transaction,
@@ -105,22 +117,19 @@ use {
 
 #[tokio::test]
 async fn poc() {
-    // @fixme modify
-    let program_id = "";
-    let program_name = "";
+    let program_id = Pubkey::from_str("{{program_id}}").unwrap();
+    let program_name = "{{program_name}}";
 
     let solana_program_id = system_program::ID;
 
     // generate some keys
-    let payer = Keypair::new();
+    let payer = keypair("payer");
 
     // Deploy program
-    // @fixme MODIFY PROCESS INSTRUCTION
     let mut program = ProgramTest::new(
         program_name,
         program_id,
-        // the entrypoint function is generated by the #[program] macro
-        processor!(MODIFY PROCESS INSTRUCTION),
+        {{processor}},
     );
 
     program.add_account(
@@ -131,7 +140,7 @@ async fn poc() {
         },
     );
 
-    let mut program_context = program.start_with_context().await;
+{{accounts}}    let mut program_context = program.start_with_context().await;
     let mut recent_blockhash = program_context.last_blockhash.clone();
 
     // Get PDA
@@ -176,4 +185,624 @@ async fn poc() {
         .unwrap();
     println!("{:?}", pda_state);
 }
-"#;
+
+{{keypair_helper}}"#;
+pub const ANCHOR_IDL_TEMPLATE: &str = r#"#![cfg(feature = "{{test_feature}}")]
+
+use std::str::FromStr;
+use {
+    anchor_client::solana_sdk::{
+        account::Account,
+        instruction::Instruction,
+        pubkey::Pubkey,
+        signature::{read_keypair_file, Keypair, Signer},
+        transaction::Transaction,
+    },
+    anchor_lang::{system_program, InstructionData, ToAccountMetas},
+    solana_test_framework::*,
+    {{crate_ident}}::{accounts, instruction},
+};
+
+fn program_test(payer: &Keypair) -> (ProgramTest, Pubkey) {
+    let program_id = Pubkey::from_str("{{program_id}}").unwrap();
+    let program_name = "{{program_name}}";
+
+    // Deploy program
+    let mut program = ProgramTest::new(
+        program_name,
+        program_id,
+        {{processor}},
+    );
+
+    program.add_account(
+        payer.pubkey(),
+        Account {
+            lamports: 1_000_000_000_000_000,
+            ..Account::default()
+        },
+    );
+
+{{accounts}}    (program, program_id)
+}
+
+{{keypair_helper}}
+{{instruction_tests}}"#;
+
+pub const SOLANA_INSTRUCTIONS_TEMPLATE: &str = r#"#![cfg(feature = "{{test_feature}}")]
+
+use solana_program::instruction::AccountMeta;
+use solana_sdk::{
+    account::Account,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+    transaction::Transaction,
+};
+use solana_test_framework::*;
+use std::str::FromStr;
+
+fn program_test(payer: &Keypair) -> (ProgramTest, Pubkey) {
+    let program_id = Pubkey::from_str("{{program_id}}").unwrap();
+    let program_name = "{{program_name}}";
+
+    // Deploy program
+    let mut program = ProgramTest::new(
+        program_name,
+        program_id,
+        {{processor}},
+    );
+
+    program.add_account(
+        payer.pubkey(),
+        Account {
+            lamports: 1_000_000_000_000_000,
+            ..Account::default()
+        },
+    );
+
+{{accounts}}    (program, program_id)
+}
+
+{{keypair_helper}}
+{{instruction_tests}}"#;
+
+pub const SPL_TOKEN_TEMPLATE: &str = r#"#![cfg(feature = "{{test_feature}}")]
+
+use solana_program::{program_pack::Pack, system_instruction};
+use solana_sdk::{
+    account::Account,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+    transaction::Transaction,
+};
+use solana_test_framework::*;
+use std::str::FromStr;
+
+#[tokio::test]
+async fn poc() {
+    let program_id = Pubkey::from_str("{{program_id}}").unwrap();
+    let program_name = "{{program_name}}";
+
+    // generate some keys
+    let payer = keypair("payer");
+    let mint = keypair("mint");
+    let mint_authority = keypair("mint_authority");
+    let token_account = keypair("token_account");
+
+    // Deploy program - the SPL Token program is loaded by ProgramTest
+    let mut program = ProgramTest::new(
+        program_name,
+        program_id,
+        {{processor}},
+    );
+
+    program.add_account(
+        payer.pubkey(),
+        Account {
+            lamports: 1_000_000_000_000_000,
+            ..Account::default()
+        },
+    );
+
+{{accounts}}    let mut program_context = program.start_with_context().await;
+    let rent = program_context.banks_client.get_rent().await.unwrap();
+    let recent_blockhash = program_context.last_blockhash;
+
+    // Create a mint and a token account owned by the payer
+    let setup = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &mint_authority.pubkey(),
+            None,
+            6,
+        )
+        .unwrap(),
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &token_account.pubkey(),
+            rent.minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account(
+            &spl_token::id(),
+            &token_account.pubkey(),
+            &mint.pubkey(),
+            &payer.pubkey(),
+        )
+        .unwrap(),
+        spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &token_account.pubkey(),
+            &mint_authority.pubkey(),
+            &[],
+            1_000_000,
+        )
+        .unwrap(),
+    ];
+    let mut tx = Transaction::new_with_payer(&setup, Some(&payer.pubkey()));
+    tx.sign(
+        &[&payer, &mint, &token_account, &mint_authority],
+        recent_blockhash,
+    );
+    program_context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap();
+
+    let token = program_context
+        .banks_client
+        .get_account(token_account.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    let token = spl_token::state::Account::unpack(&token.data).unwrap();
+    assert_eq!(token.amount, 1_000_000);
+
+    // @fixme call the tested program with the mint and token account
+}
+
+{{keypair_helper}}"#;
+pub const CPI_TEMPLATE: &str = r#"#![cfg(feature = "{{test_feature}}")]
+
+use solana_program::instruction::AccountMeta;
+use solana_sdk::{
+    account::Account,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+    transaction::Transaction,
+};
+use solana_test_framework::*;
+use std::str::FromStr;
+
+#[tokio::test]
+async fn poc() {
+    let program_id = Pubkey::from_str("{{program_id}}").unwrap();
+    let program_name = "{{program_name}}";
+
+    // @fixme set the program invoked through CPI
+    let callee_program_id = Pubkey::new_unique();
+    let callee_program_name = "callee_program";
+
+    // generate some keys
+    let payer = keypair("payer");
+
+    // Deploy both programs - the callee is loaded from its built .so
+    let mut program = ProgramTest::new(
+        program_name,
+        program_id,
+        {{processor}},
+    );
+    program.add_program(callee_program_name, callee_program_id, None);
+
+    program.add_account(
+        payer.pubkey(),
+        Account {
+            lamports: 1_000_000_000_000_000,
+            ..Account::default()
+        },
+    );
+
+{{accounts}}    let mut program_context = program.start_with_context().await;
+    let recent_blockhash = program_context.last_blockhash;
+
+    // The callee has to be passed to the caller to be invoked
+    let acc = vec![
+        AccountMeta::new(payer.pubkey(), true),
+        AccountMeta::new_readonly(callee_program_id, false),
+    ];
+
+    // @fixme modify INSTRUCTION
+    let ix = Instruction {
+        program_id,
+        data: borsh::to_vec(&INSTRUCTION {}).unwrap(),
+        accounts: acc,
+    };
+
+    let mut tx = Transaction::new_with_payer(&[ix], Some(&payer.pubkey()));
+    tx.sign(&[&payer], recent_blockhash);
+    program_context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap();
+}
+
+{{keypair_helper}}"#;
+pub const UPGRADEABLE_TEMPLATE: &str = r#"#![cfg(feature = "{{test_feature}}")]
+
+use solana_program::{
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    rent::Rent,
+};
+use solana_sdk::{
+    account::Account,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+};
+use solana_test_framework::*;
+use std::str::FromStr;
+
+#[tokio::test]
+async fn poc() {
+    let program_id = Pubkey::from_str("{{program_id}}").unwrap();
+    let program_name = "{{program_name}}";
+
+    // generate some keys
+    let payer = keypair("payer");
+    let upgrade_authority = keypair("upgrade_authority");
+
+    // Deploy the program with the upgradeable loader, as on mainnet, so the
+    // ProgramData account and upgrade authority can be inspected
+    let (programdata_address, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    let program_data = std::fs::read(format!("target/deploy/{}.so", program_name))
+        .expect("Build the program with cargo build-bpf first");
+
+    let program_account = bincode::serialize(&UpgradeableLoaderState::Program {
+        programdata_address,
+    })
+    .unwrap();
+    let mut programdata_account = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(upgrade_authority.pubkey()),
+    })
+    .unwrap();
+    programdata_account.resize(UpgradeableLoaderState::programdata_data_offset().unwrap(), 0);
+    programdata_account.extend_from_slice(&program_data);
+
+    let mut program = ProgramTest::default();
+    program.add_account(
+        program_id,
+        Account {
+            lamports: Rent::default().minimum_balance(program_account.len()),
+            data: program_account,
+            owner: bpf_loader_upgradeable::id(),
+            executable: true,
+            rent_epoch: 0,
+        },
+    );
+    program.add_account(
+        programdata_address,
+        Account {
+            lamports: Rent::default().minimum_balance(programdata_account.len()),
+            data: programdata_account,
+            owner: bpf_loader_upgradeable::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+
+    program.add_account(
+        payer.pubkey(),
+        Account {
+            lamports: 1_000_000_000_000_000,
+            ..Account::default()
+        },
+    );
+
+{{accounts}}    let mut program_context = program.start_with_context().await;
+
+    let programdata = program_context
+        .banks_client
+        .get_account(programdata_address)
+        .await
+        .unwrap()
+        .unwrap();
+    println!("{:?}", bincode::deserialize::<UpgradeableLoaderState>(&programdata.data));
+
+    // @fixme send instructions to the program
+}
+
+{{keypair_helper}}"#;
+pub const CLOCK_TEMPLATE: &str = r#"#![cfg(feature = "{{test_feature}}")]
+
+use solana_program::sysvar::clock::Clock;
+use solana_sdk::{
+    account::Account,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+};
+use solana_test_framework::*;
+use std::str::FromStr;
+
+#[tokio::test]
+async fn poc() {
+    let program_id = Pubkey::from_str("{{program_id}}").unwrap();
+    let program_name = "{{program_name}}";
+
+    // generate some keys
+    let payer = keypair("payer");
+
+    // Deploy program
+    let mut program = ProgramTest::new(
+        program_name,
+        program_id,
+        {{processor}},
+    );
+
+    program.add_account(
+        payer.pubkey(),
+        Account {
+            lamports: 1_000_000_000_000_000,
+            ..Account::default()
+        },
+    );
+
+{{accounts}}    let mut program_context = program.start_with_context().await;
+
+    // Move forward in slots, e.g. to pass slot based cooldowns
+    program_context.warp_to_slot(1_000).unwrap();
+
+    // Move the clock forward by a day, e.g. to pass timestamp based locks
+    let mut clock: Clock = program_context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp += 24 * 60 * 60;
+    program_context.set_sysvar(&clock);
+
+    let clock: Clock = program_context.banks_client.get_sysvar().await.unwrap();
+    println!("{:?}", clock);
+
+    // @fixme send instructions to the program
+}
+
+{{keypair_helper}}"#;
+
+/// Built-in template
+pub struct BuiltinTemplate {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub contents: &'static str,
+}
+
+/// Catalogue of built-in templates, selected with `init --template <name>`.
+pub const BUILTIN_TEMPLATES: &[BuiltinTemplate] = &[
+    BuiltinTemplate {
+        name: "solana",
+        description: "Basic POC for native programs",
+        contents: SOLANA_TEMPLATE,
+    },
+    BuiltinTemplate {
+        name: "anchor",
+        description: "Basic POC for Anchor programs",
+        contents: ANCHOR_TEMPLATE,
+    },
+    BuiltinTemplate {
+        name: "anchor-idl",
+        description: "One test per instruction of the Anchor IDL",
+        contents: ANCHOR_IDL_TEMPLATE,
+    },
+    BuiltinTemplate {
+        name: "solana-instructions",
+        description: "One test per variant of the native instruction enum",
+        contents: SOLANA_INSTRUCTIONS_TEMPLATE,
+    },
+    BuiltinTemplate {
+        name: "spl-token",
+        description: "Mint and token account set up for the payer",
+        contents: SPL_TOKEN_TEMPLATE,
+    },
+    BuiltinTemplate {
+        name: "cpi",
+        description: "Tested program calling a second program through CPI",
+        contents: CPI_TEMPLATE,
+    },
+    BuiltinTemplate {
+        name: "upgradeable",
+        description: "Program deployed with the upgradeable loader and its ProgramData",
+        contents: UPGRADEABLE_TEMPLATE,
+    },
+    BuiltinTemplate {
+        name: "clock",
+        description: "Slot warping and Clock sysvar manipulation",
+        contents: CLOCK_TEMPLATE,
+    },
+];
+
+/// Keywords of Rust 2015 to 2024, including the reserved ones
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Convert a name to a `snake_case` identifier usable in generated code.
+/// Keywords become raw identifiers such as `r#move`, except those that
+/// cannot be raw, which get a trailing underscore.
+pub fn to_ident(name: &str) -> String {
+    let ident = to_snake_case(name);
+    if !RUST_KEYWORDS.contains(&ident.as_str()) {
+        return ident;
+    }
+    match ident.as_str() {
+        "crate" | "self" | "Self" | "super" => format!("{}_", ident),
+        _ => format!("r#{}", ident),
+    }
+}
+
+/// Convert `camelCase` or `PascalCase` identifiers to `snake_case`.
+pub fn to_snake_case(name: &str) -> String {
+    let mut out = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_uppercase() {
+            if prev_lower {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+            prev_lower = false;
+        } else {
+            prev_lower = c.is_lowercase() || c.is_ascii_digit();
+            out.push(if c == '-' { '_' } else { c });
+        }
+    }
+    out
+}
+
+/// Convert `snake_case` or `camelCase` identifiers to `PascalCase`.
+pub fn to_pascal_case(name: &str) -> String {
+    name.split(['_', '-'])
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Whether `key` is a base58 encoded 32 byte public key.
+pub fn is_pubkey(key: &str) -> bool {
+    bs58::decode(key)
+        .into_vec()
+        .is_ok_and(|bytes| bytes.len() == 32)
+}
+
+/// Path of `path` relative to the directory `base`, both absolute or both
+/// relative to the same directory, e.g. `../fixtures/mint.bin`.
+pub fn relative_path(base: &Path, path: &Path) -> PathBuf {
+    let base: Vec<Component<'_>> = base.components().collect();
+    let path: Vec<Component<'_>> = path.components().collect();
+    let common = base.iter().zip(&path).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for component in &path[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+/// `path` joined with the current directory when it is relative.
+pub fn absolute_path(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir()
+            .map(|dir| dir.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template;
+    use std::collections::BTreeMap;
+
+    fn template_vars() -> BTreeMap<String, String> {
+        let mut vars: BTreeMap<String, String> = [
+            ("test_feature", "test-sbf"),
+            ("program_id", "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS"),
+            ("program_name", "vault"),
+            ("crate_ident", "vault"),
+            ("accounts", ""),
+            ("keys_dir", "tests/fixtures/keys"),
+            ("processor", "None"),
+            ("instruction_tests", ""),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+        let keypair_helper = template::render(KEYPAIR_HELPER, &vars).unwrap();
+        vars.insert(String::from("keypair_helper"), keypair_helper);
+        vars
+    }
+
+    #[test]
+    fn builtin_templates_share_keypair_helper() {
+        let vars = template_vars();
+        for builtin in BUILTIN_TEMPLATES {
+            assert_eq!(
+                builtin.contents.matches("{{keypair_helper}}").count(),
+                1,
+                "{}",
+                builtin.name
+            );
+            let rendered = template::render(builtin.contents, &vars).unwrap();
+            assert_eq!(
+                rendered
+                    .matches("fn keypair(name: &str) -> Keypair")
+                    .count(),
+                1,
+                "{}",
+                builtin.name
+            );
+            assert!(rendered.contains(
+                "let path = format!(\"{}/tests/fixtures/keys/{}.json\", env!(\"CARGO_MANIFEST_DIR\"), name);"
+            ));
+        }
+    }
+
+    #[test]
+    fn converts_identifiers() {
+        assert_eq!(to_snake_case("initializeVault"), "initialize_vault");
+        assert_eq!(to_snake_case("my-prog"), "my_prog");
+        assert_eq!(to_snake_case("TransferV2"), "transfer_v2");
+        assert_eq!(to_pascal_case("initialize_vault"), "InitializeVault");
+        assert_eq!(to_pascal_case("my-prog"), "MyProg");
+    }
+
+    #[test]
+    fn escapes_keywords() {
+        assert_eq!(to_ident("systemProgram"), "system_program");
+        assert_eq!(to_ident("move"), "r#move");
+        assert_eq!(to_ident("type"), "r#type");
+        assert_eq!(to_ident("self"), "self_");
+        assert_eq!(to_ident("crate"), "crate_");
+    }
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(
+            relative_path(Path::new("/p/tests"), Path::new("/p/fixtures/mint.bin")),
+            Path::new("../fixtures/mint.bin")
+        );
+        assert_eq!(
+            relative_path(Path::new("/p"), Path::new("/p/tests/fixtures/keys")),
+            Path::new("tests/fixtures/keys")
+        );
+    }
+
+    #[test]
+    fn recognizes_pubkeys() {
+        assert!(is_pubkey("11111111111111111111111111111111"));
+        assert!(!is_pubkey("1111"));
+        assert!(!is_pubkey("not base58 0OIl"));
+    }
+}
@@ -0,0 +1,243 @@
+//! Cargo workspace discovery
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::Document;
+
+use crate::error::{Error, ErrorKind};
+use crate::project_toml::{check_if_is_anchor, read_manifest};
+
+/// Kind of a workspace member
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberKind {
+    Anchor,
+    Native,
+    NonProgram,
+}
+
+impl MemberKind {
+    pub fn is_program(self) -> bool {
+        self != MemberKind::NonProgram
+    }
+}
+
+impl fmt::Display for MemberKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemberKind::Anchor => write!(f, "anchor"),
+            MemberKind::Native => write!(f, "native"),
+            MemberKind::NonProgram => write!(f, "non-program"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub name: String,
+    pub path: PathBuf,
+    pub kind: MemberKind,
+}
+
+/// Returns true for virtual manifests, i.e. `[workspace]` without `[package]`.
+pub fn is_workspace(document: &Document) -> bool {
+    document.get("workspace").is_some() && document.get("package").is_none()
+}
+
+/// Expand `[workspace].members` globs, skipping `[workspace].exclude`, and
+/// classify each member.
+pub fn members(root: &Path, document: &Document) -> Result<Vec<Member>, Error> {
+    let patterns = |key: &str| -> Vec<String> {
+        document["workspace"]
+            .get(key)
+            .and_then(|value| value.as_array())
+            .map(|array| {
+                array
+                    .iter()
+                    .filter_map(|value| value.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let excluded: Vec<PathBuf> = patterns("exclude")
+        .iter()
+        .map(|pattern| root.join(pattern))
+        .collect();
+
+    let mut members = Vec::new();
+    for pattern in patterns("members") {
+        let full_pattern = root.join(&pattern);
+        let paths = glob::glob(&full_pattern.to_string_lossy())
+            .map_err(|e| ErrorKind::IncorrectCargoFile.context(e))?;
+        for path in paths.flatten() {
+            if excluded.contains(&path) || !path.join("Cargo.toml").exists() {
+                continue;
+            }
+            members.push(read_member(path)?);
+        }
+    }
+    members.sort_by(|a, b| a.path.cmp(&b.path));
+    members.dedup_by(|a, b| a.path == b.path);
+    Ok(members)
+}
+
+fn read_member(path: PathBuf) -> Result<Member, Error> {
+    let document = read_manifest(&path.join("Cargo.toml"))?;
+    let name = document
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str())
+        .map(String::from)
+        .unwrap_or_else(|| path.file_name().unwrap().to_string_lossy().into_owned());
+
+    let kind = if is_anchor_program(&path, &document) {
+        MemberKind::Anchor
+    } else if is_native_program(&path, &document) {
+        MemberKind::Native
+    } else {
+        MemberKind::NonProgram
+    };
+    Ok(Member { name, path, kind })
+}
+
+/// Anchor programs depend on `anchor-lang` and build a `cdylib` or declare a
+/// `#[program]` module - clients and SDKs only use the types.
+fn is_anchor_program(path: &Path, document: &Document) -> bool {
+    check_if_is_anchor(document) && (is_cdylib(document) || lib_rs_contains(path, "#[program]"))
+}
+
+/// Native programs depend on `solana-program` and build a `cdylib` or declare
+/// an entrypoint - clients and libraries only have the dependency.
+fn is_native_program(path: &Path, document: &Document) -> bool {
+    let has_solana_program = document
+        .get("dependencies")
+        .and_then(|deps| deps.get("solana-program"))
+        .is_some();
+    has_solana_program && (is_cdylib(document) || lib_rs_contains(path, "entrypoint!"))
+}
+
+fn is_cdylib(document: &Document) -> bool {
+    document
+        .get("lib")
+        .and_then(|lib| lib.get("crate-type"))
+        .and_then(|crate_type| crate_type.as_array())
+        .is_some_and(|crate_type| crate_type.iter().any(|t| t.as_str() == Some("cdylib")))
+}
+
+fn lib_rs_contains(path: &Path, pattern: &str) -> bool {
+    fs::read_to_string(path.join("src").join("lib.rs")).is_ok_and(|lib_rs| lib_rs.contains(pattern))
+}
+
+/// Members of the workspace at `path`, or `None` when `path` holds a package
+/// manifest.
+pub fn load_workspace(path: &Path) -> Result<Option<Vec<Member>>, Error> {
+    let document = read_manifest(&path.join("Cargo.toml"))?;
+    if !is_workspace(&document) {
+        return Ok(None);
+    }
+    members(path, &document).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn classifies_workspace_members() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        write(
+            &root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"programs/*\", \"client\"]\nexclude = [\"programs/old\"]\n",
+        );
+        write(
+            &root.join("programs/vault/Cargo.toml"),
+            "[package]\nname = \"vault\"\n\n[lib]\ncrate-type = [\"cdylib\", \"lib\"]\n\n[dependencies]\nanchor-lang = \"0.29\"\n",
+        );
+        write(
+            &root.join("programs/token/Cargo.toml"),
+            "[package]\nname = \"token\"\n\n[lib]\ncrate-type = [\"cdylib\", \"lib\"]\n\n[dependencies]\nsolana-program = \"1.17\"\n",
+        );
+        write(
+            &root.join("programs/old/Cargo.toml"),
+            "[package]\nname = \"old\"\n",
+        );
+        fs::create_dir_all(root.join("programs/not-a-crate")).unwrap();
+        write(
+            &root.join("client/Cargo.toml"),
+            "[package]\nname = \"client\"\n\n[dependencies]\nsolana-program = \"1.17\"\n",
+        );
+        write(&root.join("client/src/lib.rs"), "pub fn client() {}\n");
+
+        let members = load_workspace(root).unwrap().unwrap();
+        let members: Vec<(&str, MemberKind)> = members
+            .iter()
+            .map(|member| (member.name.as_str(), member.kind))
+            .collect();
+        assert_eq!(
+            members,
+            [
+                ("client", MemberKind::NonProgram),
+                ("token", MemberKind::Native),
+                ("vault", MemberKind::Anchor),
+            ]
+        );
+    }
+
+    #[test]
+    fn entrypoint_makes_native_program() {
+        let root = tempfile::tempdir().unwrap();
+        let member = root.path().join("program");
+        write(
+            &member.join("Cargo.toml"),
+            "[package]\nname = \"program\"\n\n[dependencies]\nsolana-program = \"1.17\"\n",
+        );
+        write(
+            &member.join("src/lib.rs"),
+            "entrypoint!(process_instruction);\n",
+        );
+        assert_eq!(read_member(member).unwrap().kind, MemberKind::Native);
+    }
+
+    #[test]
+    fn anchor_program_needs_cdylib_or_program_module() {
+        let root = tempfile::tempdir().unwrap();
+        let manifest = |name: &str| {
+            format!(
+                "[package]\nname = \"{}\"\n\n[dependencies]\nanchor-lang = \"0.29\"\n",
+                name
+            )
+        };
+
+        let client = root.path().join("client");
+        write(&client.join("Cargo.toml"), &manifest("client"));
+        write(
+            &client.join("src/lib.rs"),
+            "use anchor_lang::prelude::*;\n\npub fn deposit(vault: Pubkey) {}\n",
+        );
+        assert_eq!(read_member(client).unwrap().kind, MemberKind::NonProgram);
+
+        let program = root.path().join("program");
+        write(&program.join("Cargo.toml"), &manifest("program"));
+        write(
+            &program.join("src/lib.rs"),
+            "use anchor_lang::prelude::*;\n\n#[program]\npub mod vault {}\n",
+        );
+        assert_eq!(read_member(program).unwrap().kind, MemberKind::Anchor);
+    }
+
+    #[test]
+    fn package_manifest_is_not_workspace() {
+        let root = tempfile::tempdir().unwrap();
+        write(
+            &root.path().join("Cargo.toml"),
+            "[package]\nname = \"vault\"\n\n[workspace]\n",
+        );
+        assert!(load_workspace(root.path()).unwrap().is_none());
+    }
+}